@@ -0,0 +1,160 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use time::Duration;
+use crate::domain::{AuctionType, FeeSchedule, FeeTier, UnresolvedAuctionType};
+use crate::money::{Amount, Currency};
+
+pub const DEFAULT_BIND_ADDR: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 8080;
+
+// Parse the BIND_ADDR environment value, falling back to the loopback address when missing
+pub fn parse_bind_addr(value: Option<&str>) -> Result<String, String> {
+    match value {
+        None => Ok(DEFAULT_BIND_ADDR.to_string()),
+        Some(addr) => addr.trim().parse::<IpAddr>()
+            .map(|ip| ip.to_string())
+            .map_err(|_| format!("Invalid bind address: {}", addr)),
+    }
+}
+
+// Parse the PORT environment value, falling back to the default port when missing
+pub fn parse_port(value: Option<&str>) -> Result<u16, String> {
+    match value {
+        None => Ok(DEFAULT_PORT),
+        Some(port) => port.trim().parse::<u16>()
+            .map_err(|_| format!("Invalid port: {}", port)),
+    }
+}
+
+// Site wide rules for the web api
+#[derive(Debug, Clone)]
+pub struct SiteConfig {
+    /// Shortest time between start and expiry accepted for new auctions
+    pub min_auction_duration: Duration,
+    /// Number of bids a user can place in a burst
+    pub bid_burst: u32,
+    /// Time until a user can place another bid once the burst is used up
+    pub bid_refill_interval: std::time::Duration,
+    /// Longest auction title in characters
+    pub max_title_length: usize,
+    /// Currency of new auctions that do not name one
+    pub default_currency: Currency,
+    /// Type of new auctions that do not name one, None for English without reserve price or min raise.
+    /// Bare amounts are in the currency of each new auction.
+    pub default_auction_type: Option<UnresolvedAuctionType>,
+    /// Commission taken of the winning price of sold auctions
+    pub fee_schedule: FeeSchedule,
+    /// Most auctions a seller can have that have not ended, None for no limit
+    pub max_active_auctions_per_seller: Option<usize>,
+    /// Currencies auctions and bids may be in, None allows every currency
+    pub allowed_currencies: Option<Vec<Currency>>,
+    /// Largest amount accepted in bids and auction options per currency, currencies not listed are unbounded
+    pub max_amounts: Vec<Amount>,
+    /// Largest min raise of English auctions in basis points of the reserve price, None for no limit
+    pub max_min_raise_bps: Option<u32>,
+    /// Reject bids from the address the auction was created from, to deter shill bidding
+    pub block_shill_bids: bool,
+    /// Time between the sweeps that report auctions that have started or ended
+    pub sweep_interval: std::time::Duration,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        SiteConfig {
+            min_auction_duration: Duration::ZERO,
+            bid_burst: 10,
+            bid_refill_interval: std::time::Duration::from_secs(1),
+            max_title_length: 200,
+            default_currency: Currency::VAC,
+            default_auction_type: None,
+            fee_schedule: FeeSchedule::default(),
+            max_active_auctions_per_seller: None,
+            allowed_currencies: None,
+            max_amounts: Vec::new(),
+            max_min_raise_bps: None,
+            block_shill_bids: false,
+            sweep_interval: std::time::Duration::from_secs(1),
+        }
+    }
+}
+
+impl SiteConfig {
+    /// The default config with the settings that are set in the environment, read through `var` so that
+    /// tests need not touch the process environment, e.g. `SiteConfig::from_env(|name| std::env::var(name).ok())`.
+    ///
+    /// Durations are in seconds except BID_REFILL_INTERVAL_MS and SWEEP_INTERVAL_MS, lists are separated by commas,
+    /// e.g. ALLOWED_CURRENCIES=SEK,DKK, MAX_AMOUNTS=SEK1000000 and FEE_TIERS=0:500,100000:250 (from:basis points).
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<SiteConfig, String> {
+        let mut config = SiteConfig::default();
+        if let Some(value) = var("MIN_AUCTION_DURATION_SECS") {
+            config.min_auction_duration = Duration::seconds(parse_setting("MIN_AUCTION_DURATION_SECS", &value)?);
+        }
+        if let Some(value) = var("BID_BURST") {
+            config.bid_burst = parse_setting("BID_BURST", &value)?;
+        }
+        if let Some(value) = var("BID_REFILL_INTERVAL_MS") {
+            config.bid_refill_interval = std::time::Duration::from_millis(parse_setting("BID_REFILL_INTERVAL_MS", &value)?);
+        }
+        if let Some(value) = var("MAX_TITLE_LENGTH") {
+            config.max_title_length = parse_setting("MAX_TITLE_LENGTH", &value)?;
+        }
+        if let Some(value) = var("DEFAULT_CURRENCY") {
+            config.default_currency = parse_setting("DEFAULT_CURRENCY", &value)?;
+        }
+        if let Some(value) = var("DEFAULT_AUCTION_TYPE") {
+            // Bare amounts are in the currency of the auction, as within an auction, so the type is only checked here
+            let value = value.trim();
+            AuctionType::from_str(value).map_err(|e| format!("Invalid DEFAULT_AUCTION_TYPE: {}", e))?;
+            config.default_auction_type = Some(UnresolvedAuctionType::Text(value.to_string()));
+        }
+        if let Some(value) = var("FEE_TIERS") {
+            let tiers = parse_list(&value, parse_fee_tier)?;
+            // Listed from the lowest band up, so that a mistyped boundary is not silently reordered
+            if tiers.windows(2).any(|pair| pair[0].from >= pair[1].from) {
+                return Err(format!("Invalid FEE_TIERS: {}, the tiers must be in increasing order", value));
+            }
+            config.fee_schedule = FeeSchedule::tiered(tiers)
+                .map_err(|e| format!("Invalid FEE_TIERS: {}", e))?;
+        }
+        if let Some(value) = var("MAX_ACTIVE_AUCTIONS_PER_SELLER") {
+            config.max_active_auctions_per_seller = Some(parse_setting("MAX_ACTIVE_AUCTIONS_PER_SELLER", &value)?);
+        }
+        if let Some(value) = var("ALLOWED_CURRENCIES") {
+            config.allowed_currencies = Some(parse_list(&value, |item| parse_setting("ALLOWED_CURRENCIES", item))?);
+        }
+        if let Some(value) = var("MAX_AMOUNTS") {
+            config.max_amounts = parse_list(&value, |item| parse_setting("MAX_AMOUNTS", item))?;
+        }
+        if let Some(value) = var("MAX_MIN_RAISE_BPS") {
+            config.max_min_raise_bps = Some(parse_setting("MAX_MIN_RAISE_BPS", &value)?);
+        }
+        if let Some(value) = var("BLOCK_SHILL_BIDS") {
+            config.block_shill_bids = parse_setting("BLOCK_SHILL_BIDS", &value)?;
+        }
+        if let Some(value) = var("SWEEP_INTERVAL_MS") {
+            config.sweep_interval = std::time::Duration::from_millis(parse_setting("SWEEP_INTERVAL_MS", &value)?);
+        }
+        Ok(config)
+    }
+}
+
+fn parse_setting<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.trim().parse::<T>().map_err(|_| format!("Invalid {}: {}", name, value))
+}
+
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(parse).collect()
+}
+
+// A tier of the fee schedule as the start of the band and its rate, e.g. 100000:250
+fn parse_fee_tier(value: &str) -> Result<FeeTier, String> {
+    let (from, rate_bps) = value.split_once(':').ok_or_else(|| format!("Invalid FEE_TIERS: {}", value))?;
+    let rate_bps = parse_setting("FEE_TIERS", rate_bps)?;
+    if rate_bps > 10_000 {
+        return Err(format!("Invalid FEE_TIERS: {}, a rate is at most 10000 basis points", value));
+    }
+    Ok(FeeTier {
+        from: parse_setting("FEE_TIERS", from)?,
+        rate_bps,
+    })
+}
@@ -1,18 +1,21 @@
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 use core::fmt;
+use std::collections::HashSet;
 use std::str::FromStr;
-use crate::money::Currency;
+use crate::money::{Amount, AmountValue, Currency};
 use super::bids::Bid;
-use super::core::{AuctionId, Errors, User};
+use super::core::{AuctionId, Errors, User, UserId};
 use super::single_sealed_bid::Options as SBOptions;
-use super::timed_ascending::Options as TAOptions;
-use super::states::State;
+use super::timed_ascending::{FinalRound, Options as TAOptions};
+use super::reverse::Options as ReverseOptions;
+use super::states::{AuctionOutcome, State};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AuctionType {
     TimedAscending(TAOptions),
     SingleSealedBid(SBOptions),
+    Reverse(ReverseOptions),
 }
 
 impl Serialize for AuctionType {
@@ -22,8 +25,147 @@ impl Serialize for AuctionType {
 }
 impl<'de> Deserialize<'de> for AuctionType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
-        let text = String::deserialize(deserializer)?;
-        AuctionType::from_str(&text).map_err(serde::de::Error::custom)
+        match AuctionTypeRepr::deserialize(deserializer)? {
+            AuctionTypeRepr::Text(text) => AuctionType::from_str(&text).map_err(serde::de::Error::custom),
+            AuctionTypeRepr::Structured(structured) => Ok(AuctionType::from(structured)),
+        }
+    }
+}
+
+/// An auction type as read from a request or a log. The bare amounts of the older string form ("English|0|0|0")
+/// are in the currency of the auction, so the type is only resolved once that is known.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnresolvedAuctionType {
+    Text(String),
+    Resolved(AuctionType),
+}
+
+impl UnresolvedAuctionType {
+    pub fn resolve(&self, currency: Currency) -> AuctionType {
+        match self {
+            UnresolvedAuctionType::Text(text) => AuctionType::parse_in(text, currency)
+                .expect("the auction type is checked when read"),
+            UnresolvedAuctionType::Resolved(typ) => typ.clone(),
+        }
+    }
+}
+
+impl Serialize for UnresolvedAuctionType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        match self {
+            UnresolvedAuctionType::Text(text) => serializer.serialize_str(text),
+            UnresolvedAuctionType::Resolved(typ) => typ.serialize(serializer),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for UnresolvedAuctionType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        match AuctionTypeRepr::deserialize(deserializer)? {
+            // Whether the text parses does not depend on the currency of bare amounts
+            AuctionTypeRepr::Text(text) => AuctionType::from_str(&text)
+                .map(|_| UnresolvedAuctionType::Text(text))
+                .map_err(serde::de::Error::custom),
+            AuctionTypeRepr::Structured(structured) => Ok(UnresolvedAuctionType::Resolved(AuctionType::from(structured))),
+        }
+    }
+}
+
+// Auction types are written in the compact string form but clients may also send a structured object,
+// e.g. { "kind": "English", "reservePrice": "SEK0", "minRaise": "SEK0", "timeFrame": 0 }
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AuctionTypeRepr {
+    Text(String),
+    Structured(StructuredAuctionType),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind")]
+enum StructuredAuctionType {
+    English {
+        #[serde(rename = "reservePrice")]
+        reserve_price: Amount,
+        #[serde(rename = "minRaise")]
+        min_raise: Amount,
+        /// Seconds
+        #[serde(rename = "timeFrame", default)]
+        time_frame: i64,
+        #[serde(rename = "allowSelfOutbid", default)]
+        allow_self_outbid: Option<bool>,
+        #[serde(rename = "maxExtensions", default)]
+        max_extensions: Option<u32>,
+        #[serde(rename = "hideBidders", default)]
+        hide_bidders: bool,
+        #[serde(rename = "strictIncrease", default)]
+        strict_increase: bool,
+        /// Seconds
+        #[serde(rename = "clockSkewGrace", default)]
+        clock_skew_grace: u32,
+        #[serde(rename = "reserveSecret", default)]
+        reserve_secret: bool,
+        #[serde(rename = "maxHistory", default)]
+        max_history: Option<usize>,
+        #[serde(rename = "finalRound", default)]
+        final_round: Option<StructuredFinalRound>,
+        #[serde(rename = "maxBid", default)]
+        max_bid: Option<Amount>,
+    },
+    Blind {
+        #[serde(rename = "allowRevision", default)]
+        allow_revision: bool,
+        #[serde(rename = "discloseAll", default)]
+        disclose_all: Option<bool>,
+    },
+    Vickrey {
+        #[serde(rename = "allowRevision", default)]
+        allow_revision: bool,
+        #[serde(rename = "discloseAll", default)]
+        disclose_all: Option<bool>,
+    },
+    Reverse {
+        #[serde(rename = "maxPrice")]
+        max_price: Amount,
+    },
+}
+
+#[derive(Deserialize)]
+struct StructuredFinalRound {
+    /// Seconds
+    seconds: u32,
+    bidders: u32,
+}
+
+impl From<StructuredAuctionType> for AuctionType {
+    fn from(structured: StructuredAuctionType) -> Self {
+        match structured {
+            StructuredAuctionType::English { reserve_price, min_raise, time_frame, allow_self_outbid, max_extensions, hide_bidders, strict_increase, clock_skew_grace, reserve_secret, max_history, final_round, max_bid } => {
+                AuctionType::TimedAscending(TAOptions {
+                    reserve_price,
+                    min_raise,
+                    time_frame: Duration::seconds(time_frame),
+                    allow_self_outbid: allow_self_outbid.unwrap_or(true),
+                    max_extensions,
+                    hide_bidders,
+                    strict_increase,
+                    clock_skew_grace: Duration::seconds(clock_skew_grace.into()),
+                    reserve_secret,
+                    max_history,
+                    final_round: final_round.map(|round| FinalRound { duration: Duration::seconds(round.seconds.into()), bidders: round.bidders.max(1) }),
+                    max_bid,
+                })
+            },
+            StructuredAuctionType::Blind { allow_revision, disclose_all } => AuctionType::SingleSealedBid(SBOptions {
+                allow_revision,
+                disclose_all: disclose_all.unwrap_or(true),
+                ..SBOptions::blind()
+            }),
+            StructuredAuctionType::Vickrey { allow_revision, disclose_all } => AuctionType::SingleSealedBid(SBOptions {
+                allow_revision,
+                disclose_all: disclose_all.unwrap_or(true),
+                ..SBOptions::vickrey()
+            }),
+            StructuredAuctionType::Reverse { max_price } => AuctionType::Reverse(ReverseOptions { max_price }),
+        }
     }
 }
 
@@ -32,41 +174,221 @@ impl fmt::Display for AuctionType {
         match self {
             AuctionType::TimedAscending(opts) => write!(f, "{}", opts),
             AuctionType::SingleSealedBid(opts) => write!(f, "{}", opts),
+            AuctionType::Reverse(opts) => write!(f, "{}", opts),
         }
     }
 }
 
-impl FromStr for AuctionType {
-    type Err = String;
+impl AuctionType {
+    /// The string form for responses, a secret reserve price is left out. The command log keeps the full form.
+    pub fn public_string(&self) -> String {
+        match self {
+            AuctionType::TimedAscending(opts) => opts.public_string(),
+            _ => self.to_string(),
+        }
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Ok(opts) = TAOptions::from_str(s) {
+    /// The string form within an auction in the given currency, English amounts in that currency are written bare
+    pub fn to_string_in(&self, currency: Currency) -> String {
+        match self {
+            AuctionType::TimedAscending(opts) => opts.to_string_in(currency),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The public string form within an auction in the given currency
+    pub fn public_string_in(&self, currency: Currency) -> String {
+        match self {
+            AuctionType::TimedAscending(opts) => opts.public_string_in(currency),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Reads the string form within an auction in the given currency, bare English amounts are in that currency
+    pub fn parse_in(s: &str, currency: Currency) -> Result<Self, String> {
+        if let Ok(opts) = TAOptions::parse_in(s, currency) {
             return Ok(AuctionType::TimedAscending(opts));
         }
         
         if let Ok(opts) = SBOptions::from_str(s) {
             return Ok(AuctionType::SingleSealedBid(opts));
         }
+
+        if let Ok(opts) = ReverseOptions::from_str(s) {
+            return Ok(AuctionType::Reverse(opts));
+        }
         
         Err(format!("Unknown auction type: {}", s))
     }
 }
 
+impl FromStr for AuctionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuctionType::parse_in(s, Currency::VAC)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "AuctionRepr", from = "AuctionRepr")]
 pub struct Auction {
-    #[serde(rename = "id")]
     pub auction_id: AuctionId,
-    #[serde(with="time::serde::rfc3339",rename = "startsAt")]
     pub starts_at: OffsetDateTime,
     pub title: String,
-    #[serde(with="time::serde::rfc3339")]
     pub expiry: OffsetDateTime,
-    #[serde(rename = "user")]
     pub seller: User,
-    #[serde(rename = "type")]
     pub typ: AuctionType,
-    #[serde(rename = "currency")]
     pub auction_currency: Currency,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+}
+
+// The serialized form of an auction, the type is written and read in the currency of the auction
+#[derive(Serialize, Deserialize)]
+struct AuctionRepr {
+    id: AuctionId,
+    #[serde(with="time::serde::rfc3339",rename = "startsAt")]
+    starts_at: OffsetDateTime,
+    title: String,
+    #[serde(with="time::serde::rfc3339")]
+    expiry: OffsetDateTime,
+    user: User,
+    #[serde(rename = "type")]
+    typ: UnresolvedAuctionType,
+    currency: Currency,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "imageUrl", default, skip_serializing_if = "Option::is_none")]
+    image_url: Option<String>,
+}
+
+impl From<Auction> for AuctionRepr {
+    fn from(auction: Auction) -> Self {
+        AuctionRepr {
+            id: auction.auction_id,
+            starts_at: auction.starts_at,
+            title: auction.title,
+            expiry: auction.expiry,
+            user: auction.seller,
+            typ: UnresolvedAuctionType::Text(auction.typ.to_string_in(auction.auction_currency)),
+            currency: auction.auction_currency,
+            description: auction.description,
+            image_url: auction.image_url,
+        }
+    }
+}
+
+impl From<AuctionRepr> for Auction {
+    fn from(repr: AuctionRepr) -> Self {
+        Auction {
+            auction_id: repr.id,
+            starts_at: repr.starts_at,
+            title: repr.title,
+            expiry: repr.expiry,
+            seller: repr.user,
+            typ: repr.typ.resolve(repr.currency),
+            auction_currency: repr.currency,
+            description: repr.description,
+            image_url: repr.image_url,
+        }
+    }
+}
+
+pub fn validate_auction_duration(auction: &Auction, min_duration: Duration) -> Result<(), Errors> {
+    let duration = auction.expiry - auction.starts_at;
+    if duration <= Duration::ZERO || duration < min_duration {
+        return Err(Errors::InvalidAuctionDuration(auction.auction_id));
+    }
+
+    Ok(())
+}
+
+pub fn validate_auction_title(title: &str, max_length: usize) -> Result<(), Errors> {
+    if title.trim().is_empty() {
+        return Err(Errors::InvalidAuctionTitle("title must not be empty".to_string()));
+    }
+    if title.trim() != title {
+        return Err(Errors::InvalidAuctionTitle("title must not start or end with whitespace".to_string()));
+    }
+    if title.chars().count() > max_length {
+        return Err(Errors::InvalidAuctionTitle(format!("title must be at most {} characters", max_length)));
+    }
+    if title.chars().any(char::is_control) {
+        return Err(Errors::InvalidAuctionTitle("title must not contain control characters".to_string()));
+    }
+
+    Ok(())
+}
+
+// Only a syntactic check of an absolute http(s) url, the image is not fetched
+pub fn validate_image_url(url: &str) -> Result<(), Errors> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"));
+    let host = rest.map(|rest| rest.split(['/', '?', '#']).next().unwrap_or_default());
+    match host {
+        Some(host) if !host.is_empty() && !url.chars().any(|c| c.is_whitespace() || c.is_control()) => Ok(()),
+        _ => Err(Errors::InvalidImageUrl(url.to_string())),
+    }
+}
+
+pub fn validate_auction(auction: &Auction) -> Result<(), Errors> {
+    validate_auction_duration(auction, Duration::ZERO)?;
+    validate_auction_currency(auction)
+}
+
+// The amounts of the auction type have to be in the auction currency
+pub fn validate_auction_currency(auction: &Auction) -> Result<(), Errors> {
+    for amount in option_amounts(&auction.typ) {
+        if amount.currency() != auction.auction_currency {
+            return Err(Errors::AuctionCurrencyMismatch((
+                auction.auction_currency,
+                amount.currency()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// The amounts set in the options of the auction type
+fn option_amounts(typ: &AuctionType) -> Vec<Amount> {
+    match typ {
+        AuctionType::TimedAscending(opts) => [opts.reserve_price, opts.min_raise].into_iter().chain(opts.max_bid).collect(),
+        AuctionType::Reverse(opts) => vec![opts.max_price],
+        AuctionType::SingleSealedBid(_) => Vec::new(),
+    }
+}
+
+// Amounts sent by clients are checked against the ceiling of their currency, currencies without one are unbounded.
+// Amounts read back from logs are not checked, so a lowered ceiling does not break replay.
+pub fn validate_amount_within_ceiling(amount: Amount, ceilings: &[Amount]) -> Result<(), Errors> {
+    match ceilings.iter().find(|ceiling| ceiling.currency() == amount.currency()) {
+        Some(ceiling) if amount.value() > ceiling.value() => Err(Errors::AmountAboveCeiling(*ceiling)),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_auction_amounts(auction: &Auction, ceilings: &[Amount]) -> Result<(), Errors> {
+    option_amounts(&auction.typ).into_iter()
+        .try_for_each(|amount| validate_amount_within_ceiling(amount, ceilings))
+}
+
+// Only the seller or support may move the end of an auction
+pub fn validate_extend(user: &User, auction: &Auction) -> Result<(), Errors> {
+    match user {
+        User::Support { .. } => Ok(()),
+        User::BuyerOrSeller { user_id, .. } if user_id == auction.seller.user_id() => Ok(()),
+        _ => Err(Errors::CannotExtendAuction((user.user_id().clone(), auction.auction_id))),
+    }
+}
+
+// Only the seller or support may end an auction early
+pub fn validate_close(user: &User, auction: &Auction) -> Result<(), Errors> {
+    match user {
+        User::Support { .. } => Ok(()),
+        User::BuyerOrSeller { user_id, .. } if user_id == auction.seller.user_id() => Ok(()),
+        _ => Err(Errors::CannotCloseAuction((user.user_id().clone(), auction.auction_id))),
+    }
 }
 
 pub fn validate_bid(bid: &Bid, auction: &Auction) -> Result<(), Errors> {
@@ -80,27 +402,95 @@ pub fn validate_bid(bid: &Bid, auction: &Auction) -> Result<(), Errors> {
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+// A min raise far above the reserve price leaves the auction at its first bid.
+// Without a reserve price there is nothing to compare with, so any min raise is accepted.
+pub fn validate_min_raise(auction: &Auction, max_bps: Option<u32>) -> Result<(), Errors> {
+    match (&auction.typ, max_bps) {
+        (AuctionType::TimedAscending(opts), Some(max_bps)) if opts.reserve_price.value() > 0 => {
+            let max_min_raise = opts.reserve_price.value() as i128 * max_bps as i128 / 10_000;
+            if (opts.min_raise.value() as i128) > max_min_raise {
+                return Err(Errors::MinRaiseTooLarge(Amount::new(auction.auction_currency, max_min_raise as AmountValue)));
+            }
+            Ok(())
+        },
+        _ => Ok(()),
+    }
+}
+
+// Bids are in the currency of the auction, there is no conversion between currencies yet
+pub fn validate_bid_currency(auction: &Auction, currency: Option<Currency>) -> Result<(), Errors> {
+    match currency {
+        Some(currency) if currency != auction.auction_currency => {
+            Err(Errors::BidCurrencyMismatch((auction.auction_currency, currency)))
+        },
+        _ => Ok(()),
+    }
+}
+
+// None allows every currency
+pub fn validate_currency_allowed(currency: Currency, allowed: Option<&[Currency]>) -> Result<(), Errors> {
+    match allowed {
+        Some(allowed) if !allowed.contains(&currency) => Err(Errors::CurrencyNotAllowed(currency)),
+        _ => Ok(()),
+    }
+}
+
+// Suspended users are kept outside of the repository, so this is checked before handling a bid
+pub fn validate_bidder_not_suspended(bid: &Bid, suspended: &HashSet<UserId>) -> Result<(), Errors> {
+    if suspended.contains(bid.bidder.user_id()) {
+        return Err(Errors::BidderSuspended(bid.bidder.user_id().clone()));
+    }
+
+    Ok(())
+}
+
+// Clients that read the auction at a version may require that no command has been accepted since, see Repository::version
+pub fn validate_expected_version(auction_id: AuctionId, version: u64, expected: Option<u64>) -> Result<(), Errors> {
+    match expected {
+        Some(expected) if expected != version => Err(Errors::VersionConflict((auction_id, expected))),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AuctionState {
     SingleSealedBid(super::single_sealed_bid::SingleSealedBidState),
     TimedAscending(super::timed_ascending::TimedAscendingState),
+    Reverse(super::reverse::ReverseState),
 }
 
 pub fn empty_state(auction: &Auction) -> AuctionState {
     match &auction.typ {
         AuctionType::SingleSealedBid(opt) => {
             AuctionState::SingleSealedBid(
-                super::single_sealed_bid::empty_state(auction.expiry, opt.clone())
+                super::single_sealed_bid::empty_state(auction.starts_at, auction.expiry, opt.clone())
             )
         },
         AuctionType::TimedAscending(opt) => {
             AuctionState::TimedAscending(
                 super::timed_ascending::empty_state(auction.starts_at, auction.expiry, opt.clone())
             )
+        },
+        AuctionType::Reverse(opt) => {
+            AuctionState::Reverse(
+                super::reverse::empty_state(auction.starts_at, auction.expiry, opt.clone())
+            )
         }
     }
 }
 
+// One line report of an auction, e.g. `#1 "auction" — SOLD to Buyer_2 for SEK12`
+pub fn summarize(auction: &Auction, state: &AuctionState) -> String {
+    let amount = |value| Amount::new(auction.auction_currency, value);
+    let outcome = match state.outcome() {
+        AuctionOutcome::Ongoing => "ONGOING".to_string(),
+        AuctionOutcome::NoBids => "UNSOLD (no bids)".to_string(),
+        AuctionOutcome::ReserveNotMet { highest } => format!("UNSOLD (reserve not met, high {})", amount(highest)),
+        AuctionOutcome::Sold { price, winner } => format!("SOLD to {} for {}", winner, amount(price)),
+    };
+    format!("#{} {:?} — {}", auction.auction_id, auction.title, outcome)
+}
+
 impl State for AuctionState{
     fn inc(&self, now: OffsetDateTime) -> Self where Self: Sized {
         match self {
@@ -109,6 +499,9 @@ impl State for AuctionState{
             },
             AuctionState::TimedAscending(state) => {
                 AuctionState::TimedAscending(state.inc(now))
+            },
+            AuctionState::Reverse(state) => {
+                AuctionState::Reverse(state.inc(now))
             }
         }
     }
@@ -122,28 +515,148 @@ impl State for AuctionState{
             AuctionState::TimedAscending(state) => {
                 let (new_state, result) = state.add_bid(bid);
                 (AuctionState::TimedAscending(new_state), result)
+            },
+            AuctionState::Reverse(state) => {
+                let (new_state, result) = state.add_bid(bid);
+                (AuctionState::Reverse(new_state), result)
             }
         }
     }
 
+    fn inc_mut(&mut self, now: OffsetDateTime) {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.inc_mut(now),
+            AuctionState::TimedAscending(state) => state.inc_mut(now),
+            AuctionState::Reverse(state) => state.inc_mut(now),
+        }
+    }
+
+    fn add_bid_mut(&mut self, bid: Bid) -> Result<(), Errors> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.add_bid_mut(bid),
+            AuctionState::TimedAscending(state) => state.add_bid_mut(bid),
+            AuctionState::Reverse(state) => state.add_bid_mut(bid),
+        }
+    }
+
+    fn apply_bids(&self, bids: impl IntoIterator<Item = Bid>) -> (Self, Vec<Result<(), Errors>>) {
+        match self {
+            AuctionState::SingleSealedBid(state) => {
+                let (new_state, results) = state.apply_bids(bids);
+                (AuctionState::SingleSealedBid(new_state), results)
+            },
+            AuctionState::TimedAscending(state) => {
+                let (new_state, results) = state.apply_bids(bids);
+                (AuctionState::TimedAscending(new_state), results)
+            },
+            AuctionState::Reverse(state) => {
+                let (new_state, results) = state.apply_bids(bids);
+                (AuctionState::Reverse(new_state), results)
+            }
+        }
+    }
+
+    fn close(&self, now: OffsetDateTime) -> Self {
+        match self {
+            AuctionState::SingleSealedBid(state) => AuctionState::SingleSealedBid(state.close(now)),
+            AuctionState::TimedAscending(state) => AuctionState::TimedAscending(state.close(now)),
+            AuctionState::Reverse(state) => AuctionState::Reverse(state.close(now))
+        }
+    }
+
+    fn extend(&self, new_expiry: OffsetDateTime) -> Self {
+        match self {
+            AuctionState::SingleSealedBid(state) => AuctionState::SingleSealedBid(state.extend(new_expiry)),
+            AuctionState::TimedAscending(state) => AuctionState::TimedAscending(state.extend(new_expiry)),
+            AuctionState::Reverse(state) => AuctionState::Reverse(state.extend(new_expiry))
+        }
+    }
+
     fn get_bids(&self) -> Vec<Bid> {
         match self {
             AuctionState::SingleSealedBid(state) => state.get_bids(),
-            AuctionState::TimedAscending(state) => state.get_bids()
+            AuctionState::TimedAscending(state) => state.get_bids(),
+            AuctionState::Reverse(state) => state.get_bids()
         }
     }
 
     fn try_get_amount_and_winner(&self) -> Option<(i64, super::UserId)> {
         match self {
             AuctionState::SingleSealedBid(state) => state.try_get_amount_and_winner(),
-            AuctionState::TimedAscending(state) => state.try_get_amount_and_winner()
+            AuctionState::TimedAscending(state) => state.try_get_amount_and_winner(),
+            AuctionState::Reverse(state) => state.try_get_amount_and_winner()
+        }
+    }
+
+    fn scheduled_phase_changes(&self) -> Vec<(OffsetDateTime, &'static str)> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.scheduled_phase_changes(),
+            AuctionState::TimedAscending(state) => state.scheduled_phase_changes(),
+            AuctionState::Reverse(state) => state.scheduled_phase_changes()
+        }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.phase_name(),
+            AuctionState::TimedAscending(state) => state.phase_name(),
+            AuctionState::Reverse(state) => state.phase_name()
+        }
+    }
+
+    fn has_started(&self) -> bool {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.has_started(),
+            AuctionState::TimedAscending(state) => state.has_started(),
+            AuctionState::Reverse(state) => state.has_started()
         }
     }
 
     fn has_ended(&self) -> bool {
         match self {
             AuctionState::SingleSealedBid(state) => state.has_ended(),
-            AuctionState::TimedAscending(state) => state.has_ended()
+            AuctionState::TimedAscending(state) => state.has_ended(),
+            AuctionState::Reverse(state) => state.has_ended()
+        }
+    }
+
+    fn time_left(&self, now: OffsetDateTime) -> Option<Duration> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.time_left(now),
+            AuctionState::TimedAscending(state) => state.time_left(now),
+            AuctionState::Reverse(state) => state.time_left(now)
+        }
+    }
+
+    fn first_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.first_bid_time(),
+            AuctionState::TimedAscending(state) => state.first_bid_time(),
+            AuctionState::Reverse(state) => state.first_bid_time()
+        }
+    }
+
+    fn last_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.last_bid_time(),
+            AuctionState::TimedAscending(state) => state.last_bid_time(),
+            AuctionState::Reverse(state) => state.last_bid_time()
+        }
+    }
+
+    fn bids_of(&self, user_id: &UserId) -> Vec<Bid> {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.bids_of(user_id),
+            AuctionState::TimedAscending(state) => state.bids_of(user_id),
+            AuctionState::Reverse(state) => state.bids_of(user_id)
+        }
+    }
+
+    fn outcome(&self) -> AuctionOutcome {
+        match self {
+            AuctionState::SingleSealedBid(state) => state.outcome(),
+            AuctionState::TimedAscending(state) => state.outcome(),
+            AuctionState::Reverse(state) => state.outcome()
         }
     }
 }
\ No newline at end of file
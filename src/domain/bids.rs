@@ -2,10 +2,13 @@
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use crate::money::AmountValue;
-use super::core::{AuctionId, User};
+use super::core::{AuctionId, BidId, Errors, User, UserId};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Bid {
+    /// Assigned when the bid is placed, kept as is when a command log is replayed
+    #[serde(rename = "id", default, skip_serializing_if = "Option::is_none")]
+    pub bid_id: Option<BidId>,
     #[serde(rename = "auction")]
     pub for_auction: AuctionId,
     #[serde(rename = "user")]
@@ -14,4 +17,46 @@ pub struct Bid {
     pub at: OffsetDateTime,
     #[serde(rename = "amount")]
     pub bid_amount: AmountValue,
+    /// Support user that placed the bid on behalf of the bidder
+    #[serde(rename = "placedBy", default, skip_serializing_if = "Option::is_none")]
+    pub placed_by: Option<UserId>,
+}
+
+/// The id of a bid logged without one, from the auction and the number of commands accepted on it before the bid,
+/// so that replaying the log gives the same ids
+pub fn derived_bid_id(auction_id: AuctionId, sequence: u64) -> BidId {
+    BidId::from_u64_pair(auction_id as u64, sequence)
+}
+
+impl Bid {
+    /// A newly placed bid with a fresh id, the amount must be positive and the bidder named.
+    /// Bids read back from a log are deserialized as they were and not checked again.
+    pub fn new(for_auction: AuctionId, bidder: User, at: OffsetDateTime, bid_amount: AmountValue) -> Result<Bid, Errors> {
+        if bid_amount <= 0 {
+            return Err(Errors::InvalidBidAmount(bid_amount));
+        }
+        if bidder.user_id().is_empty() {
+            return Err(Errors::InvalidUserData("Bidder id is empty".to_string()));
+        }
+        Ok(Bid {
+            bid_id: Some(BidId::new_v4()),
+            for_auction,
+            bidder,
+            at,
+            bid_amount,
+            placed_by: None,
+        })
+    }
+}
+
+/// Standing of a bid from the view of the bidder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BidStatus {
+    /// The leading bid of an ongoing auction
+    Winning,
+    Outbid,
+    /// Sealed bids are not compared until the auction has ended
+    Sealed,
+    Won,
+    Lost,
 }
\ No newline at end of file
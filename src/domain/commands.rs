@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
-use super::auctions::Auction;
+use super::auctions::{Auction, AuctionType};
 use super::bids::Bid;
+use super::core::{AuctionId, User, UserId};
+use crate::money::{Amount, AmountValue, Currency};
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "$type")]
 pub enum Command {
@@ -18,6 +23,254 @@ pub enum Command {
         timestamp: OffsetDateTime,
         bid: Bid,
     },
+
+    /// End an auction before its expiry, e.g. when the item was sold elsewhere
+    #[serde(rename = "CloseAuction")]
+    CloseAuction {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+        user: User,
+    },
+
+    /// Move the expiry of an auction to a later time, e.g. after a site outage
+    #[serde(rename = "ExtendAuction")]
+    ExtendAuction {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+        #[serde(with="time::serde::rfc3339", rename = "newExpiry")]
+        new_expiry: OffsetDateTime,
+        user: User,
+    },
+}
+
+// Compact single line form of commands, fields are separated by '|', e.g. PlaceBid|1|Buyer_1|SEK10|2016-01-15T08:28:00Z.
+// Fields that may contain separators (titles, users, auction types) are escaped with a backslash.
+// Bids do not know the currency of their auction, so bid amounts are written in the virtual currency, see to_string_in.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_in(f, Currency::VAC)
+    }
+}
+
+// The line form with bid amounts in the currency of the auction
+struct CommandIn<'a>(&'a Command, Currency);
+
+impl fmt::Display for CommandIn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_in(f, self.1)
+    }
+}
+
+impl Command {
+    /// The compact line form with the bid amount of a PlaceBid in the currency of its auction
+    pub fn to_string_in(&self, currency: Currency) -> String {
+        CommandIn(self, currency).to_string()
+    }
+
+    fn write_in(&self, f: &mut fmt::Formatter<'_>, currency: Currency) -> fmt::Result {
+        let fields = match self {
+            Command::AddAuction { timestamp, auction } => {
+                let mut fields = vec![
+                    "AddAuction".to_string(),
+                    format_time(timestamp)?,
+                    auction.auction_id.to_string(),
+                    format_time(&auction.starts_at)?,
+                    format_time(&auction.expiry)?,
+                    auction.title.clone(),
+                    auction.seller.to_string(),
+                    auction.typ.to_string_in(auction.auction_currency),
+                    auction.auction_currency.to_string(),
+                ];
+                // Description and image url are only written when set, keeping older lines unchanged
+                if auction.description.is_some() || auction.image_url.is_some() {
+                    fields.push(auction.description.clone().unwrap_or_default());
+                    fields.push(auction.image_url.clone().unwrap_or_default());
+                }
+                fields
+            },
+            Command::PlaceBid { timestamp, bid } => {
+                let mut fields = vec![
+                    "PlaceBid".to_string(),
+                    bid.for_auction.to_string(),
+                    bid.bidder.user_id().clone(),
+                    Amount::new(currency, bid.bid_amount).to_string(),
+                    format_time(&bid.at)?,
+                ];
+                // Only what the short form above does not already say is written, as flags
+                match &bid.bidder {
+                    User::BuyerOrSeller { user_id, name } if name != user_id => fields.push(format!("Name={}", name)),
+                    User::BuyerOrSeller { .. } => {},
+                    User::Support { .. } => fields.push("Support".to_string()),
+                }
+                if *timestamp != bid.at {
+                    fields.push(format!("At={}", format_time(timestamp)?));
+                }
+                if let Some(bid_id) = bid.bid_id {
+                    fields.push(format!("Id={}", bid_id.simple()));
+                }
+                if let Some(placed_by) = &bid.placed_by {
+                    fields.push(format!("PlacedBy={}", placed_by));
+                }
+                fields
+            },
+            Command::CloseAuction { timestamp, auction_id, user } => vec![
+                "CloseAuction".to_string(),
+                format_time(timestamp)?,
+                auction_id.to_string(),
+                user.to_string(),
+            ],
+            Command::ExtendAuction { timestamp, auction_id, new_expiry, user } => vec![
+                "ExtendAuction".to_string(),
+                format_time(timestamp)?,
+                auction_id.to_string(),
+                format_time(new_expiry)?,
+                user.to_string(),
+            ],
+        };
+        let escaped: Vec<String> = fields.iter().map(|field| escape_field(field)).collect();
+        write!(f, "{}", escaped.join("|"))
+    }
+
+    /// Reads the compact line form, bare bid amounts are in the given currency
+    pub fn parse_in(s: &str, currency: Currency) -> Result<Self, String> {
+        let fields = split_fields(s)?;
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+
+        match fields.as_slice() {
+            ["AddAuction", at, id, starts_at, expiry, title, seller, typ, currency_code, extra @ ..]
+                if extra.is_empty() || extra.len() == 2 => {
+                let optional = |i: usize| extra.get(i).filter(|field| !field.is_empty()).map(|field| field.to_string());
+                let auction_currency = currency_code.parse()?;
+                Ok(Command::AddAuction {
+                    timestamp: parse_time(at)?,
+                    auction: Auction {
+                        auction_id: id.parse()
+                            .map_err(|_| format!("Invalid auction id: {}", id))?,
+                        starts_at: parse_time(starts_at)?,
+                        title: title.to_string(),
+                        expiry: parse_time(expiry)?,
+                        seller: seller.parse()?,
+                        typ: AuctionType::parse_in(typ, auction_currency)?,
+                        auction_currency,
+                        description: optional(0),
+                        image_url: optional(1),
+                    },
+                })
+            },
+            ["PlaceBid", auction_id, bidder_id, amount, bid_at, flags @ ..] => {
+                let at = parse_time(bid_at)?;
+                let mut timestamp = at;
+                let mut bidder = User::BuyerOrSeller { user_id: bidder_id.to_string(), name: bidder_id.to_string() };
+                let mut bid_id = None;
+                let mut placed_by = None;
+                for flag in flags {
+                    match flag.split_once('=') {
+                        None if *flag == "Support" => bidder = User::Support { user_id: bidder_id.to_string() },
+                        Some(("Name", name)) => bidder = User::BuyerOrSeller { user_id: bidder_id.to_string(), name: name.to_string() },
+                        Some(("At", value)) => timestamp = parse_time(value)?,
+                        Some(("Id", value)) => {
+                            bid_id = Some(value.parse().map_err(|_| format!("Invalid bid id: {}", value))?);
+                        },
+                        Some(("PlacedBy", user_id)) => placed_by = Some(user_id.to_string()),
+                        _ => return Err(format!("Invalid PlaceBid flag: {}", flag)),
+                    }
+                }
+                Ok(Command::PlaceBid {
+                    timestamp,
+                    bid: Bid {
+                        bid_id,
+                        for_auction: auction_id.parse()
+                            .map_err(|_| format!("Invalid auction id: {}", auction_id))?,
+                        bidder,
+                        at,
+                        bid_amount: parse_bid_amount(amount, currency)?,
+                        placed_by,
+                    },
+                })
+            },
+            ["CloseAuction", at, auction_id, user] => {
+                Ok(Command::CloseAuction {
+                    timestamp: parse_time(at)?,
+                    auction_id: auction_id.parse()
+                        .map_err(|_| format!("Invalid auction id: {}", auction_id))?,
+                    user: user.parse()?,
+                })
+            },
+            ["ExtendAuction", at, auction_id, new_expiry, user] => {
+                Ok(Command::ExtendAuction {
+                    timestamp: parse_time(at)?,
+                    auction_id: auction_id.parse()
+                        .map_err(|_| format!("Invalid auction id: {}", auction_id))?,
+                    new_expiry: parse_time(new_expiry)?,
+                    user: user.parse()?,
+                })
+            },
+            _ => Err(format!("Invalid command format: {}", s)),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Command::parse_in(s, Currency::VAC)
+    }
+}
+
+// A bare amount is in the given currency, a prefixed one in its own
+fn parse_bid_amount(s: &str, currency: Currency) -> Result<AmountValue, String> {
+    let amount = match s.parse::<AmountValue>() {
+        Ok(value) => Amount::new(currency, value),
+        Err(_) => Amount::from_str(s).map_err(|_| format!("Invalid bid amount: {}", s))?,
+    };
+    Ok(amount.value())
+}
+
+fn format_time(time: &OffsetDateTime) -> Result<String, fmt::Error> {
+    time.format(&Rfc3339).map_err(|_| fmt::Error)
+}
+
+fn parse_time(s: &str) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(s, &Rfc3339).map_err(|e| format!("Invalid timestamp {}: {}", s, e))
+}
+
+fn escape_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn split_fields(s: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => current.push('\n'),
+                Some('r') => current.push('\r'),
+                Some(escaped @ ('\\' | '|')) => current.push(escaped),
+                _ => return Err(format!("Invalid escape sequence in: {}", s)),
+            },
+            '|' => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    Ok(fields)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -36,4 +289,44 @@ pub enum Event {
         timestamp: OffsetDateTime,
         bid: Bid,
     },
+
+    #[serde(rename = "AuctionClosed")]
+    AuctionClosed {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+        user: User,
+    },
+
+    #[serde(rename = "AuctionExtended")]
+    AuctionExtended {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+        #[serde(with="time::serde::rfc3339", rename = "newExpiry")]
+        new_expiry: OffsetDateTime,
+        user: User,
+    },
+
+    /// The start of the auction has passed, bids are now accepted
+    #[serde(rename = "AuctionStarted")]
+    AuctionStarted {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+    },
+
+    /// The auction has passed its expiry, with the winner and the price to pay when sold
+    #[serde(rename = "AuctionEnded")]
+    AuctionEnded {
+        #[serde(with="time::serde::rfc3339", rename = "at")]
+        timestamp: OffsetDateTime,
+        #[serde(rename = "auction")]
+        auction_id: AuctionId,
+        winner: Option<UserId>,
+        price: Option<Amount>,
+    },
 }
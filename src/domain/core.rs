@@ -1,10 +1,13 @@
 // src/domain/core.rs
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
+use crate::money::{Amount, AmountValue, Currency};
 
 pub type UserId = String;
 pub type AuctionId = i64;
+pub type BidId = uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum User {
@@ -31,7 +34,15 @@ impl<'de> Deserialize<'de> for User {
         D: serde::Deserializer<'de>,
     {
         let text = String::deserialize(deserializer)?;
-        let parts: Vec<&str> = text.split('|').collect();
+        User::from_str(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for User {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('|').collect();
         
         match parts.as_slice() {
             ["BuyerOrSeller", user_id, name] => {
@@ -46,9 +57,7 @@ impl<'de> Deserialize<'de> for User {
                 })
             },
             _ => {
-                Err(serde::de::Error::custom(
-                    format!("parsing User failed, could not interpret values: {:?}", parts)
-                ))
+                Err(format!("parsing User failed, could not interpret values: {:?}", parts))
             }
         }
     }
@@ -90,9 +99,113 @@ pub enum Errors {
     #[error("Invalid user data: {0}")]
     InvalidUserData(String),
     
+    #[error("Amount is above the largest accepted for the currency: {0}")]
+    AmountAboveCeiling(Amount),
+
+    #[error("Bid amount must be positive: {0}")]
+    InvalidBidAmount(AmountValue),
+
     #[error("Must place bid over highest bid: {0:?}")]
     MustPlaceBidOverHighestBid(i64),
     
+    #[error("Must place bid under lowest bid: {0:?}")]
+    MustPlaceBidUnderLowestBid(i64),
+    
     #[error("Already placed bid")]
     AlreadyPlacedBid,
-}
\ No newline at end of file
+
+    #[error("Currency does not match auction currency: {0:?}")]
+    AuctionCurrencyMismatch((Currency, Currency)),
+
+    #[error("Bid currency does not match auction currency: {0:?}")]
+    BidCurrencyMismatch((Currency, Currency)),
+
+    #[error("Invalid auction duration: {0}")]
+    InvalidAuctionDuration(AuctionId),
+
+    #[error("Only the seller or support can close the auction: {0:?}")]
+    CannotCloseAuction((UserId, AuctionId)),
+
+    #[error("Invalid auction title: {0}")]
+    InvalidAuctionTitle(String),
+
+    #[error("Bidder is suspended: {0}")]
+    BidderSuspended(UserId),
+
+    #[error("Invalid image url: {0}")]
+    InvalidImageUrl(String),
+
+    #[error("Already the highest bidder")]
+    AlreadyHighestBidder,
+
+    #[error("Bid is placed before the latest bid on auction: {0}")]
+    BidOutOfOrder(AuctionId),
+
+    #[error("Auction has changed since the expected version: {0:?}")]
+    VersionConflict((AuctionId, u64)),
+
+    #[error("Seller has reached the limit of active auctions: {0}")]
+    SellerAuctionLimitReached(UserId),
+
+    #[error("Bid comes from the address the auction was created from: {0}")]
+    SuspectedShillBid(AuctionId),
+
+    #[error("Min raise is above the largest allowed for the reserve price: {0}")]
+    MinRaiseTooLarge(Amount),
+
+    #[error("Bid is above the maximum bid of the auction: {0}")]
+    BidExceedsMaximum(Amount),
+
+    #[error("Only the leading bidders may bid in the final round: {0}")]
+    NotInFinalRound(UserId),
+
+    #[error("Currency is not allowed on this site: {0}")]
+    CurrencyNotAllowed(Currency),
+
+    #[error("Only the seller or support can extend the auction: {0:?}")]
+    CannotExtendAuction((UserId, AuctionId)),
+
+    #[error("The new expiry is not after the current end of the auction: {0}")]
+    CannotShortenAuction(AuctionId),
+
+    #[error("Bid is above the max price of the auction: {0}")]
+    BidAboveMaxPrice(Amount),
+}
+
+impl Errors {
+    // Stable machine readable code, keep unchanged when the message changes
+    pub fn code(&self) -> &'static str {
+        match self {
+            Errors::UnknownAuction(_) => "unknown_auction",
+            Errors::AuctionAlreadyExists(_) => "auction_already_exists",
+            Errors::AuctionHasEnded(_) => "auction_has_ended",
+            Errors::AuctionHasNotStarted(_) => "auction_has_not_started",
+            Errors::SellerCannotPlaceBids(_) => "seller_cannot_place_bids",
+            Errors::InvalidUserData(_) => "invalid_user_data",
+            Errors::InvalidBidAmount(_) => "invalid_bid_amount",
+            Errors::AmountAboveCeiling(_) => "amount_above_ceiling",
+            Errors::MustPlaceBidOverHighestBid(_) => "must_place_bid_over_highest_bid",
+            Errors::MustPlaceBidUnderLowestBid(_) => "must_place_bid_under_lowest_bid",
+            Errors::AlreadyPlacedBid => "already_placed_bid",
+            Errors::AuctionCurrencyMismatch(_) => "auction_currency_mismatch",
+            Errors::BidCurrencyMismatch(_) => "bid_currency_mismatch",
+            Errors::InvalidAuctionDuration(_) => "invalid_auction_duration",
+            Errors::CannotCloseAuction(_) => "cannot_close_auction",
+            Errors::InvalidAuctionTitle(_) => "invalid_auction_title",
+            Errors::BidderSuspended(_) => "bidder_suspended",
+            Errors::InvalidImageUrl(_) => "invalid_image_url",
+            Errors::AlreadyHighestBidder => "already_highest_bidder",
+            Errors::BidOutOfOrder(_) => "bid_out_of_order",
+            Errors::VersionConflict(_) => "version_conflict",
+            Errors::SellerAuctionLimitReached(_) => "seller_auction_limit_reached",
+            Errors::SuspectedShillBid(_) => "suspected_shill_bid",
+            Errors::MinRaiseTooLarge(_) => "min_raise_too_large",
+            Errors::BidExceedsMaximum(_) => "bid_exceeds_maximum",
+            Errors::NotInFinalRound(_) => "not_in_final_round",
+            Errors::CurrencyNotAllowed(_) => "currency_not_allowed",
+            Errors::CannotExtendAuction(_) => "cannot_extend_auction",
+            Errors::CannotShortenAuction(_) => "cannot_shorten_auction",
+            Errors::BidAboveMaxPrice(_) => "bid_above_max_price",
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use super::commands::Event;
+
+// Fan out of accepted events to any number of subscribers, e.g. notifications or search indexing
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: Sender<Event>,
+}
+
+impl EventBus {
+    /// Subscribers that fall more than `capacity` events behind will miss the oldest events
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus { sender }
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    // Publishing without subscribers is not an error, the event is dropped
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new(1024)
+    }
+}
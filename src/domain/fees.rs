@@ -0,0 +1,97 @@
+use crate::money::{Amount, AmountValue, MoneyError, RoundingMode};
+
+const BASIS_POINTS: i128 = 10_000;
+
+// Basis points of the price rounded half up to whole minor units, e.g. 250 bps of SEK10000 is SEK250
+pub fn compute_commission(price: Amount, rate_bps: u32) -> Result<Amount, MoneyError> {
+    compute_commission_rounded(price, rate_bps, RoundingMode::HalfUp)
+}
+
+// Basis points of the price rounded to whole minor units as the mode says
+pub fn compute_commission_rounded(price: Amount, rate_bps: u32, rounding: RoundingMode) -> Result<Amount, MoneyError> {
+    validate_real_currency(price)?;
+    validate_rate(rate_bps)?;
+    Ok(Amount::new(price.currency(), round_basis_points(price.value() as i128 * rate_bps as i128, rounding)?))
+}
+
+// Fees are only remitted in currencies that are paid out
+fn validate_real_currency(price: Amount) -> Result<(), MoneyError> {
+    if price.currency().is_virtual() {
+        Err(MoneyError::VirtualCurrency(price.currency()))
+    } else {
+        Ok(())
+    }
+}
+
+// The commission is never more than the price
+fn validate_rate(rate_bps: u32) -> Result<(), MoneyError> {
+    if rate_bps as i128 > BASIS_POINTS {
+        Err(MoneyError::RateAboveWholePrice(rate_bps))
+    } else {
+        Ok(())
+    }
+}
+
+fn round_basis_points(scaled: i128, rounding: RoundingMode) -> Result<AmountValue, MoneyError> {
+    AmountValue::try_from(rounding.divide(scaled, BASIS_POINTS)).map_err(|_| MoneyError::OutOfRange)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeTier {
+    /// The rate applies to the part of the price from this value, in minor units of the price currency
+    pub from: AmountValue,
+    pub rate_bps: u32,
+}
+
+/// Commission the marketplace takes of the winning price. Each tier charges its rate on the part of the price
+/// between its start and the start of the next tier, so a price just above a boundary costs no more than
+/// the price at the boundary plus the next rate on the difference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSchedule {
+    tiers: Vec<FeeTier>,
+    rounding: RoundingMode,
+}
+
+impl FeeSchedule {
+    pub fn flat(rate_bps: u32) -> Result<Self, MoneyError> {
+        FeeSchedule::tiered(vec![FeeTier { from: 0, rate_bps }])
+    }
+
+    // Prices below the first tier are free of commission, no tier may take more than the whole price
+    pub fn tiered(mut tiers: Vec<FeeTier>) -> Result<Self, MoneyError> {
+        for tier in &tiers {
+            validate_rate(tier.rate_bps)?;
+        }
+        tiers.sort_by_key(|tier| tier.from);
+        Ok(FeeSchedule { tiers, rounding: RoundingMode::default() })
+    }
+
+    /// Round the commission to minor units with the given mode instead of half up
+    pub fn with_rounding(self, rounding: RoundingMode) -> Self {
+        FeeSchedule { rounding, ..self }
+    }
+
+    pub fn commission(&self, price: Amount) -> Result<Amount, MoneyError> {
+        validate_real_currency(price)?;
+        let value = price.value();
+        let scaled: i128 = self.tiers.iter().enumerate()
+            .map(|(i, tier)| {
+                let band_end = self.tiers.get(i + 1).map_or(value, |next| next.from.min(value));
+                let band = (band_end - tier.from).max(0);
+                band as i128 * tier.rate_bps as i128
+            })
+            .sum();
+        Ok(Amount::new(price.currency(), round_basis_points(scaled, self.rounding)?))
+    }
+
+    /// What the seller receives of the price once the commission is taken
+    pub fn net_to_seller(&self, price: Amount) -> Result<Amount, MoneyError> {
+        Ok(Amount::new(price.currency(), price.value() - self.commission(price)?.value()))
+    }
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        FeeSchedule { tiers: vec![FeeTier { from: 0, rate_bps: 0 }], rounding: RoundingMode::default() }
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use super::auctions::{Auction, AuctionState};
+use super::core::{AuctionId, UserId};
+use super::states::State;
+use super::Repository;
+
+/// Secondary indexes of a repository, by seller and by phase, kept up to date by the repository.
+/// The repository stays authoritative, the index only answers which auctions to look at.
+/// The phase is the one of the stored state, as of the last command or sweep of the auction, so an
+/// auction that has passed its expiry since is still found under its earlier phase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepositoryIndex {
+    by_seller: HashMap<UserId, HashSet<AuctionId>>,
+    by_phase: HashMap<&'static str, HashSet<AuctionId>>,
+    // Auctions whose stored state has not ended, the only ones that can be awaiting start or ongoing as of now
+    unended: BTreeSet<AuctionId>,
+    // Where each auction is indexed, so that it can be moved without scanning the sets
+    entries: HashMap<AuctionId, (UserId, &'static str)>,
+}
+
+impl RepositoryIndex {
+    pub fn build(repository: &Repository) -> Self {
+        let mut index = RepositoryIndex::default();
+        for (auction, state) in repository.iter() {
+            index.update(auction, state);
+        }
+        index
+    }
+
+    /// Index a new auction or move an indexed one to its current phase
+    pub fn update(&mut self, auction: &Auction, state: &AuctionState) {
+        self.remove(auction.auction_id);
+        let seller = auction.seller.user_id().clone();
+        let phase = state.phase_name();
+        self.by_seller.entry(seller.clone()).or_default().insert(auction.auction_id);
+        self.by_phase.entry(phase).or_default().insert(auction.auction_id);
+        if !state.has_ended() {
+            self.unended.insert(auction.auction_id);
+        }
+        self.entries.insert(auction.auction_id, (seller, phase));
+    }
+
+    pub fn remove(&mut self, auction_id: AuctionId) {
+        if let Some((seller, phase)) = self.entries.remove(&auction_id) {
+            remove_from(&mut self.by_seller, &seller, auction_id);
+            remove_from(&mut self.by_phase, &phase, auction_id);
+            self.unended.remove(&auction_id);
+        }
+    }
+
+    pub fn by_seller(&self, seller: &UserId) -> Option<&HashSet<AuctionId>> {
+        self.by_seller.get(seller)
+    }
+
+    /// Auctions by the name of their phase, see State::phase_name
+    pub fn by_phase(&self, phase: &str) -> Option<&HashSet<AuctionId>> {
+        self.by_phase.get(phase)
+    }
+
+    /// Auctions whose stored state has not ended, ordered by id
+    pub fn unended(&self) -> impl Iterator<Item = AuctionId> + '_ {
+        self.unended.iter().copied()
+    }
+}
+
+// Empty sets are dropped so that the index of a repository compares equal to a freshly built one
+fn remove_from<K: std::hash::Hash + Eq>(sets: &mut HashMap<K, HashSet<AuctionId>>, key: &K, auction_id: AuctionId) {
+    if let Some(ids) = sets.get_mut(key) {
+        ids.remove(&auction_id);
+        if ids.is_empty() {
+            sets.remove(key);
+        }
+    }
+}
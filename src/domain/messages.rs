@@ -0,0 +1,96 @@
+use super::core::Errors;
+
+/// Language of the messages of errors, English unless another is asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Sv,
+}
+
+impl Lang {
+    /// The language of a tag such as sv or sv-SE, None for a language without messages
+    pub fn from_tag(tag: &str) -> Option<Lang> {
+        let primary = tag.trim().split('-').next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Lang::En),
+            "sv" => Some(Lang::Sv),
+            _ => None,
+        }
+    }
+
+    /// The most preferred language of an Accept-Language header that has messages, English when none has
+    pub fn from_accept_language(header: &str) -> Lang {
+        let mut ranges: Vec<(Lang, f32)> = header.split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let lang = Lang::from_tag(parts.next()?)?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                Some((lang, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // Stable, so of equally preferred languages the first listed is taken
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranges.first().map(|(lang, _)| *lang).unwrap_or_default()
+    }
+}
+
+// Keyed by the code of the error, the detail of the error is appended to the message
+const SWEDISH: &[(&str, &str)] = &[
+    ("unknown_auction", "Okänd auktion"),
+    ("auction_already_exists", "Auktionen finns redan"),
+    ("auction_has_ended", "Auktionen har avslutats"),
+    ("auction_has_not_started", "Auktionen har inte börjat"),
+    ("seller_cannot_place_bids", "Säljaren kan inte lägga bud"),
+    ("invalid_user_data", "Ogiltiga användaruppgifter"),
+    ("invalid_bid_amount", "Budet måste vara större än noll"),
+    ("amount_above_ceiling", "Beloppet är högre än det största som tas emot för valutan"),
+    ("must_place_bid_over_highest_bid", "Budet måste vara högre än det högsta budet"),
+    ("must_place_bid_under_lowest_bid", "Budet måste vara lägre än det lägsta budet"),
+    ("already_placed_bid", "Du har redan lagt ett bud"),
+    ("auction_currency_mismatch", "Valutan stämmer inte med auktionens valuta"),
+    ("bid_currency_mismatch", "Budets valuta stämmer inte med auktionens valuta"),
+    ("invalid_auction_duration", "Ogiltig auktionslängd"),
+    ("cannot_close_auction", "Endast säljaren eller support kan avsluta auktionen"),
+    ("invalid_auction_title", "Ogiltig auktionstitel"),
+    ("bidder_suspended", "Budgivaren är avstängd"),
+    ("invalid_image_url", "Ogiltig bildadress"),
+    ("already_highest_bidder", "Du har redan det högsta budet"),
+    ("bid_out_of_order", "Budet är lagt före det senaste budet på auktionen"),
+    ("version_conflict", "Auktionen har ändrats sedan den förväntade versionen"),
+    ("seller_auction_limit_reached", "Säljaren har nått gränsen för aktiva auktioner"),
+    ("suspected_shill_bid", "Budet kommer från adressen som auktionen skapades från"),
+    ("min_raise_too_large", "Minsta höjning är större än vad som tillåts för reservationspriset"),
+    ("bid_exceeds_maximum", "Budet är högre än auktionens högsta tillåtna bud"),
+    ("not_in_final_round", "Endast de ledande budgivarna får bjuda i slutrundan"),
+    ("currency_not_allowed", "Valutan är inte tillåten på sajten"),
+    ("cannot_extend_auction", "Endast säljaren eller support kan förlänga auktionen"),
+    ("cannot_shorten_auction", "Det nya slutet är inte efter auktionens nuvarande slut"),
+    ("bid_above_max_price", "Budet är högre än auktionens högsta pris"),
+];
+
+fn catalog(lang: Lang) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        // The English messages are those the errors display
+        Lang::En => &[],
+        Lang::Sv => SWEDISH,
+    }
+}
+
+impl Errors {
+    /// The message of the error in the language, in English when the language has no message for the code
+    pub fn message(&self, lang: Lang) -> String {
+        let english = self.to_string();
+        let Some((_, message)) = catalog(lang).iter().find(|(code, _)| *code == self.code()) else {
+            return english;
+        };
+        // The detail follows the message in the English form, e.g. the id in "Auction has ended: 1"
+        match english.split_once(": ") {
+            Some((_, detail)) => format!("{}: {}", message, detail),
+            None => message.to_string(),
+        }
+    }
+}
@@ -3,23 +3,101 @@ pub mod auctions;
 pub mod bids;
 pub mod commands;
 pub mod core;
+pub mod event_bus;
+pub mod fees;
+pub mod index;
+pub mod messages;
+pub mod repository;
+pub mod reverse;
 pub mod states;
+pub mod stats;
 pub mod timed_ascending;
 pub mod single_sealed_bid;
+pub mod verify;
 
-use std::collections::HashMap;
 use thiserror::Error;
+use crate::money::Amount;
+use time::OffsetDateTime;
 
 pub use self::auctions::*;
 pub use self::bids::*;
 pub use self::commands::*;
 pub use self::core::*;
+pub use self::event_bus::EventBus;
+pub use self::fees::{compute_commission, compute_commission_rounded, FeeSchedule, FeeTier};
+pub use self::index::RepositoryIndex;
+pub use self::messages::Lang;
+pub use self::repository::Repository;
 pub use self::states::*;
+pub use self::stats::{auction_phase, repository_stats, AuctionPhase, RepositoryStats};
+pub use self::verify::{compare_replayed, ConsistencyReport, Discrepancy};
 
-pub type Repository = HashMap<AuctionId, (Auction, AuctionState)>;
-
+// Ordered by id
 pub fn auctions(repository: &Repository) -> Vec<Auction> {
-    repository.values().map(|(auction, _)| auction.clone()).collect()
+    repository.iter_auctions().cloned().collect()
+}
+
+// Auctions whose effective end, including extensions by bids and a final round, falls within the window.
+// The stored state is moved on to the start of the window first, it may not have been swept yet.
+pub fn auctions_ending_between(repository: &Repository, from: OffsetDateTime, to: OffsetDateTime) -> Vec<Auction> {
+    let mut ending: Vec<(OffsetDateTime, &Auction)> = repository
+        .iter()
+        .filter_map(|(auction, state)| {
+            let end = from + state.inc(from).time_left(from)?;
+            (end <= to).then_some((end, auction))
+        })
+        .collect();
+    ending.sort_by_key(|(end, auction)| (*end, auction.auction_id));
+    ending.into_iter().map(|(_, auction)| auction.clone()).collect()
+}
+
+// The limit is a site setting kept outside of the repository, so this is checked before adding an auction
+pub fn validate_seller_auction_limit(
+    repository: &Repository,
+    auction: &Auction,
+    now: OffsetDateTime,
+    max_active: Option<usize>
+) -> Result<(), Errors> {
+    let Some(max_active) = max_active else {
+        return Ok(());
+    };
+    let seller = auction.seller.user_id();
+    let active = repository
+        .iter()
+        .filter(|(other, state)| other.seller.user_id() == seller && !state.inc(now).has_ended())
+        .count();
+    if active >= max_active {
+        return Err(Errors::SellerAuctionLimitReached(seller.clone()));
+    }
+
+    Ok(())
+}
+
+// Bids of the user across all auctions as of now, ordered by time
+pub fn bids_by(repository: &Repository, user_id: &UserId, now: OffsetDateTime) -> Vec<(Bid, BidStatus)> {
+    let mut bids: Vec<(Bid, BidStatus)> = repository
+        .iter()
+        .flat_map(|(_, state)| {
+            let state = state.inc(now);
+            // Bids are kept with the leading bid first
+            let visible = state.get_bids();
+            let leading = visible.first();
+            let won = matches!(state.outcome(), AuctionOutcome::Sold { winner, .. } if winner == *user_id);
+            state.bids_of(user_id).into_iter().map(|bid| {
+                let is_leading = leading.is_some_and(|leading| *leading == bid);
+                let status = match (state.has_ended(), is_leading) {
+                    (true, true) if won => BidStatus::Won,
+                    (true, _) => BidStatus::Lost,
+                    (false, _) if !visible.contains(&bid) => BidStatus::Sealed,
+                    (false, true) => BidStatus::Winning,
+                    (false, false) => BidStatus::Outbid,
+                };
+                (bid, status)
+            }).collect::<Vec<_>>()
+        })
+        .collect();
+    bids.sort_by_key(|(bid, _)| (bid.at, bid.for_auction));
+    bids
 }
 
 #[derive(Debug, Error)]
@@ -29,33 +107,131 @@ pub enum HandleError {
 }
 
 pub fn handle(command: Command, mut repository: Repository) -> Result<(Event, Repository), HandleError> {
+    let event = handle_mut(command, &mut repository)?;
+    Ok((event, repository))
+}
+
+// Reports auctions that have passed their start, ordered by id, with an event for each.
+// Reported auctions are marked in the repository so every auction is reported once, also when
+// a bid or sweep_ended has already moved its state past the start.
+pub fn sweep_started(repository: &mut Repository, now: OffsetDateTime) -> Vec<Event> {
+    let started: Vec<AuctionId> = repository
+        .iter()
+        .filter(|(auction, _)| !repository.is_start_reported(auction.auction_id))
+        .filter(|(_, state)| state.inc(now).has_started())
+        .map(|(auction, _)| auction.auction_id)
+        .collect();
+
+    started.into_iter().map(|auction_id| {
+        repository.mark_start_reported(auction_id);
+        Event::AuctionStarted { timestamp: now, auction_id }
+    }).collect()
+}
+
+// Moves auctions that have passed their expiry to their ended state, ordered by id, with an event for each.
+// Auctions that had already ended are left out, so every auction is reported once.
+pub fn sweep_ended(repository: &mut Repository, now: OffsetDateTime) -> Vec<Event> {
+    let ended: Vec<(Auction, AuctionState)> = repository
+        .iter()
+        .filter(|(_, state)| !state.has_ended())
+        .map(|(auction, state)| (auction.clone(), state.inc(now)))
+        .filter(|(_, state)| state.has_ended())
+        .collect();
+
+    ended.into_iter().map(|(auction, state)| {
+        let (winner, price) = match state.try_get_amount_and_winner() {
+            Some((amount, winner)) => (Some(winner), Some(Amount::new(auction.auction_currency, amount))),
+            None => (None, None),
+        };
+        let auction_id = auction.auction_id;
+        repository.insert(auction, state);
+        Event::AuctionEnded { timestamp: now, auction_id, winner, price }
+    }).collect()
+}
+
+// Apply each command in turn in place, a failing command adds nothing, see handle_mut
+pub fn handle_all(
+    commands: impl IntoIterator<Item = Command>,
+    mut repository: Repository
+) -> (Vec<Result<Event, HandleError>>, Repository) {
+    let results = commands
+        .into_iter()
+        .map(|command| handle_mut(command, &mut repository))
+        .collect();
+    (results, repository)
+}
+
+/// Add the bid to the state of its auction with the checks of a PlaceBid command, so that a bid can be tried
+/// on a copy of a single state
+pub fn add_bid_to(auction: &Auction, state: &mut AuctionState, bid: Bid) -> Result<(), Errors> {
+    validate_bid(&bid, auction)?;
+    // States compute expiry extensions from the bid time, so bids are applied in time order
+    if State::last_bid_time(state).is_some_and(|last| bid.at < last) {
+        return Err(Errors::BidOutOfOrder(bid.for_auction));
+    }
+
+    State::add_bid_mut(state, bid)
+}
+
+/// Handle the command on the repository in place, without the copy of the repository that handle takes.
+/// A rejected command adds nothing, though a rejected bid may have moved the state of its auction forward in time.
+pub fn handle_mut(command: Command, repository: &mut Repository) -> Result<Event, HandleError> {
     match command {
         Command::AddAuction { timestamp, auction } => {
             let auction_id = auction.auction_id;
-            if !repository.contains_key(&auction_id) {
+            validate_auction(&auction)?;
+            if !repository.contains(auction_id) {
                 let empty = empty_state(&auction);
-                repository.insert(auction_id, (auction.clone(), empty));
+                repository.insert(auction.clone(), empty);
 
-                Ok((Event::AuctionAdded { timestamp:timestamp, auction }, repository))
+                Ok(Event::AuctionAdded { timestamp, auction })
             } else {
                 Err(HandleError::from(Errors::AuctionAlreadyExists(auction_id)))
             }
         }
 
-        Command::PlaceBid { timestamp, bid } => {
+        Command::PlaceBid { timestamp, mut bid } => {
             let auction_id = bid.for_auction;
-            match repository.get(&auction_id) {
-                Some((auction, state)) => {
-                    validate_bid(&bid, auction)?;
+            // Bids get their id when placed, those of older logs one derived from their place in the log
+            let sequence = repository.version(auction_id);
+            bid.bid_id.get_or_insert_with(|| derived_bid_id(auction_id, sequence));
+            repository.update(auction_id, |auction, state| add_bid_to(auction, state, bid.clone()))
+                .ok_or(Errors::UnknownAuction(auction_id))??;
+            repository.bump_version(auction_id);
+            Ok(Event::BidAccepted { timestamp, bid })
+        }
 
-                    let (next_auction_state, bid_result) = State::add_bid(&state.clone(), bid.clone());
-                    bid_result?;
+        Command::CloseAuction { timestamp, auction_id, user } => {
+            repository.update(auction_id, |auction, state| {
+                validate_close(&user, auction)?;
+                if state.inc(timestamp).has_ended() {
+                    return Err(Errors::AuctionHasEnded(auction_id));
+                }
 
-                    repository.insert(auction_id, (auction.clone(), next_auction_state));
-                    Ok((Event::BidAccepted { timestamp, bid }, repository))
+                *state = State::close(state, timestamp);
+                Ok(())
+            }).ok_or(Errors::UnknownAuction(auction_id))??;
+            repository.bump_version(auction_id);
+            Ok(Event::AuctionClosed { timestamp, auction_id, user })
+        }
+
+        Command::ExtendAuction { timestamp, auction_id, new_expiry, user } => {
+            repository.update(auction_id, |auction, state| {
+                validate_extend(&user, auction)?;
+                // The current end includes any extensions by bids
+                let current_end = match state.inc(timestamp).time_left(timestamp) {
+                    Some(time_left) => timestamp + time_left,
+                    None => return Err(Errors::AuctionHasEnded(auction_id)),
+                };
+                if new_expiry <= current_end {
+                    return Err(Errors::CannotShortenAuction(auction_id));
                 }
-                None => Err(HandleError::from(Errors::UnknownAuction(auction_id))),
-            }
+
+                *state = State::extend(state, new_expiry);
+                Ok(())
+            }).ok_or(Errors::UnknownAuction(auction_id))??;
+            repository.bump_version(auction_id);
+            Ok(Event::AuctionExtended { timestamp, auction_id, new_expiry, user })
         }
     }
 }
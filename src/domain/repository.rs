@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use super::auctions::{Auction, AuctionState};
+use super::core::AuctionId;
+use super::index::RepositoryIndex;
+
+/// The auctions and their states, keyed by auction id and iterated in id order so that listings are stable.
+/// Callers go through these methods rather than the map so that the representation can change
+/// and so that the index is kept up to date with every change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Repository {
+    auctions: BTreeMap<AuctionId, (Auction, AuctionState)>,
+    index: RepositoryIndex,
+    // Kept apart from the states, so that moving a state forward in time does not reset the version
+    versions: HashMap<AuctionId, u64>,
+    // Auctions whose start has been reported by sweep_started, whatever their state has moved on to since
+    start_reported: HashSet<AuctionId>,
+}
+
+impl Repository {
+    pub fn new() -> Self {
+        Repository::default()
+    }
+
+    pub fn get(&self, auction_id: AuctionId) -> Option<(&Auction, &AuctionState)> {
+        self.auctions.get(&auction_id).map(|(auction, state)| (auction, state))
+    }
+
+    /// Change the state of the auction in place and index it as changed, None for an unknown auction
+    pub fn update<R>(&mut self, auction_id: AuctionId, f: impl FnOnce(&Auction, &mut AuctionState) -> R) -> Option<R> {
+        let (auction, state) = self.auctions.get_mut(&auction_id)?;
+        let result = f(auction, state);
+        self.index.update(auction, state);
+        Some(result)
+    }
+
+    pub fn get_auction(&self, auction_id: AuctionId) -> Option<&Auction> {
+        self.get(auction_id).map(|(auction, _)| auction)
+    }
+
+    pub fn get_state(&self, auction_id: AuctionId) -> Option<&AuctionState> {
+        self.get(auction_id).map(|(_, state)| state)
+    }
+
+    pub fn contains(&self, auction_id: AuctionId) -> bool {
+        self.auctions.contains_key(&auction_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.auctions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.auctions.is_empty()
+    }
+
+    /// Auctions by seller and by phase of their stored state
+    pub fn index(&self) -> &RepositoryIndex {
+        &self.index
+    }
+
+    /// Auctions with their states, ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = (&Auction, &AuctionState)> {
+        self.auctions.values().map(|(auction, state)| (auction, state))
+    }
+
+    /// Auctions ordered by id
+    pub fn iter_auctions(&self) -> impl Iterator<Item = &Auction> {
+        self.auctions.values().map(|(auction, _)| auction)
+    }
+
+    /// Adds the auction under its id, replacing an auction with the same id
+    pub fn insert(&mut self, auction: Auction, state: AuctionState) {
+        self.index.update(&auction, &state);
+        self.auctions.insert(auction.auction_id, (auction, state));
+    }
+
+    pub fn remove(&mut self, auction_id: AuctionId) -> Option<(Auction, AuctionState)> {
+        self.versions.remove(&auction_id);
+        self.start_reported.remove(&auction_id);
+        self.index.remove(auction_id);
+        self.auctions.remove(&auction_id)
+    }
+
+    /// Number of commands accepted on the auction since it was added, it only ever grows.
+    /// Clients send it back to have a command rejected when the auction has changed since they read it.
+    pub fn version(&self, auction_id: AuctionId) -> u64 {
+        self.versions.get(&auction_id).copied().unwrap_or(0)
+    }
+
+    pub fn bump_version(&mut self, auction_id: AuctionId) {
+        *self.versions.entry(auction_id).or_insert(0) += 1;
+    }
+
+    /// Restore the version of an auction, e.g. from a snapshot
+    pub fn set_version(&mut self, auction_id: AuctionId, version: u64) {
+        self.versions.insert(auction_id, version);
+    }
+
+    pub fn is_start_reported(&self, auction_id: AuctionId) -> bool {
+        self.start_reported.contains(&auction_id)
+    }
+
+    pub fn mark_start_reported(&mut self, auction_id: AuctionId) {
+        self.start_reported.insert(auction_id);
+    }
+}
+
+impl FromIterator<(Auction, AuctionState)> for Repository {
+    fn from_iter<I: IntoIterator<Item = (Auction, AuctionState)>>(iter: I) -> Self {
+        let mut repository = Repository::new();
+        for (auction, state) in iter {
+            repository.insert(auction, state);
+        }
+        repository
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use std::fmt;
+use std::str::FromStr;
+use crate::money::{Amount, AmountValue};
+use super::bids::Bid;
+use super::core::{Errors, UserId};
+use super::states::{AuctionOutcome, State};
+
+/// Reverse auction used for procurement: suppliers underbid each other and the lowest bid wins.
+/// The seller of the auction is the buyer looking for a supplier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Options {
+    /// The highest price the buyer is willing to pay, bids above it are not accepted.
+    /// If the max price is 0, that is the equivalent of not setting it.
+    pub max_price: Amount,
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Reverse|{}", self.max_price)
+    }
+}
+
+impl FromStr for Options {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('|').collect();
+        if parts.len() != 2 || parts[0] != "Reverse" {
+            return Err(format!("Invalid Reverse options format: {}", s));
+        }
+
+        let max_price = Amount::from_str(parts[1])
+            .map_err(|e| format!("Invalid max price: {}", e))?;
+
+        Ok(Options { max_price })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReverseState {
+    AwaitingStart {
+        #[serde(with="time::serde::rfc3339")]
+        start: OffsetDateTime,
+        #[serde(with="time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        options: Options,
+    },
+    OnGoing {
+        bids: Vec<Bid>,
+        #[serde(with="time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        options: Options,
+    },
+    HasEnded {
+        bids: Vec<Bid>,
+        #[serde(with="time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        options: Options,
+    },
+}
+
+pub fn empty_state(start: OffsetDateTime, expiry: OffsetDateTime, options: Options) -> ReverseState {
+    ReverseState::AwaitingStart {
+        start,
+        expiry,
+        options,
+    }
+}
+
+impl ReverseState {
+    fn inc_in_place(&mut self, now: OffsetDateTime) {
+        match self {
+            ReverseState::AwaitingStart { start, expiry, options } => {
+                if now > *start {
+                    let bids = Vec::new();
+                    if now < *expiry {
+                        // AwaitingStart -> OnGoing
+                        *self = ReverseState::OnGoing { bids, expiry: *expiry, options: options.clone() };
+                    } else {
+                        // AwaitingStart -> HasEnded
+                        *self = ReverseState::HasEnded { bids, expiry: *expiry, options: options.clone() };
+                    }
+                }
+            },
+            ReverseState::OnGoing { bids, expiry, options } => {
+                // The auction ends at the expiry instant, see State
+                if now >= *expiry {
+                    // OnGoing -> HasEnded
+                    *self = ReverseState::HasEnded {
+                        bids: std::mem::take(bids),
+                        expiry: *expiry,
+                        options: options.clone(),
+                    };
+                }
+            },
+            ReverseState::HasEnded { .. } => {}
+        }
+    }
+
+    fn close_in_place(&mut self, now: OffsetDateTime) {
+        match self {
+            ReverseState::AwaitingStart { options, .. } => {
+                *self = ReverseState::HasEnded { bids: Vec::new(), expiry: now, options: options.clone() };
+            },
+            ReverseState::OnGoing { bids, options, .. } => {
+                *self = ReverseState::HasEnded { bids: std::mem::take(bids), expiry: now, options: options.clone() };
+            },
+            ReverseState::HasEnded { .. } => {}
+        }
+    }
+
+    fn add_bid_in_place(&mut self, bid: Bid) -> Result<(), Errors> {
+        let auction_id = bid.for_auction;
+        let bid_amount = bid.bid_amount;
+
+        self.inc_in_place(bid.at);
+
+        match self {
+            ReverseState::AwaitingStart { .. } => Err(Errors::AuctionHasNotStarted(auction_id)),
+            ReverseState::OnGoing { bids, options, .. } => {
+                let max_price = options.max_price.value();
+                if max_price > 0 && bid_amount > max_price {
+                    return Err(Errors::BidAboveMaxPrice(options.max_price));
+                }
+
+                // You cannot bid higher than or equal to the current lowest bid
+                if let Some(lowest_bid) = bids.first() {
+                    if bid_amount >= lowest_bid.bid_amount {
+                        return Err(Errors::MustPlaceBidUnderLowestBid(lowest_bid.bid_amount));
+                    }
+                }
+
+                bids.insert(0, bid);
+                Ok(())
+            },
+            ReverseState::HasEnded { .. } => Err(Errors::AuctionHasEnded(auction_id)),
+        }
+    }
+}
+
+impl State for ReverseState {
+    fn inc(&self, now: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        next.inc_in_place(now);
+        next
+    }
+
+    fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) {
+        let mut next = self.clone();
+        let result = next.add_bid_in_place(bid);
+        (next, result)
+    }
+
+    fn inc_mut(&mut self, now: OffsetDateTime) {
+        self.inc_in_place(now);
+    }
+
+    fn add_bid_mut(&mut self, bid: Bid) -> Result<(), Errors> {
+        self.add_bid_in_place(bid)
+    }
+
+    fn close(&self, now: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        next.close_in_place(now);
+        next
+    }
+
+    fn extend(&self, new_expiry: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        match &mut next {
+            ReverseState::AwaitingStart { expiry, .. } |
+            ReverseState::OnGoing { expiry, .. } => *expiry = new_expiry,
+            ReverseState::HasEnded { .. } => {}
+        }
+        next
+    }
+
+    fn apply_bids(&self, bids: impl IntoIterator<Item = Bid>) -> (Self, Vec<Result<(), Errors>>) {
+        let mut next = self.clone();
+        let results = bids.into_iter().map(|bid| next.add_bid_in_place(bid)).collect();
+        (next, results)
+    }
+
+    fn get_bids(&self) -> Vec<Bid> {
+        match self {
+            ReverseState::AwaitingStart { .. } => Vec::new(),
+            ReverseState::OnGoing { bids, .. } |
+            ReverseState::HasEnded { bids, .. } => bids.clone(),
+        }
+    }
+
+    fn try_get_amount_and_winner(&self) -> Option<(AmountValue, UserId)> {
+        match self {
+            ReverseState::HasEnded { bids, .. } => bids
+                .first()
+                .map(|bid| (bid.bid_amount, bid.bidder.user_id().clone())),
+            _ => None,
+        }
+    }
+
+    fn has_started(&self) -> bool {
+        !matches!(self, ReverseState::AwaitingStart { .. })
+    }
+
+    fn has_ended(&self) -> bool {
+        matches!(self, ReverseState::HasEnded { .. })
+    }
+
+    fn scheduled_phase_changes(&self) -> Vec<(OffsetDateTime, &'static str)> {
+        match self {
+            ReverseState::AwaitingStart { start, expiry, .. } if start < expiry => {
+                vec![(*start, "on_going"), (*expiry, "has_ended")]
+            },
+            ReverseState::AwaitingStart { start, .. } => vec![(*start, "has_ended")],
+            ReverseState::OnGoing { expiry, .. } => vec![(*expiry, "has_ended")],
+            ReverseState::HasEnded { .. } => Vec::new(),
+        }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self {
+            ReverseState::AwaitingStart { .. } => "awaiting_start",
+            ReverseState::OnGoing { .. } => "on_going",
+            ReverseState::HasEnded { .. } => "has_ended",
+        }
+    }
+
+    fn time_left(&self, now: OffsetDateTime) -> Option<Duration> {
+        match self {
+            ReverseState::AwaitingStart { expiry, .. } |
+            ReverseState::OnGoing { expiry, .. } if now < *expiry => Some(*expiry - now),
+            _ => None,
+        }
+    }
+
+    fn first_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            ReverseState::AwaitingStart { .. } => None,
+            ReverseState::OnGoing { bids, .. } |
+            ReverseState::HasEnded { bids, .. } => bids.iter().map(|bid| bid.at).min(),
+        }
+    }
+
+    fn last_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            ReverseState::AwaitingStart { .. } => None,
+            ReverseState::OnGoing { bids, .. } |
+            ReverseState::HasEnded { bids, .. } => bids.iter().map(|bid| bid.at).max(),
+        }
+    }
+
+    fn outcome(&self) -> AuctionOutcome {
+        match self {
+            ReverseState::HasEnded { .. } => match self.try_get_amount_and_winner() {
+                Some((price, winner)) => AuctionOutcome::Sold { price, winner },
+                None => AuctionOutcome::NoBids,
+            },
+            _ => AuctionOutcome::Ongoing,
+        }
+    }
+}
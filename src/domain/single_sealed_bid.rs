@@ -1,15 +1,15 @@
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 use super::bids::Bid;
 use super::core::{Errors, UserId};
-use super::states::State;
+use super::states::{AuctionOutcome, State};
 use crate::money::AmountValue;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Options {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
     /// Sealed first-price auction
     /// In this type of auction all bidders simultaneously submit sealed bids so that no bidder knows the bid of any
     /// other participant. The highest bidder pays the price they submitted.
@@ -22,12 +22,42 @@ pub enum Options {
     Vickrey,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub kind: Kind,
+
+    /// A bidder may replace their sealed bid until the auction ends, the last bid counts.
+    /// Otherwise a second bid from the same bidder is rejected.
+    pub allow_revision: bool,
+
+    /// All bids are disclosed once the auction has ended.
+    /// Otherwise only the winning bid is, the losing bids are kept for the price and for the bidders themselves.
+    pub disclose_all: bool,
+}
+
+impl Options {
+    pub fn blind() -> Self {
+        Options { kind: Kind::Blind, allow_revision: false, disclose_all: true }
+    }
+
+    pub fn vickrey() -> Self {
+        Options { kind: Kind::Vickrey, allow_revision: false, disclose_all: true }
+    }
+}
+
 impl fmt::Display for Options {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Options::Blind => write!(f, "Blind"),
-            Options::Vickrey => write!(f, "Vickrey"),
+        match self.kind {
+            Kind::Blind => write!(f, "Blind")?,
+            Kind::Vickrey => write!(f, "Vickrey")?,
+        }
+        if self.allow_revision {
+            write!(f, "|AllowRevision")?;
         }
+        if !self.disclose_all {
+            write!(f, "|DiscloseWinnerOnly")?;
+        }
+        Ok(())
     }
 }
 
@@ -35,131 +65,284 @@ impl FromStr for Options {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Blind" => Ok(Options::Blind),
-            "Vickrey" => Ok(Options::Vickrey),
-            _ => Err(format!("Unknown SingleSealedBid option: {}", s)),
+        let mut parts = s.split('|');
+        let kind = match parts.next() {
+            Some("Blind") => Kind::Blind,
+            Some("Vickrey") => Kind::Vickrey,
+            _ => return Err(format!("Unknown SingleSealedBid option: {}", s)),
+        };
+        let mut allow_revision = false;
+        let mut disclose_all = true;
+        for flag in parts {
+            match flag {
+                "AllowRevision" => allow_revision = true,
+                "DiscloseWinnerOnly" => disclose_all = false,
+                _ => return Err(format!("Invalid SingleSealedBid flag: {}", flag)),
+            }
         }
+        Ok(Options { kind, allow_revision, disclose_all })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// Written in the string form, the same as in the auction type, so states saved before the flags still load
+impl Serialize for Options {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Options {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let text = String::deserialize(deserializer)?;
+        Options::from_str(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SingleSealedBidState {
+    AwaitingStart {
+        #[serde(with="time::serde::rfc3339")]
+        start: OffsetDateTime,
+        #[serde(with="time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        options: Options,
+    },
     AcceptingBids {
         bids: HashMap<UserId, Bid>,
+        #[serde(with="time::serde::rfc3339")]
         expiry: OffsetDateTime,
         options: Options,
     },
     DisclosingBids {
         bids: Vec<Bid>,
+        #[serde(with="time::serde::rfc3339")]
         expiry: OffsetDateTime,
         options: Options,
     },
 }
 
-pub fn empty_state(expiry: OffsetDateTime, options: Options) -> SingleSealedBidState {
-    SingleSealedBidState::AcceptingBids {
-        bids: HashMap::new(),
+pub fn empty_state(start: OffsetDateTime, expiry: OffsetDateTime, options: Options) -> SingleSealedBidState {
+    SingleSealedBidState::AwaitingStart {
+        start,
         expiry,
         options,
     }
 }
 
-impl State for SingleSealedBidState{
+// Sort bids by amount (highest first), of equal bids the earlier one first
+fn disclose(bids: &HashMap<UserId, Bid>, expiry: OffsetDateTime, options: &Options) -> SingleSealedBidState {
+    let mut sorted_bids = bids.values().cloned().collect::<Vec<_>>();
+    sorted_bids.sort_by(|a, b| b.bid_amount.cmp(&a.bid_amount).then(a.at.cmp(&b.at)));
 
-    fn inc(&self, now: OffsetDateTime) -> Self {
+    SingleSealedBidState::DisclosingBids {
+        bids: sorted_bids,
+        expiry,
+        options: options.clone(),
+    }
+}
+
+/// The price and winner of a Vickrey auction from its bids, highest first.
+/// The highest bidder wins and pays the second highest bid, or their own bid when it is the only one.
+/// When the two highest bids are equal the earlier bidder wins and pays that amount.
+/// None when there are no bids.
+pub fn vickrey_price(bids: &[Bid]) -> Option<(AmountValue, UserId)> {
+    match bids {
+        [] => None,
+        [only] => Some((only.bid_amount, only.bidder.user_id().clone())),
+        [highest, second, ..] => Some((second.bid_amount, highest.bidder.user_id().clone())),
+    }
+}
+
+impl SingleSealedBidState {
+    fn inc_in_place(&mut self, now: OffsetDateTime) {
         match self {
+            SingleSealedBidState::AwaitingStart { start, expiry, options } => {
+                if now > *start {
+                    if now < *expiry {
+                        // AwaitingStart -> AcceptingBids
+                        *self = SingleSealedBidState::AcceptingBids {
+                            bids: HashMap::new(),
+                            expiry: *expiry,
+                            options: options.clone(),
+                        };
+                    } else {
+                        // AwaitingStart -> DisclosingBids
+                        *self = SingleSealedBidState::DisclosingBids {
+                            bids: Vec::new(),
+                            expiry: *expiry,
+                            options: options.clone(),
+                        };
+                    }
+                }
+            },
             SingleSealedBidState::AcceptingBids { bids, expiry, options } => {
+                // The auction ends at the expiry instant, see State
                 if now >= *expiry {
-                    // Sort bids by amount (highest first)
-                    let mut sorted_bids = bids.values().cloned().collect::<Vec<_>>();
-                    sorted_bids.sort_by(|a, b| b.bid_amount.cmp(&a.bid_amount));
-                    
-                    SingleSealedBidState::DisclosingBids {
-                        bids: sorted_bids,
-                        expiry: *expiry,
-                        options: options.clone(),
-                    }
-                } else {
-                    self.clone()
+                    *self = disclose(bids, *expiry, options);
                 }
             },
-            SingleSealedBidState::DisclosingBids { .. } => self.clone(),
+            SingleSealedBidState::DisclosingBids { .. } => {},
         }
     }
 
-    fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) {
-        let now = bid.at;
+    fn add_bid_in_place(&mut self, bid: Bid) -> Result<(), Errors> {
         let auction_id = bid.for_auction;
         let user = bid.bidder.user_id().clone();
-        
-        let next = self.inc(now);
-        
-        match &next {
-            SingleSealedBidState::AcceptingBids { bids, expiry, options } => {
-                if bids.contains_key(&user) {
-                    return (next, Err(Errors::AlreadyPlacedBid));
+
+        self.inc_in_place(bid.at);
+
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } => Err(Errors::AuctionHasNotStarted(auction_id)),
+            SingleSealedBidState::AcceptingBids { bids, options, .. } => {
+                if bids.contains_key(&user) && !options.allow_revision {
+                    return Err(Errors::AlreadyPlacedBid);
                 }
-                
-                let mut new_bids = bids.clone();
-                new_bids.insert(user, bid);
-                
-                (
-                    SingleSealedBidState::AcceptingBids {
-                        bids: new_bids,
-                        expiry: *expiry,
-                        options: options.clone(),
-                    },
-                    Ok(())
-                )
+
+                // A revised bid replaces the earlier bid of the bidder
+                bids.insert(user, bid);
+                Ok(())
             },
-            SingleSealedBidState::DisclosingBids { .. } => {
-                (next, Err(Errors::AuctionHasEnded(auction_id)))
-            }
+            SingleSealedBidState::DisclosingBids { .. } => Err(Errors::AuctionHasEnded(auction_id)),
         }
     }
+}
+
+impl State for SingleSealedBidState{
 
+    fn inc(&self, now: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        next.inc_in_place(now);
+        next
+    }
+
+    fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) {
+        let mut next = self.clone();
+        let result = next.add_bid_in_place(bid);
+        (next, result)
+    }
+
+    fn inc_mut(&mut self, now: OffsetDateTime) {
+        self.inc_in_place(now);
+    }
+
+    fn add_bid_mut(&mut self, bid: Bid) -> Result<(), Errors> {
+        self.add_bid_in_place(bid)
+    }
+
+    fn close(&self, now: OffsetDateTime) -> Self {
+        match self {
+            SingleSealedBidState::AwaitingStart { options, .. } => disclose(&HashMap::new(), now, options),
+            SingleSealedBidState::AcceptingBids { bids, options, .. } => disclose(bids, now, options),
+            SingleSealedBidState::DisclosingBids { .. } => self.clone(),
+        }
+    }
+
+    fn extend(&self, new_expiry: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        match &mut next {
+            SingleSealedBidState::AwaitingStart { expiry, .. } |
+            SingleSealedBidState::AcceptingBids { expiry, .. } => *expiry = new_expiry,
+            SingleSealedBidState::DisclosingBids { .. } => {}
+        }
+        next
+    }
+
+    /// Highest first once the auction has ended, only the winning bid unless the options disclose all bids
     fn get_bids(&self) -> Vec<Bid> {
         match self {
-            SingleSealedBidState::DisclosingBids { bids, .. } => bids.clone(),
+            SingleSealedBidState::DisclosingBids { bids, options, .. } if options.disclose_all => bids.clone(),
+            SingleSealedBidState::DisclosingBids { bids, .. } => bids.iter().take(1).cloned().collect(),
             _ => Vec::new(),
         }
     }
 
+    // A bidder always sees their own bid, also when it is not disclosed to others
+    fn bids_of(&self, user_id: &UserId) -> Vec<Bid> {
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } => Vec::new(),
+            SingleSealedBidState::AcceptingBids { bids, .. } => bids.get(user_id).cloned().into_iter().collect(),
+            SingleSealedBidState::DisclosingBids { bids, .. } => bids.iter().filter(|bid| bid.bidder.user_id() == user_id).cloned().collect(),
+        }
+    }
+
     fn try_get_amount_and_winner(&self) -> Option<(AmountValue, UserId)> {
         match self {
+            SingleSealedBidState::AwaitingStart { .. } |
             SingleSealedBidState::AcceptingBids { .. } => None,
-            SingleSealedBidState::DisclosingBids { bids, options, .. } => {
-                if bids.is_empty() {
-                    return None;
-                }
-                
-                match options {
-                    Options::Vickrey => {
-                        if bids.len() == 1 {
-                            // Only one bid, winner pays their own bid
-                            Some((bids[0].bid_amount, bids[0].bidder.user_id().clone()))
-                        } else {
-                            // Winner pays the second highest bid
-                            Some((bids[1].bid_amount, bids[0].bidder.user_id().clone()))
-                        }
-                    },
-                    Options::Blind => {
-                        // Winner pays their own bid
-                        Some((bids[0].bid_amount, bids[0].bidder.user_id().clone()))
-                    }
-                }
+            SingleSealedBidState::DisclosingBids { bids, options, .. } => match options.kind {
+                Kind::Vickrey => vickrey_price(bids),
+                // Winner pays their own bid, no winner without bids
+                Kind::Blind => bids.first().map(|bid| (bid.bid_amount, bid.bidder.user_id().clone())),
             }
         }
     }
 
+    fn has_started(&self) -> bool {
+        !matches!(self, SingleSealedBidState::AwaitingStart { .. })
+    }
+
     fn has_ended(&self) -> bool {
         match self {
+            SingleSealedBidState::AwaitingStart { .. } |
             SingleSealedBidState::AcceptingBids { .. } => false,
             SingleSealedBidState::DisclosingBids { .. } => true,
         }
     }
 
+    fn scheduled_phase_changes(&self) -> Vec<(OffsetDateTime, &'static str)> {
+        match self {
+            SingleSealedBidState::AwaitingStart { start, expiry, .. } if start < expiry => {
+                vec![(*start, "accepting_bids"), (*expiry, "disclosing_bids")]
+            },
+            SingleSealedBidState::AwaitingStart { start, .. } => vec![(*start, "disclosing_bids")],
+            SingleSealedBidState::AcceptingBids { expiry, .. } => vec![(*expiry, "disclosing_bids")],
+            SingleSealedBidState::DisclosingBids { .. } => Vec::new(),
+        }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } => "awaiting_start",
+            SingleSealedBidState::AcceptingBids { .. } => "accepting_bids",
+            SingleSealedBidState::DisclosingBids { .. } => "disclosing_bids",
+        }
+    }
+
+    fn time_left(&self, now: OffsetDateTime) -> Option<Duration> {
+        match self {
+            SingleSealedBidState::AwaitingStart { expiry, .. } |
+            SingleSealedBidState::AcceptingBids { expiry, .. } if now < *expiry => Some(*expiry - now),
+            _ => None,
+        }
+    }
+
+    fn first_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } => None,
+            SingleSealedBidState::AcceptingBids { bids, .. } => bids.values().map(|bid| bid.at).min(),
+            SingleSealedBidState::DisclosingBids { bids, .. } => bids.iter().map(|bid| bid.at).min(),
+        }
+    }
+
+    fn last_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } => None,
+            SingleSealedBidState::AcceptingBids { bids, .. } => bids.values().map(|bid| bid.at).max(),
+            SingleSealedBidState::DisclosingBids { bids, .. } => bids.iter().map(|bid| bid.at).max(),
+        }
+    }
+
+    fn outcome(&self) -> AuctionOutcome {
+        match self {
+            SingleSealedBidState::AwaitingStart { .. } |
+            SingleSealedBidState::AcceptingBids { .. } => AuctionOutcome::Ongoing,
+            SingleSealedBidState::DisclosingBids { .. } => match self.try_get_amount_and_winner() {
+                Some((price, winner)) => AuctionOutcome::Sold { price, winner },
+                None => AuctionOutcome::NoBids,
+            },
+        }
+    }
+
 }
 
 
@@ -1,13 +1,109 @@
 // src/domain/states.rs
-use time::OffsetDateTime;
+use serde::Serialize;
+use time::{Duration, OffsetDateTime};
 use crate::money::AmountValue;
 use super::bids::Bid;
 use super::core::{Errors, UserId};
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "$type")]
+pub enum AuctionOutcome {
+    Ongoing,
+    NoBids,
+    ReserveNotMet { highest: AmountValue },
+    Sold { price: AmountValue, winner: UserId },
+}
+
+impl AuctionOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuctionOutcome::Ongoing => "ongoing",
+            AuctionOutcome::NoBids => "no_bids",
+            AuctionOutcome::ReserveNotMet { .. } => "reserve_not_met",
+            AuctionOutcome::Sold { .. } => "sold",
+        }
+    }
+}
+
+/// Result of an auction that has ended, for reporting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndedAuction {
+    pub winner: Option<UserId>,
+    pub price: Option<AmountValue>,
+    /// Number of bids visible once ended
+    pub bid_count: usize,
+    pub outcome: AuctionOutcome,
+}
+
+/// Bids are accepted strictly before the expiry of an auction,
+/// at the expiry instant or later the auction has ended.
 pub trait State {
     fn inc(&self, now: OffsetDateTime) -> Self where Self: Sized;
     fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) where Self: Sized;
+    /// End the auction as of now regardless of its expiry, an ended auction is left as is
+    fn close(&self, now: OffsetDateTime) -> Self where Self: Sized;
+    /// Move the expiry of the auction to the given time, an ended auction is left as is
+    fn extend(&self, new_expiry: OffsetDateTime) -> Self where Self: Sized;
+    /// inc without copying the state, for hot paths
+    fn inc_mut(&mut self, now: OffsetDateTime) where Self: Sized {
+        *self = self.inc(now);
+    }
+    /// add_bid without copying the state, for hot paths.
+    /// A rejected bid may still have moved the state forward to the time of the bid, as inc would.
+    fn add_bid_mut(&mut self, bid: Bid) -> Result<(), Errors> where Self: Sized {
+        let (next, result) = self.add_bid(bid);
+        *self = next;
+        result
+    }
+    fn apply_bids(&self, bids: impl IntoIterator<Item = Bid>) -> (Self, Vec<Result<(), Errors>>) where Self: Sized + Clone {
+        let mut next = self.clone();
+        let results = bids.into_iter().map(|bid| {
+            let (state, result) = next.add_bid(bid);
+            next = state;
+            result
+        }).collect();
+        (next, results)
+    }
     fn get_bids(&self) -> Vec<Bid>;
+    /// Bids of the user, including their own bids that are not visible to others
+    fn bids_of(&self, user_id: &UserId) -> Vec<Bid> {
+        self.get_bids().into_iter().filter(|bid| bid.bidder.user_id() == user_id).collect()
+    }
     fn try_get_amount_and_winner(&self) -> Option<(AmountValue, UserId)>;
+    /// False while awaiting the start of the auction, increment the state to the current time first
+    fn has_started(&self) -> bool;
     fn has_ended(&self) -> bool;
+    /// Name of the current variant in snake case, e.g. "on_going", for logs and labels
+    fn phase_name(&self) -> &'static str;
+    /// Times at which the phase will change if no bid or command intervenes, with the name of the phase changed to
+    fn scheduled_phase_changes(&self) -> Vec<(OffsetDateTime, &'static str)>;
+    /// The phase changes from `from` up to and including `to`, in order, for debugging and notification sweeps.
+    /// A start is reported at the start instant, bids are accepted right after it.
+    fn transitions_between(&self, from: OffsetDateTime, to: OffsetDateTime) -> Vec<(OffsetDateTime, &'static str)> where Self: Sized {
+        self.inc(from).scheduled_phase_changes().into_iter()
+            .filter(|(at, _)| from <= *at && *at <= to)
+            .collect()
+    }
+    fn time_left(&self, now: OffsetDateTime) -> Option<Duration>;
+    fn first_bid_time(&self) -> Option<OffsetDateTime>;
+    fn last_bid_time(&self) -> Option<OffsetDateTime>;
+    fn outcome(&self) -> AuctionOutcome;
+    /// The result of the auction as of `now`, None when it has not ended by then
+    fn ended_view(&self, now: OffsetDateTime) -> Option<EndedAuction> where Self: Sized {
+        let ended = self.inc(now);
+        if !ended.has_ended() {
+            return None;
+        }
+        let outcome = ended.outcome();
+        let (winner, price) = match &outcome {
+            AuctionOutcome::Sold { price, winner } => (Some(winner.clone()), Some(*price)),
+            _ => (None, None),
+        };
+        Some(EndedAuction {
+            winner,
+            price,
+            bid_count: ended.get_bids().len(),
+            outcome,
+        })
+    }
 }
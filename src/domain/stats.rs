@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+use crate::money::{Amount, Currency};
+use super::auctions::{Auction, AuctionState, AuctionType};
+use super::states::State;
+use super::Repository;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AuctionPhase {
+    AwaitingStart,
+    Ongoing,
+    Ended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepositoryStats {
+    #[serde(rename = "totalAuctions")]
+    pub total_auctions: usize,
+    /// Keyed by auction kind: English, Blind, Vickrey or Reverse
+    #[serde(rename = "byType")]
+    pub by_type: BTreeMap<&'static str, usize>,
+    #[serde(rename = "byPhase")]
+    pub by_phase: BTreeMap<AuctionPhase, usize>,
+    #[serde(rename = "totalBids")]
+    pub total_bids: usize,
+    /// Sum of the leading bid of every auction, per currency
+    #[serde(rename = "highBidTotals")]
+    pub high_bid_totals: BTreeMap<Currency, Amount>,
+}
+
+fn kind(auction: &Auction) -> &'static str {
+    match &auction.typ {
+        AuctionType::TimedAscending(_) => "English",
+        AuctionType::SingleSealedBid(options) => match options.kind {
+            super::single_sealed_bid::Kind::Blind => "Blind",
+            super::single_sealed_bid::Kind::Vickrey => "Vickrey",
+        },
+        AuctionType::Reverse(_) => "Reverse",
+    }
+}
+
+pub fn auction_phase(auction: &Auction, state: &AuctionState, now: OffsetDateTime) -> AuctionPhase {
+    if state.inc(now).has_ended() {
+        AuctionPhase::Ended
+    } else if now < auction.starts_at {
+        AuctionPhase::AwaitingStart
+    } else {
+        AuctionPhase::Ongoing
+    }
+}
+
+pub fn repository_stats(repository: &Repository, now: OffsetDateTime) -> RepositoryStats {
+    let mut stats = RepositoryStats {
+        total_auctions: repository.len(),
+        by_type: BTreeMap::new(),
+        by_phase: BTreeMap::new(),
+        total_bids: 0,
+        high_bid_totals: BTreeMap::new(),
+    };
+
+    for (auction, state) in repository.iter() {
+        let phase = auction_phase(auction, state, now);
+        let state = state.inc(now);
+        *stats.by_type.entry(kind(auction)).or_default() += 1;
+        *stats.by_phase.entry(phase).or_default() += 1;
+
+        // Bids are kept with the leading bid first, sealed bids are not visible until disclosed
+        let bids = state.get_bids();
+        stats.total_bids += bids.len();
+        if let Some(leading) = bids.first() {
+            let currency = auction.auction_currency;
+            let amount = Amount::new(currency, leading.bid_amount);
+            let total = stats.high_bid_totals.entry(currency).or_insert(Amount::new(currency, 0));
+            // Totals are keyed by currency so the sum is always of the same currency
+            *total = (*total + amount).expect("Totals are kept per currency");
+        }
+    }
+
+    stats
+}
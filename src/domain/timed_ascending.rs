@@ -2,63 +2,198 @@ use serde::{Deserialize, Serialize};
 use time::{Duration, OffsetDateTime};
 use std::fmt;
 use std::str::FromStr;
-use crate::money::AmountValue;
+use crate::money::{Amount, AmountValue, Currency};
 use super::bids::Bid;
 use super::core::{Errors, UserId};
-use super::states::State;
+use super::states::{AuctionOutcome, State};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Options {
     /// The seller has set a minimum sale price in advance (the 'reserve' price)
     /// and the final bid does not reach that price the item remains unsold.
     /// If the reserve price is 0, that is the equivalent of not setting it.
-    pub reserve_price: AmountValue,
+    pub reserve_price: Amount,
     
     /// Sometimes the auctioneer sets a minimum amount by which the next bid must exceed the current highest bid.
     /// Having min raise equal to 0 is the equivalent of not setting it.
-    pub min_raise: AmountValue,
+    pub min_raise: Amount,
     
     /// If no competing bidder challenges the standing bid within a given time frame,
     /// the standing bid becomes the winner, and the item is sold to the highest bidder
     /// at a price equal to his or her bid.
+    #[serde(with = "seconds")]
     pub time_frame: Duration,
+
+    /// Whether the bidder holding the highest bid may raise it further.
+    /// Raising your own bid only inflates the price and extends the auction.
+    #[serde(default = "allow_self_outbid_default")]
+    pub allow_self_outbid: bool,
+
+    /// Caps how many times bids may extend the expiry of the auction, None for no limit.
+    #[serde(default)]
+    pub max_extensions: Option<u32>,
+
+    /// Bidder identities are only disclosed once the auction has ended, until then only amounts are shown.
+    #[serde(default)]
+    pub hide_bidders: bool,
+
+    /// Require a bid to exceed the highest bid even when min raise is 0, otherwise an equal bid is accepted.
+    #[serde(default)]
+    pub strict_increase: bool,
+
+    /// Bids arriving this long after the expiry are still accepted and extend the auction,
+    /// to allow for clients whose clocks drift. Zero rejects any bid after the expiry.
+    #[serde(with = "seconds", default)]
+    pub clock_skew_grace: Duration,
+
+    /// The reserve price is kept from bidders, responses show it as *. It is still enforced.
+    #[serde(default)]
+    pub reserve_secret: bool,
+
+    /// Keep this many of the most recent bids, None keeps all. As every accepted bid is at least the one before it,
+    /// the most recent bids are also the highest, so the standing bid and the winner are never evicted.
+    /// The first bid and the standing bids of the bidders of a final round are kept as well.
+    #[serde(default)]
+    pub max_history: Option<usize>,
+
+    /// Once the open bidding has ended, the leading bidders get one sealed bid each before the auction ends.
+    /// None ends the auction with the open bidding.
+    #[serde(default)]
+    pub final_round: Option<FinalRound>,
+
+    /// Bids above this amount are rejected as most likely mistyped, None accepts any amount.
+    #[serde(default)]
+    pub max_bid: Option<Amount>,
+}
+
+/// A sealed round that follows the open bidding of an English auction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalRound {
+    /// How long the round lasts after the expiry of the open bidding
+    #[serde(with = "seconds")]
+    pub duration: Duration,
+    /// How many of the leading bidders of the open bidding may place a sealed bid
+    pub bidders: u32,
+}
+
+fn allow_self_outbid_default() -> bool {
+    true
+}
+
+// The time frame is written as whole seconds in JSON, the same as in the string form
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.whole_seconds())
+    }
+
+    // Snapshots written before used the default (seconds, nanoseconds) pair
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Seconds(i64),
+        SecondsAndNanos((i64, i32)),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Seconds(seconds) => Duration::seconds(seconds),
+            Repr::SecondsAndNanos((seconds, nanos)) => Duration::new(seconds, nanos),
+        })
+    }
 }
 
 impl Options {
-    pub fn default_options() -> Self {
+    // No reserve price or min raise, in the currency of the auction
+    pub fn default_options(currency: Currency) -> Self {
         Options {
-            reserve_price: 0,
-            min_raise: 0,
+            reserve_price: Amount::new(currency, 0),
+            min_raise: Amount::new(currency, 0),
             time_frame: Duration::seconds(0),
+            allow_self_outbid: true,
+            max_extensions: None,
+            hide_bidders: false,
+            strict_increase: false,
+            clock_skew_grace: Duration::ZERO,
+            reserve_secret: false,
+            max_history: None,
+            final_round: None,
+            max_bid: None,
         }
     }
-}
 
-impl fmt::Display for Options {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f, 
-            "English|{}|{}|{}",
-            self.reserve_price,
-            self.min_raise,
-            self.time_frame.whole_seconds()
-        )
+    /// The string form shown to bidders, with the reserve price left out when it is secret
+    pub fn public_string(&self) -> String {
+        self.format(None, self.reserve_secret)
     }
-}
 
-impl FromStr for Options {
-    type Err = String;
+    /// The string form within an auction in the given currency, amounts in that currency are written bare
+    /// as in the older form ("English|0|0|0")
+    pub fn to_string_in(&self, currency: Currency) -> String {
+        self.format(Some(currency), false)
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    /// The public string form within an auction in the given currency
+    pub fn public_string_in(&self, currency: Currency) -> String {
+        self.format(Some(currency), self.reserve_secret)
+    }
+
+    /// Reads the string form within an auction in the given currency, bare amounts are in that currency
+    pub fn parse_in(s: &str, currency: Currency) -> Result<Self, String> {
         let parts: Vec<&str> = s.split('|').collect();
-        if parts.len() != 4 || parts[0] != "English" {
+        if parts.len() < 4 || parts[0] != "English" {
             return Err(format!("Invalid TimedAscending options format: {}", s));
         }
+
+        let mut allow_self_outbid = true;
+        let mut max_extensions = None;
+        let mut hide_bidders = false;
+        let mut strict_increase = false;
+        let mut clock_skew_grace = Duration::ZERO;
+        let mut reserve_secret = false;
+        let mut max_history = None;
+        let mut final_round = None;
+        let mut max_bid = None;
+        for flag in &parts[4..] {
+            match flag.split_once('=') {
+                None if *flag == "NoSelfOutbid" => allow_self_outbid = false,
+                None if *flag == "HideBidders" => hide_bidders = true,
+                None if *flag == "StrictIncrease" => strict_increase = true,
+                None if *flag == "ReserveSecret" => reserve_secret = true,
+                Some(("MaxExtensions", value)) => {
+                    max_extensions = Some(value.parse::<u32>()
+                        .map_err(|_| format!("Invalid max extensions: {}", value))?);
+                },
+                Some(("MaxHistory", value)) => {
+                    max_history = Some(value.parse::<usize>()
+                        .map_err(|_| format!("Invalid max history: {}", value))?);
+                },
+                Some(("FinalRound", value)) => {
+                    // Seconds and the number of bidders, e.g. FinalRound=600/3
+                    let invalid = || format!("Invalid final round: {}", value);
+                    let (seconds, bidders) = value.split_once('/').ok_or_else(invalid)?;
+                    let seconds = seconds.parse::<u32>().map_err(|_| invalid())?;
+                    let bidders = bidders.parse::<u32>().ok().filter(|bidders| *bidders > 0).ok_or_else(invalid)?;
+                    final_round = Some(FinalRound { duration: Duration::seconds(seconds.into()), bidders });
+                },
+                Some(("MaxBid", value)) => {
+                    max_bid = Some(Amount::from_str(value)
+                        .map_err(|e| format!("Invalid max bid: {}", e))?);
+                },
+                Some(("ClockSkewGrace", value)) => {
+                    clock_skew_grace = Duration::seconds(value.parse::<u32>()
+                        .map_err(|_| format!("Invalid clock skew grace: {}", value))?.into());
+                },
+                _ => return Err(format!("Invalid TimedAscending flag: {}", flag)),
+            }
+        }
         
-        let reserve_price = parts[1].parse::<i64>()
+        let reserve_price = parse_option_amount(parts[1], currency)
             .map_err(|e| format!("Invalid reserve price: {}", e))?;
             
-        let min_raise = parts[2].parse::<i64>()
+        let min_raise = parse_option_amount(parts[2], currency)
             .map_err(|e| format!("Invalid min raise: {}", e))?;
             
         let time_frame_seconds = parts[3].parse::<i64>()
@@ -68,24 +203,112 @@ impl FromStr for Options {
             reserve_price,
             min_raise,
             time_frame: Duration::seconds(time_frame_seconds),
+            allow_self_outbid,
+            max_extensions,
+            hide_bidders,
+            strict_increase,
+            clock_skew_grace,
+            reserve_secret,
+            max_history,
+            final_round,
+            max_bid,
         })
     }
+
+    fn format(&self, bare_currency: Option<Currency>, hide_reserve: bool) -> String {
+        let option_amount = |amount: Amount| match bare_currency {
+            Some(currency) if amount.currency() == currency => amount.value().to_string(),
+            _ => amount.to_string(),
+        };
+        let reserve = if hide_reserve { "*".to_string() } else { option_amount(self.reserve_price) };
+        let mut s = format!("English|{}|{}|{}", reserve, option_amount(self.min_raise), self.time_frame.whole_seconds());
+        // Flags are only written when not the default to keep the common form unchanged
+        if !self.allow_self_outbid {
+            s.push_str("|NoSelfOutbid");
+        }
+        if let Some(max_extensions) = self.max_extensions {
+            s.push_str(&format!("|MaxExtensions={}", max_extensions));
+        }
+        if self.hide_bidders {
+            s.push_str("|HideBidders");
+        }
+        if self.strict_increase {
+            s.push_str("|StrictIncrease");
+        }
+        if self.clock_skew_grace != Duration::ZERO {
+            s.push_str(&format!("|ClockSkewGrace={}", self.clock_skew_grace.whole_seconds()));
+        }
+        if self.reserve_secret {
+            s.push_str("|ReserveSecret");
+        }
+        if let Some(max_history) = self.max_history {
+            s.push_str(&format!("|MaxHistory={}", max_history));
+        }
+        if let Some(round) = self.final_round {
+            s.push_str(&format!("|FinalRound={}/{}", round.duration.whole_seconds(), round.bidders));
+        }
+        if let Some(max_bid) = self.max_bid {
+            s.push_str(&format!("|MaxBid={}", max_bid));
+        }
+        s
+    }
+}
+
+// Older command logs wrote reserve price and min raise as bare values ("English|0|0|0"),
+// those are read as amounts in the currency of the auction, or the default currency on their own.
+fn parse_option_amount(s: &str, currency: Currency) -> Result<Amount, String> {
+    match s.parse::<AmountValue>() {
+        Ok(value) => Ok(Amount::new(currency, value)),
+        Err(_) => Amount::from_str(s),
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(None, false))
+    }
+}
+
+impl FromStr for Options {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Options::parse_in(s, Currency::VAC)
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimedAscendingState {
     AwaitingStart {
+        #[serde(with="time::serde::rfc3339")]
         start: OffsetDateTime,
+        #[serde(with="time::serde::rfc3339")]
         starting_expiry: OffsetDateTime,
         options: Options,
     },
     OnGoing {
         bids: Vec<Bid>,
+        #[serde(with="time::serde::rfc3339")]
         next_expiry: OffsetDateTime,
         options: Options,
+        /// Number of times bids have extended the expiry
+        #[serde(default)]
+        extensions: u32,
+    },
+    /// The open bidding has ended and the leading bidders may each place one sealed bid until the expiry
+    FinalRound {
+        /// The bids of the open bidding, newest first
+        bids: Vec<Bid>,
+        /// In the order they were placed, only disclosed once the auction has ended
+        sealed_bids: Vec<Bid>,
+        eligible: Vec<UserId>,
+        #[serde(with="time::serde::rfc3339")]
+        expiry: OffsetDateTime,
+        options: Options,
     },
     HasEnded {
         bids: Vec<Bid>,
+        #[serde(with="time::serde::rfc3339")]
         expiry: OffsetDateTime,
         options: Options,
     },
@@ -99,121 +322,319 @@ pub fn empty_state(start: OffsetDateTime, starting_expiry: OffsetDateTime, optio
     }
 }
 
-impl State for TimedAscendingState {
-    fn inc(&self, now: OffsetDateTime) -> Self {
+// The distinct bidders with the highest bids, bids are kept highest first
+fn leading_bidders(bids: &[Bid], count: u32) -> Vec<UserId> {
+    let mut leading: Vec<UserId> = Vec::new();
+    for bid in bids {
+        if leading.len() == count as usize {
+            break;
+        }
+        if !leading.contains(bid.bidder.user_id()) {
+            leading.push(bid.bidder.user_id().clone());
+        }
+    }
+    leading
+}
+
+// The new bid is the standing bid, so evicting from the end only drops older and lower bids.
+// What the evicted bids still decide is kept with the newest ones: the first bid, for when the bidding started,
+// and the standing bid of each bidder that takes part in a final round.
+fn evict_history(bids: &mut Vec<Bid>, max_history: usize, final_round: Option<FinalRound>) {
+    if bids.len() <= max_history {
+        return;
+    }
+    let mut leading = final_round.map_or_else(Vec::new, |round| leading_bidders(bids, round.bidders));
+    let first_bid = bids.len() - 1;
+    let mut index = 0;
+    bids.retain(|bid| {
+        let keep = index < max_history || index == first_bid || leading.contains(bid.bidder.user_id());
+        // Only the standing bid of a leading bidder, the highest one
+        leading.retain(|user_id| user_id != bid.bidder.user_id());
+        index += 1;
+        keep
+    });
+}
+
+// The sealed bids highest first, the earlier of equal bids first, ahead of the open bids,
+// so that the first bid is the winning one as for an auction without a final round
+fn ranked_bids(sealed_bids: &[Bid], bids: Vec<Bid>) -> Vec<Bid> {
+    let mut ranked = sealed_bids.to_vec();
+    ranked.sort_by(|a, b| b.bid_amount.cmp(&a.bid_amount).then(a.at.cmp(&b.at)));
+    ranked.extend(bids);
+    ranked
+}
+
+impl TimedAscendingState {
+    // The auction is kept open for the clock skew grace after the expiry, for bids as well as for
+    // the sweeps and views, so that a sweep cannot end it before a late bid inside the grace arrives
+    fn inc_in_place(&mut self, now: OffsetDateTime, grace: Duration) {
         match self {
             TimedAscendingState::AwaitingStart { start, starting_expiry, options } => {
                 if now > *start {
-                    if now < *starting_expiry {
+                    if now < *starting_expiry + grace {
                         // AwaitingStart -> OnGoing
-                        TimedAscendingState::OnGoing {
+                        *self = TimedAscendingState::OnGoing {
                             bids: Vec::new(),
                             next_expiry: *starting_expiry,
                             options: options.clone(),
-                        }
+                            extensions: 0,
+                        };
                     } else {
                         // AwaitingStart -> HasEnded
-                        TimedAscendingState::HasEnded {
+                        *self = TimedAscendingState::HasEnded {
                             bids: Vec::new(),
                             expiry: *starting_expiry,
                             options: options.clone(),
-                        }
+                        };
                     }
-                } else {
-                    // AwaitingStart -> AwaitingStart
-                    self.clone()
                 }
+                // else AwaitingStart -> AwaitingStart
             },
-            TimedAscendingState::OnGoing { bids, next_expiry, options } => {
-                if now < *next_expiry {
-                    // OnGoing -> OnGoing
-                    self.clone()
-                } else {
-                    // OnGoing -> HasEnded
-                    TimedAscendingState::HasEnded {
-                        bids: bids.clone(),
-                        expiry: *next_expiry,
-                        options: options.clone(),
+            TimedAscendingState::OnGoing { bids, next_expiry, options, .. } => {
+                // The auction ends at the expiry instant, see State
+                if now >= *next_expiry + grace {
+                    match options.final_round {
+                        Some(round) if !bids.is_empty() => {
+                            // OnGoing -> FinalRound
+                            *self = TimedAscendingState::FinalRound {
+                                eligible: leading_bidders(bids, round.bidders),
+                                bids: std::mem::take(bids),
+                                sealed_bids: Vec::new(),
+                                expiry: *next_expiry + round.duration,
+                                options: options.clone(),
+                            };
+                            // The round may have passed as well
+                            self.inc_in_place(now, grace);
+                        },
+                        _ => {
+                            // OnGoing -> HasEnded
+                            *self = TimedAscendingState::HasEnded {
+                                bids: std::mem::take(bids),
+                                expiry: *next_expiry,
+                                options: options.clone(),
+                            };
+                        },
                     }
                 }
+                // else OnGoing -> OnGoing
+            },
+            TimedAscendingState::FinalRound { bids, sealed_bids, expiry, options, .. } => {
+                if now >= *expiry + grace {
+                    // FinalRound -> HasEnded
+                    *self = TimedAscendingState::HasEnded {
+                        bids: ranked_bids(sealed_bids, std::mem::take(bids)),
+                        expiry: *expiry,
+                        options: options.clone(),
+                    };
+                }
             },
             TimedAscendingState::HasEnded { .. } => {
                 // HasEnded -> HasEnded
-                self.clone()
             }
         }
     }
 
-    fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) {
+    // The end as scheduled now, after the final round when the open bidding leads into one
+    fn end(&self) -> Option<OffsetDateTime> {
+        match self {
+            TimedAscendingState::AwaitingStart { starting_expiry, .. } => Some(*starting_expiry),
+            TimedAscendingState::OnGoing { bids, next_expiry, options: Options { final_round: Some(round), .. }, .. } if !bids.is_empty() => {
+                Some(*next_expiry + round.duration)
+            },
+            TimedAscendingState::OnGoing { next_expiry, .. } => Some(*next_expiry),
+            TimedAscendingState::FinalRound { expiry, .. } => Some(*expiry),
+            TimedAscendingState::HasEnded { .. } => None,
+        }
+    }
+
+    fn clock_skew_grace(&self) -> Duration {
+        match self {
+            TimedAscendingState::AwaitingStart { options, .. } |
+            TimedAscendingState::OnGoing { options, .. } |
+            TimedAscendingState::FinalRound { options, .. } |
+            TimedAscendingState::HasEnded { options, .. } => options.clock_skew_grace,
+        }
+    }
+
+    fn close_in_place(&mut self, now: OffsetDateTime) {
+        match self {
+            TimedAscendingState::AwaitingStart { options, .. } => {
+                // AwaitingStart -> HasEnded
+                *self = TimedAscendingState::HasEnded {
+                    bids: Vec::new(),
+                    expiry: now,
+                    options: options.clone(),
+                };
+            },
+            TimedAscendingState::OnGoing { bids, options, .. } => {
+                // OnGoing -> HasEnded
+                *self = TimedAscendingState::HasEnded {
+                    bids: std::mem::take(bids),
+                    expiry: now,
+                    options: options.clone(),
+                };
+            },
+            TimedAscendingState::FinalRound { bids, sealed_bids, options, .. } => {
+                // FinalRound -> HasEnded
+                *self = TimedAscendingState::HasEnded {
+                    bids: ranked_bids(sealed_bids, std::mem::take(bids)),
+                    expiry: now,
+                    options: options.clone(),
+                };
+            },
+            TimedAscendingState::HasEnded { .. } => {}
+        }
+    }
+
+    fn add_bid_in_place(&mut self, bid: Bid) -> Result<(), Errors> {
         let now = bid.at;
         let auction_id = bid.for_auction;
         let bid_amount = bid.bid_amount;
-        
-        let next = self.inc(now);
-        
-        match &next {
+
+        self.inc_in_place(now, self.clock_skew_grace());
+
+        match self {
             TimedAscendingState::AwaitingStart { .. } => {
-                (next, Err(Errors::AuctionHasNotStarted(auction_id)))
+                Err(Errors::AuctionHasNotStarted(auction_id))
             },
-            TimedAscendingState::OnGoing { bids, next_expiry, options } => {
-                let mut new_bids = bids.clone();
-                let new_expiry = std::cmp::max(
-                    *next_expiry,
-                    now + options.time_frame
-                );
-                
-                if bids.is_empty() {
-                    // First bid is always accepted
-                    new_bids.insert(0, bid);
-                    (
-                        TimedAscendingState::OnGoing {
-                            bids: new_bids,
-                            next_expiry: new_expiry,
-                            options: options.clone(),
-                        },
-                        Ok(())
-                    )
-                } else {
-                    // Check if the bid is high enough
-                    let highest_bid = &bids[0];
+            TimedAscendingState::OnGoing { bids, next_expiry, options, extensions } => {
+                if let Some(max_bid) = options.max_bid.filter(|max_bid| bid_amount > max_bid.value()) {
+                    return Err(Errors::BidExceedsMaximum(max_bid));
+                }
+
+                // First bid is always accepted
+                if let Some(highest_bid) = bids.first() {
+                    if !options.allow_self_outbid && highest_bid.bidder.user_id() == bid.bidder.user_id() {
+                        return Err(Errors::AlreadyHighestBidder);
+                    }
+
                     let highest_amount = highest_bid.bid_amount;
-                    let min_raise = options.min_raise;
-                    
-                    // You cannot bid lower than the current bid + minimum raise
-                    if bid_amount >= (highest_amount + min_raise) {
-                        new_bids.insert(0, bid);
-                        (
-                            TimedAscendingState::OnGoing {
-                                bids: new_bids,
-                                next_expiry: new_expiry,
-                                options: options.clone(),
-                            },
-                            Ok(())
-                        )
-                    } else {
-                        (next, Err(Errors::MustPlaceBidOverHighestBid(highest_amount)))
+                    let min_raise = options.min_raise.value();
+
+                    // You cannot bid lower than the current bid + minimum raise,
+                    // nor match the current bid when increases have to be strict
+                    if bid_amount < (highest_amount + min_raise) || (options.strict_increase && bid_amount <= highest_amount) {
+                        return Err(Errors::MustPlaceBidOverHighestBid(highest_amount));
+                    }
+                }
+
+                // Once the extensions are used up bids no longer move the expiry
+                let extended_expiry = now + options.time_frame;
+                let may_extend = options.max_extensions.is_none_or(|max| *extensions < max);
+                if extended_expiry > *next_expiry && may_extend {
+                    *next_expiry = extended_expiry;
+                    *extensions += 1;
+                }
+                bids.insert(0, bid);
+                if let Some(max_history) = options.max_history {
+                    evict_history(bids, max_history.max(1), options.final_round);
+                }
+                Ok(())
+            },
+            TimedAscendingState::FinalRound { bids, sealed_bids, eligible, options, .. } => {
+                let bidder = bid.bidder.user_id();
+                if !eligible.contains(bidder) {
+                    return Err(Errors::NotInFinalRound(bidder.clone()));
+                }
+                if sealed_bids.iter().any(|sealed| sealed.bidder.user_id() == bidder) {
+                    return Err(Errors::AlreadyPlacedBid);
+                }
+                if let Some(max_bid) = options.max_bid.filter(|max_bid| bid_amount > max_bid.value()) {
+                    return Err(Errors::BidExceedsMaximum(max_bid));
+                }
+
+                // A sealed bid has to beat the open bidding the same way an open bid would
+                if let Some(highest_bid) = bids.first() {
+                    let highest_amount = highest_bid.bid_amount;
+                    if bid_amount < (highest_amount + options.min_raise.value()) || (options.strict_increase && bid_amount <= highest_amount) {
+                        return Err(Errors::MustPlaceBidOverHighestBid(highest_amount));
                     }
                 }
+                sealed_bids.push(bid);
+                Ok(())
             },
             TimedAscendingState::HasEnded { .. } => {
-                (next, Err(Errors::AuctionHasEnded(auction_id)))
+                Err(Errors::AuctionHasEnded(auction_id))
             }
         }
     }
+}
+
+impl State for TimedAscendingState {
+    fn inc(&self, now: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        next.inc_in_place(now, next.clock_skew_grace());
+        next
+    }
+
+    fn add_bid(&self, bid: Bid) -> (Self, Result<(), Errors>) {
+        let mut next = self.clone();
+        let result = next.add_bid_in_place(bid);
+        (next, result)
+    }
+
+    fn inc_mut(&mut self, now: OffsetDateTime) {
+        self.inc_in_place(now, self.clock_skew_grace());
+    }
+
+    fn add_bid_mut(&mut self, bid: Bid) -> Result<(), Errors> {
+        self.add_bid_in_place(bid)
+    }
+
+    fn close(&self, now: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        next.close_in_place(now);
+        next
+    }
+
+    fn extend(&self, new_expiry: OffsetDateTime) -> Self {
+        let mut next = self.clone();
+        match &mut next {
+            // The new expiry is the end, so the open bidding closes a final round earlier
+            TimedAscendingState::OnGoing { bids, next_expiry, options: Options { final_round: Some(round), .. }, .. } if !bids.is_empty() => {
+                *next_expiry = new_expiry - round.duration;
+            },
+            TimedAscendingState::AwaitingStart { starting_expiry: expiry, .. } |
+            TimedAscendingState::OnGoing { next_expiry: expiry, .. } |
+            TimedAscendingState::FinalRound { expiry, .. } => *expiry = new_expiry,
+            TimedAscendingState::HasEnded { .. } => {}
+        }
+        next
+    }
 
+    // Apply the bids to a single copy of the state rather than cloning the bids for every bid
+    fn apply_bids(&self, bids: impl IntoIterator<Item = Bid>) -> (Self, Vec<Result<(), Errors>>) {
+        let mut next = self.clone();
+        let results = bids.into_iter().map(|bid| next.add_bid_in_place(bid)).collect();
+        (next, results)
+    }
+
+    /// Newest first, only the kept bids when the history is capped by max_history.
+    /// Sealed bids of a final round are left out until the auction has ended, then they come first, highest first.
     fn get_bids(&self) -> Vec<Bid> {
         match self {
             TimedAscendingState::AwaitingStart { .. } => Vec::new(),
-            TimedAscendingState::OnGoing { bids, .. } => bids.clone(),
+            TimedAscendingState::OnGoing { bids, .. } |
+            TimedAscendingState::FinalRound { bids, .. } |
             TimedAscendingState::HasEnded { bids, .. } => bids.clone(),
         }
     }
 
+    fn bids_of(&self, user_id: &UserId) -> Vec<Bid> {
+        let own_sealed_bids = match self {
+            TimedAscendingState::FinalRound { sealed_bids, .. } => sealed_bids.iter().filter(|bid| bid.bidder.user_id() == user_id).cloned().collect(),
+            _ => Vec::new(),
+        };
+        own_sealed_bids.into_iter()
+            .chain(self.get_bids().into_iter().filter(|bid| bid.bidder.user_id() == user_id))
+            .collect()
+    }
+
     fn try_get_amount_and_winner(&self) -> Option<(AmountValue, UserId)> {
         match self {
             TimedAscendingState::HasEnded { bids, options, .. } => {
                 if let Some(bid) = bids.first() {
-                    if options.reserve_price < bid.bid_amount {
+                    if options.reserve_price.value() < bid.bid_amount {
                         return Some((bid.bid_amount, bid.bidder.user_id().clone()));
                     }
                 }
@@ -223,10 +644,76 @@ impl State for TimedAscendingState {
         }
     }
 
+    fn has_started(&self) -> bool {
+        !matches!(self, TimedAscendingState::AwaitingStart { .. })
+    }
+
     fn has_ended(&self) -> bool {
+        matches!(self, TimedAscendingState::HasEnded { .. })
+    }
+
+    fn scheduled_phase_changes(&self) -> Vec<(OffsetDateTime, &'static str)> {
         match self {
-            TimedAscendingState::HasEnded { .. } => true,
-            _ => false,
+            TimedAscendingState::AwaitingStart { start, starting_expiry, .. } if start < starting_expiry => {
+                vec![(*start, "on_going"), (*starting_expiry, "has_ended")]
+            },
+            TimedAscendingState::AwaitingStart { start, .. } => vec![(*start, "has_ended")],
+            TimedAscendingState::OnGoing { bids, next_expiry, options: Options { final_round: Some(round), .. }, .. } if !bids.is_empty() => {
+                vec![(*next_expiry, "final_round"), (*next_expiry + round.duration, "has_ended")]
+            },
+            TimedAscendingState::OnGoing { next_expiry, .. } => vec![(*next_expiry, "has_ended")],
+            TimedAscendingState::FinalRound { expiry, .. } => vec![(*expiry, "has_ended")],
+            TimedAscendingState::HasEnded { .. } => Vec::new(),
+        }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self {
+            TimedAscendingState::AwaitingStart { .. } => "awaiting_start",
+            TimedAscendingState::OnGoing { .. } => "on_going",
+            TimedAscendingState::FinalRound { .. } => "final_round",
+            TimedAscendingState::HasEnded { .. } => "has_ended",
+        }
+    }
+
+    fn time_left(&self, now: OffsetDateTime) -> Option<Duration> {
+        let end = self.end()?;
+        if now < end {
+            Some(end - now)
+        } else {
+            None
+        }
+    }
+
+    fn first_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            TimedAscendingState::AwaitingStart { .. } => None,
+            TimedAscendingState::OnGoing { bids, .. } |
+            TimedAscendingState::FinalRound { bids, .. } |
+            TimedAscendingState::HasEnded { bids, .. } => bids.iter().map(|bid| bid.at).min(),
+        }
+    }
+
+    fn last_bid_time(&self) -> Option<OffsetDateTime> {
+        match self {
+            TimedAscendingState::AwaitingStart { .. } => None,
+            TimedAscendingState::OnGoing { bids, .. } |
+            TimedAscendingState::FinalRound { bids, .. } |
+            TimedAscendingState::HasEnded { bids, .. } => bids.iter().map(|bid| bid.at).max(),
+        }
+    }
+
+    fn outcome(&self) -> AuctionOutcome {
+        match self {
+            TimedAscendingState::HasEnded { bids, options, .. } => match bids.first() {
+                None => AuctionOutcome::NoBids,
+                Some(bid) if options.reserve_price.value() < bid.bid_amount => AuctionOutcome::Sold {
+                    price: bid.bid_amount,
+                    winner: bid.bidder.user_id().clone(),
+                },
+                Some(bid) => AuctionOutcome::ReserveNotMet { highest: bid.bid_amount },
+            },
+            _ => AuctionOutcome::Ongoing,
         }
     }
 }
@@ -0,0 +1,89 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+use super::auctions::AuctionState;
+use super::core::{AuctionId, UserId};
+use super::states::State;
+use super::Repository;
+
+/// A way in which the live repository differs from the one replayed from the command log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum Discrepancy {
+    /// The auction is live but was never added by a logged command
+    MissingFromLog {
+        #[serde(rename = "auctionId")]
+        auction_id: AuctionId,
+    },
+    /// A logged command added the auction but it is not live
+    MissingFromLive {
+        #[serde(rename = "auctionId")]
+        auction_id: AuctionId,
+    },
+    Winner {
+        #[serde(rename = "auctionId")]
+        auction_id: AuctionId,
+        live: Option<UserId>,
+        replayed: Option<UserId>,
+    },
+    BidCount {
+        #[serde(rename = "auctionId")]
+        auction_id: AuctionId,
+        live: usize,
+        replayed: usize,
+    },
+    /// The winner and bids agree but something else in the state does not
+    State {
+        #[serde(rename = "auctionId")]
+        auction_id: AuctionId,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConsistencyReport {
+    pub consistent: bool,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+// Compare the live repository to one replayed from the command log, ordered by auction id.
+// Both are brought up to now first, so auctions that have only ended by the passing of time agree.
+pub fn compare_replayed(live: &Repository, replayed: &Repository, now: OffsetDateTime) -> ConsistencyReport {
+    let mut auction_ids: Vec<AuctionId> = live.iter_auctions().chain(replayed.iter_auctions())
+        .map(|auction| auction.auction_id)
+        .collect();
+    auction_ids.sort();
+    auction_ids.dedup();
+
+    let discrepancies: Vec<Discrepancy> = auction_ids.into_iter()
+        .flat_map(|auction_id| match (live.get_state(auction_id), replayed.get_state(auction_id)) {
+            (Some(_), None) => vec![Discrepancy::MissingFromLog { auction_id }],
+            (None, Some(_)) => vec![Discrepancy::MissingFromLive { auction_id }],
+            (Some(live), Some(replayed)) => state_discrepancies(auction_id, &live.inc(now), &replayed.inc(now)),
+            (None, None) => Vec::new(),
+        })
+        .collect();
+
+    ConsistencyReport {
+        consistent: discrepancies.is_empty(),
+        discrepancies,
+    }
+}
+
+fn state_discrepancies(auction_id: AuctionId, live: &AuctionState, replayed: &AuctionState) -> Vec<Discrepancy> {
+    if live == replayed {
+        return Vec::new();
+    }
+
+    let mut discrepancies = Vec::new();
+    let winner = |state: &AuctionState| state.try_get_amount_and_winner().map(|(_, winner)| winner);
+    if winner(live) != winner(replayed) {
+        discrepancies.push(Discrepancy::Winner { auction_id, live: winner(live), replayed: winner(replayed) });
+    }
+    let (live_bids, replayed_bids) = (live.get_bids().len(), replayed.get_bids().len());
+    if live_bids != replayed_bids {
+        discrepancies.push(Discrepancy::BidCount { auction_id, live: live_bids, replayed: replayed_bids });
+    }
+    if discrepancies.is_empty() {
+        discrepancies.push(Discrepancy::State { auction_id });
+    }
+    discrepancies
+}
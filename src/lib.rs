@@ -1,4 +1,5 @@
 // src/lib.rs
+pub mod config;
 pub mod domain;
 pub mod money;
 pub mod persistence;
@@ -1,29 +1,105 @@
 use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpServer};
-use auction_site::web::app::{configure_app, init_app_state};
+use auction_site::config::{parse_bind_addr, parse_port, SiteConfig};
+use auction_site::domain::EventBus;
+use auction_site::persistence::audit::AuditLog;
+use auction_site::persistence::event_log::EventLog;
+use auction_site::persistence::snapshot::read_snapshot;
+use auction_site::web::rate_limit::RateLimiter;
+use auction_site::web::shill::ShillGuard;
+use auction_site::web::types::{Blocklist, Watchlist};
+use auction_site::web::app::{configure_app, init_app_state, init_app_state_from, run_sweeps, run_until_stopped};
 use log::info;
+use std::path::PathBuf;
 
 // Main application
-pub async fn run_app(port: u16) -> std::io::Result<()> {
+pub async fn run_app(
+    bind_addr: &str,
+    port: u16,
+    config: SiteConfig,
+    snapshot_path: Option<PathBuf>,
+    audit_log_path: Option<PathBuf>,
+    event_log_path: Option<PathBuf>
+) -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "actix_web=info");
     env_logger::init();
 
-    let app_state = init_app_state();
+    let restored = match &snapshot_path {
+        Some(path) if path.exists() => {
+            info!("Restoring snapshot from {}", path.display());
+            Some(read_snapshot(path).map_err(std::io::Error::other)?)
+        },
+        _ => None,
+    };
+    let app_state = restored.clone().map_or_else(init_app_state, init_app_state_from);
 
-    info!("Starting server on port {}", port);
+    info!("Starting server on {}:{}", bind_addr, port);
 
-    HttpServer::new(move || {
+    let rate_limiter = web::Data::new(RateLimiter::new(config.bid_burst, config.bid_refill_interval));
+    let event_bus = web::Data::new(EventBus::default());
+    let blocklist = web::Data::new(Blocklist::default());
+    let watchlist = web::Data::new(Watchlist::default());
+    let shill_guard = web::Data::new(ShillGuard::new());
+    let audit_log = audit_log_path.map(|path| {
+        info!("Writing audit records to {}", path.display());
+        let audit_log = AuditLog::new(path);
+        // The log is verified from the restored snapshot on, it may have been started after the first auctions
+        match restored {
+            Some(repository) => audit_log.starting_from(repository),
+            None => Ok(audit_log),
+        }.map(web::Data::new)
+    }).transpose().map_err(std::io::Error::other)?;
+    let event_log = event_log_path.map(|path| {
+        info!("Writing events to {}", path.display());
+        web::Data::new(EventLog::new(path))
+    });
+    actix_web::rt::spawn(run_sweeps(
+        app_state.clone(),
+        config.sweep_interval,
+        Some(event_bus.clone()),
+        event_log.clone(),
+        Some(shill_guard.clone()),
+    ));
+    let server_state = app_state.clone();
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(app_state.clone()))
+            .app_data(web::Data::new(server_state.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(rate_limiter.clone())
+            .app_data(event_bus.clone())
+            .app_data(blocklist.clone())
+            .app_data(watchlist.clone())
+            .app_data(shill_guard.clone())
+            .configure(|cfg| {
+                if let Some(audit_log) = &audit_log {
+                    cfg.app_data(audit_log.clone());
+                }
+                if let Some(event_log) = &event_log {
+                    cfg.app_data(event_log.clone());
+                }
+            })
             .wrap(Logger::default())
             .configure(configure_app)
     })
-    .bind(("127.0.0.1", port))?
-    .run()
-    .await
+    .bind((bind_addr, port))?
+    .run();
+
+    run_until_stopped(server, app_state, snapshot_path).await
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    run_app(8080).await
+    let invalid_input = |e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e);
+    let bind_addr = parse_bind_addr(std::env::var("BIND_ADDR").ok().as_deref()).map_err(invalid_input)?;
+    let port = parse_port(std::env::var("PORT").ok().as_deref()).map_err(invalid_input)?;
+    let config = SiteConfig::from_env(|name| std::env::var(name).ok()).map_err(invalid_input)?;
+
+    run_app(
+        &bind_addr,
+        port,
+        config,
+        std::env::var_os("SNAPSHOT_PATH").map(PathBuf::from),
+        std::env::var_os("AUDIT_LOG_PATH").map(PathBuf::from),
+        std::env::var_os("EVENT_LOG_PATH").map(PathBuf::from),
+    ).await
 }
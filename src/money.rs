@@ -5,13 +5,40 @@ use std::ops::Add;
 use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Currency {
     VAC, // Virtual auction currency
     SEK, // Swedish Krona
     DKK, // Danish Krone
 }
 
+impl Currency {
+    /// Every supported currency, keep in sync with the variants above
+    pub const ALL: [Currency; 3] = [Currency::VAC, Currency::SEK, Currency::DKK];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Currency::VAC => "Virtual auction currency",
+            Currency::SEK => "Swedish krona",
+            Currency::DKK => "Danish krone",
+        }
+    }
+
+    /// Virtual currencies are not paid out, so no fees are remitted in them
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Currency::VAC)
+    }
+
+    /// Number of digits of the minor unit, e.g. öre for SEK
+    pub fn decimals(&self) -> u8 {
+        match self {
+            Currency::VAC => 0,
+            Currency::SEK => 2,
+            Currency::DKK => 2,
+        }
+    }
+}
+
 impl fmt::Display for Currency {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -30,14 +57,53 @@ impl FromStr for Currency {
             "VAC" => Ok(Currency::VAC),
             "SEK" => Ok(Currency::SEK),
             "DKK" => Ok(Currency::DKK),
-            _ => Err(format!("Unknown currency: {}", s)),
+            _ => match closest_currency(s) {
+                Some(currency) => Err(format!("Unknown currency: {}, did you mean {}?", s, currency)),
+                None => Err(format!("Unknown currency: {}", s)),
+            },
         }
     }
 }
 
+impl TryFrom<&str> for Currency {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Currency::from_str(s)
+    }
+}
+
+// A known currency one letter off the code, ignoring case, as a suggestion for a mistyped code
+fn closest_currency(code: &str) -> Option<Currency> {
+    if code.chars().count() != 3 {
+        return None;
+    }
+    let code = code.to_uppercase();
+    Currency::ALL.into_iter()
+        .map(|currency| (edit_distance(&code, &currency.to_string()), currency))
+        .filter(|(distance, _)| *distance <= 1)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, currency)| currency)
+}
+
+// Levenshtein distance, the number of single character edits between the strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 pub type AmountValue = i64;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Amount {
     currency: Currency,
     value: AmountValue,
@@ -73,12 +139,77 @@ impl Amount {
     pub fn value(&self) -> i64 {
         self.value
     }
+
+    /// Parse a decimal in major units, e.g. "10.50" SEK is 1050 öre
+    pub fn from_major_units(currency: Currency, text: &str) -> Result<Self, String> {
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+            return Err(format!("Invalid decimal amount: {}", text));
+        }
+        let decimals = currency.decimals() as usize;
+        if fraction.len() > decimals {
+            return Err(format!("{} allows at most {} decimals: {}", currency, decimals, text));
+        }
+
+        let minor_units = format!("{}{:0<width$}", whole, fraction, width = decimals);
+        let value = minor_units.parse::<AmountValue>()
+            .map_err(|_| format!("Amount out of range: {}", text))?;
+        Ok(Amount { currency, value })
+    }
+
+    /// Divide into parts that differ by at most one minor unit and sum to the amount,
+    /// the remainder goes to the first parts. No parts gives an empty split.
+    pub fn split(self, parts: u32) -> Vec<Amount> {
+        if parts == 0 {
+            return Vec::new();
+        }
+        let parts = parts as AmountValue;
+        let (share, remainder) = (self.value.div_euclid(parts), self.value.rem_euclid(parts));
+        (0..parts)
+            .map(|part| Amount {
+                currency: self.currency,
+                value: share + if part < remainder { 1 } else { 0 },
+            })
+            .collect()
+    }
+}
+
+/// How a fraction of a minor unit is rounded, jurisdictions differ in what they require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Halves are rounded up, 2.5 becomes 3
+    #[default]
+    HalfUp,
+    /// Halves are rounded to the even neighbour (banker's rounding), 2.5 becomes 2 and 3.5 becomes 4
+    HalfEven,
+    /// Fractions are dropped, 2.9 becomes 2
+    Floor,
+}
+
+impl RoundingMode {
+    /// Divide and round the quotient to a whole number, the divisor must be positive
+    pub fn divide(&self, dividend: i128, divisor: i128) -> i128 {
+        let (quotient, remainder) = (dividend.div_euclid(divisor), dividend.rem_euclid(divisor));
+        let round_up = match self {
+            RoundingMode::HalfUp => remainder * 2 >= divisor,
+            RoundingMode::HalfEven => remainder * 2 > divisor || (remainder * 2 == divisor && quotient % 2 != 0),
+            RoundingMode::Floor => false,
+        };
+        if round_up { quotient + 1 } else { quotient }
+    }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum MoneyError {
     #[error("Cannot add amounts with different currencies")]
     CurrencyMismatch,
+    #[error("{0} is a virtual currency, fees only apply to real currencies")]
+    VirtualCurrency(Currency),
+    #[error("A rate of {0} basis points is more than the whole price")]
+    RateAboveWholePrice(u32),
+    #[error("The amount is out of range")]
+    OutOfRange,
 }
 
 impl Add for Amount {
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use crate::domain::{handle_all, Command, Repository, UserId};
+use super::json_file::{append_json_line, read_json_lines, read_json_lines_from};
+
+/// Who submitted a command, from where, and whether it was handled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(with="time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub actor: UserId,
+    #[serde(rename = "sourceIp")]
+    pub source_ip: Option<String>,
+    pub command: Command,
+    pub result: Result<(), String>,
+}
+
+// Audit records are appended one per line to the file at the path
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    // Keeps concurrent appends from interleaving
+    lock: Mutex<()>,
+    // The repository restored at startup and the number of records logged before it
+    baseline: Option<(Repository, usize)>,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        AuditLog {
+            path: path.into(),
+            lock: Mutex::new(()),
+            baseline: None,
+        }
+    }
+
+    /// Replays start from the repository restored at startup, e.g. from a snapshot, with only the records
+    /// appended after it. The earlier records need not hold every command the restored repository has seen.
+    pub fn starting_from(self, repository: Repository) -> Result<Self, String> {
+        let logged = self.read_records()?.len();
+        Ok(AuditLog { baseline: Some((repository, logged)), ..self })
+    }
+
+    pub fn append(&self, record: &AuditRecord) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        append_json_line(&self.path, record)
+    }
+
+    /// The records in the order they were appended, none when nothing has been logged yet
+    pub fn read_records(&self) -> Result<Vec<AuditRecord>, String> {
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        read_json_lines(&self.path)
+    }
+
+    /// Where the log ends now, in bytes. Records are appended whole, so a position is always at the end of one.
+    pub fn position(&self) -> Result<u64, String> {
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(0);
+        }
+        std::fs::metadata(&self.path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| format!("Failed to read file metadata: {}", e))
+    }
+
+    // The records appended before the position
+    fn read_records_to(&self, position: u64) -> Result<Vec<AuditRecord>, String> {
+        if position == 0 {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&self.path).map_err(|e| format!("Failed to open file: {}", e))?;
+        read_json_lines_from(BufReader::new(file.take(position)))
+    }
+
+    /// Replay the commands that were handled into a fresh repository, or the restored one, failed commands had no effect
+    pub fn replay(&self) -> Result<Repository, String> {
+        self.replay_to(self.position()?)
+    }
+
+    /// Replay as of the position, the records appended since are left out
+    pub fn replay_to(&self, position: u64) -> Result<Repository, String> {
+        let (repository, logged) = self.baseline.clone().unwrap_or_default();
+        let commands = self.read_records_to(position)?.into_iter()
+            .skip(logged)
+            .filter(|record| record.result.is_ok())
+            .map(|record| record.command);
+        let (_, repository) = handle_all(commands, repository);
+        Ok(repository)
+    }
+}
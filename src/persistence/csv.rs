@@ -0,0 +1,38 @@
+use std::io::Write;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use crate::domain::{AuctionOutcome, Repository, State};
+use crate::money::Amount;
+
+const HEADER: [&str; 9] = ["id", "title", "seller", "currency", "winner", "price", "bids", "start", "end"];
+
+/// Write one row per ended auction for reporting, winner and price are left empty for an unsold auction.
+/// Auctions are included once they have ended as of `now`, whether or not their stored state has been moved past the end.
+pub fn export_results(repository: &Repository, now: OffsetDateTime, writer: impl Write) -> Result<(), csv::Error> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(HEADER)?;
+
+    let ended = repository.iter()
+        .map(|(auction, state)| (auction, state.inc(now)))
+        .filter(|(_, state)| state.has_ended());
+    for (auction, state) in ended {
+        let (winner, price) = match state.outcome() {
+            AuctionOutcome::Sold { price, winner } => (winner, Amount::new(auction.auction_currency, price).to_string()),
+            _ => (String::new(), String::new()),
+        };
+        let format_time = |at: OffsetDateTime| at.format(&Rfc3339).unwrap_or_default();
+        csv_writer.write_record([
+            auction.auction_id.to_string(),
+            auction.title.clone(),
+            auction.seller.user_id().clone(),
+            auction.auction_currency.to_string(),
+            winner,
+            price,
+            state.get_bids().len().to_string(),
+            format_time(auction.starts_at),
+            format_time(auction.expiry),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use crate::domain::Event;
+use super::json_file::{read_events, write_event};
+
+// Events produced by the web api are appended one per line to the file at the path, for downstream consumers
+#[derive(Debug)]
+pub struct EventLog {
+    path: PathBuf,
+    // Keeps concurrent appends from interleaving
+    lock: Mutex<()>,
+}
+
+impl EventLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        EventLog {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn append(&self, event: &Event) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        write_event(&self.path, event)
+    }
+
+    /// The events in the order they were appended, none when nothing has been logged yet
+    pub fn read_events(&self) -> Result<Vec<Event>, String> {
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        read_events(&self.path)
+    }
+}
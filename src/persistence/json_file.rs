@@ -1,24 +1,91 @@
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::{from_str, to_string};
-use crate::domain::commands::Command;
+use crate::domain::commands::{Command, Event};
 
 pub fn read_commands<P: AsRef<Path>>(path: P) -> Result<Vec<Command>, String> {
     let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
+    read_commands_from(BufReader::new(file))
+}
 
-    let mut commands = Vec::new();
+// Each line holds a JSON array of commands
+pub fn read_commands_from<R: BufRead>(reader: R) -> Result<Vec<Command>, String> {
+    commands_in(reader).collect()
+}
 
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        let parsed: Vec<Command> = from_str(&line)
-            .map_err(|e| format!("Failed to parse command: {}", e))?;
+// Read at most limit commands after skipping offset commands, along with whether more follow.
+// Lines are read one at a time so a page of a large log does not load the whole log.
+pub fn read_commands_paged<P: AsRef<Path>>(path: P, offset: usize, limit: usize) -> Result<(Vec<Command>, bool), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut commands = commands_in(BufReader::new(file));
 
-        commands.extend(parsed);
+    for skipped in commands.by_ref().take(offset) {
+        skipped?;
     }
+    let page = commands.by_ref().take(limit).collect::<Result<Vec<Command>, String>>()?;
+    let has_more = commands.next().transpose()?.is_some();
+
+    Ok((page, has_more))
+}
+
+fn commands_in<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Command, String>> {
+    reader.lines().flat_map(|line| {
+        let parsed = line
+            .map_err(|e| format!("Failed to read line: {}", e))
+            .and_then(|line| from_str::<Vec<Command>>(&line).map_err(|e| format!("Failed to parse command: {}", e)));
+        match parsed {
+            Ok(commands) => commands.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        }
+    })
+}
+
+pub fn read_commands_stdin() -> Result<Vec<Command>, String> {
+    read_commands_from(std::io::stdin().lock())
+}
+
+// Read a file with a JSON value on each line
+pub fn read_json_lines<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> Result<Vec<T>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    read_json_lines_from(BufReader::new(file))
+}
+
+pub fn read_json_lines_from<R: BufRead, T: DeserializeOwned>(reader: R) -> Result<Vec<T>, String> {
+    reader.lines()
+        .map(|line| {
+            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+            from_str(&line).map_err(|e| format!("Failed to parse line: {}", e))
+        })
+        .collect()
+}
+
+// Append the value as a line of JSON, creating the file if needed
+pub fn append_json_line<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open file for appending: {}", e))?;
+
+    let mut json = to_string(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    json.push('\n');
+
+    file.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write to file: {}", e))?;
+
+    Ok(())
+}
+
+// Events are kept apart from the commands, one event on each line
+pub fn write_event<P: AsRef<Path>>(path: P, event: &Event) -> Result<(), String> {
+    append_json_line(path, event)
+}
 
-    Ok(commands)
+pub fn read_events<P: AsRef<Path>>(path: P) -> Result<Vec<Event>, String> {
+    read_json_lines(path)
 }
 
 pub fn write_commands<P: AsRef<Path>>(path: P, commands: &[Command]) -> Result<(), String> {
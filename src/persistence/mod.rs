@@ -1 +1,5 @@
+pub mod audit;
+pub mod csv;
+pub mod event_log;
 pub mod json_file;
+pub mod snapshot;
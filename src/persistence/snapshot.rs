@@ -0,0 +1,62 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_reader, to_string};
+use crate::domain::{Auction, AuctionState, Repository};
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    auction: Auction,
+    state: AuctionState,
+    // Snapshots written before versions were kept restore at version 0
+    #[serde(default)]
+    version: u64,
+    #[serde(rename = "startReported", default)]
+    start_reported: bool,
+}
+
+pub fn read_snapshot<P: AsRef<Path>>(path: P) -> Result<Repository, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open snapshot: {}", e))?;
+    let reader = BufReader::new(file);
+
+    let entries: Vec<SnapshotEntry> = from_reader(reader)
+        .map_err(|e| format!("Failed to parse snapshot: {}", e))?;
+
+    let mut repository = Repository::new();
+    for entry in entries {
+        let auction_id = entry.auction.auction_id;
+        repository.insert(entry.auction, entry.state);
+        repository.set_version(auction_id, entry.version);
+        if entry.start_reported {
+            repository.mark_start_reported(auction_id);
+        }
+    }
+    Ok(repository)
+}
+
+pub fn write_snapshot<P: AsRef<Path>>(path: P, repository: &Repository) -> Result<(), String> {
+    let entries: Vec<SnapshotEntry> = repository
+        .iter()
+        .map(|(auction, state)| SnapshotEntry {
+            auction: auction.clone(),
+            state: state.clone(),
+            version: repository.version(auction.auction_id),
+            start_reported: repository.is_start_reported(auction.auction_id),
+        })
+        .collect();
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open snapshot for writing: {}", e))?;
+
+    let json = to_string(&entries).map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+
+    file.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+    Ok(())
+}
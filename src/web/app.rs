@@ -1,73 +1,568 @@
-use actix_web::{web, HttpRequest, HttpResponse, Result};
+use actix_web::body::MessageBody;
+use actix_web::dev::{Server, ServiceRequest, ServiceResponse};
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::header::{self, ContentType};
+use actix_web::middleware::{from_fn, Next};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError, Result};
 use base64::{Engine as _, engine::general_purpose};
+use futures_util::future::ready;
+use futures_util::stream::{self, StreamExt};
 use serde_json::Value;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
+use time::format_description::well_known::Rfc3339;
+use time_tz::{timezones, OffsetDateTimeExt, Tz};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Instant;
+use log::{error, info};
 
-use crate::domain::{auctions, AuctionId, Bid, Command, User, handle};
-use crate::domain::states::State;
-use crate::money::Amount;
-use super::types::{AddAuctionRequest, ApiError, AppState, AuctionBid, AuctionDetail, AuctionItem, BidRequest};
+use crate::config::SiteConfig;
+use crate::domain::{
+    auction_phase, auctions_ending_between, AuctionPhase, bids_by, validate_expected_version, validate_auction_duration, validate_min_raise, validate_bid_currency, validate_amount_within_ceiling, validate_currency_allowed, validate_seller_auction_limit, validate_bidder_not_suspended, Auction, AuctionId,
+    AuctionState, AuctionType, Bid, Command, Errors, Event, EventBus, HandleError, Lang, User, UserId, add_bid_to, handle_mut,
+    compare_replayed, repository_stats, sweep_ended, sweep_started,
+};
+use crate::domain::states::{AuctionOutcome, State};
+use crate::domain::Repository;
+use crate::money::{Amount, Currency};
+use crate::persistence::audit::{AuditLog, AuditRecord};
+use crate::persistence::event_log::EventLog;
+use crate::persistence::snapshot::write_snapshot;
+use super::errors::AuthError;
+use super::lock::lock_recovering;
+use super::openapi::openapi_document;
+use super::rate_limit::RateLimiter;
+use super::shill::ShillGuard;
+use super::types::{
+    AddAuctionRequest, ApiError, AppState, AuctionBid, AuctionValidation, AuctionWinner, AuctionsQuery, BidPosition, BidPreview, BidResult, BidQuery, BuyerBidItem, Blocklist, AuctionDetail, SuspensionStatus, Watchlist, WatchStatus, AuctionItem, BidRequest, CurrencyItem,
+    EndingSoonQuery, SellerAuctionItem, SellerQuery, TimeZoneQuery, AUCTION_ITEM_FIELDS,
+};
+
+const DEFAULT_ENDING_SOON_MINUTES: i64 = 60;
 
 // Initialize application state
 pub fn init_app_state() -> AppState {
-    Arc::new(Mutex::new(HashMap::new()))
+    Arc::new(Mutex::new(Repository::new()))
+}
+
+// Initialize application state from a previously stored repository
+pub fn init_app_state_from(repository: Repository) -> AppState {
+    Arc::new(Mutex::new(repository))
+}
+
+// Run the server until it stops and flush a final snapshot of the repository.
+// Actix stops gracefully on SIGTERM/SIGINT, waiting for in-flight requests to complete.
+pub async fn run_until_stopped<P: AsRef<Path>>(
+    server: Server,
+    app_state: AppState,
+    snapshot_path: Option<P>
+) -> std::io::Result<()> {
+    server.await?;
+
+    if let Some(path) = snapshot_path {
+        let repository = lock_recovering(&app_state);
+        write_snapshot(&path, &repository).map_err(std::io::Error::other)?;
+        info!("Wrote snapshot to {}", path.as_ref().display());
+    }
+
+    Ok(())
+}
+
+// Report the auctions that have started and ended as of now to subscribers and the event log,
+// an auction that starts and ends within one interval is reported as started first
+pub fn sweep_once(
+    app_state: &AppState,
+    now: OffsetDateTime,
+    event_bus: &Option<web::Data<EventBus>>,
+    event_log: &Option<web::Data<EventLog>>,
+    shill_guard: &Option<web::Data<ShillGuard>>
+) {
+    let events = {
+        let mut repository = lock_recovering(app_state);
+        let mut events = sweep_started(&mut repository, now);
+        events.extend(sweep_ended(&mut repository, now));
+        if let Some(shill_guard) = shill_guard {
+            shill_guard.forget_ended(&repository);
+        }
+        events
+    };
+    // Published once the lock is released
+    for event in events {
+        publish(&event, event_bus, event_log);
+    }
+}
+
+// Sweep at every interval for as long as the server runs, spawn it next to the server
+pub async fn run_sweeps(
+    app_state: AppState,
+    interval: std::time::Duration,
+    event_bus: Option<web::Data<EventBus>>,
+    event_log: Option<web::Data<EventLog>>,
+    shill_guard: Option<web::Data<ShillGuard>>
+) {
+    let mut ticks = actix_web::rt::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        sweep_once(&app_state, OffsetDateTime::now_utc(), &event_bus, &event_log, &shill_guard);
+    }
 }
 
 // Read x-jwt-payload header and extract user information
-fn get_auth_user(req: &HttpRequest) -> Option<User> {
-    let auth_header = req.headers().get("x-jwt-payload")?;
-    let auth_str = auth_header.to_str().ok()?;
+fn get_auth_user(req: &HttpRequest) -> std::result::Result<User, AuthError> {
+    let auth_str = req.headers().get("x-jwt-payload")
+        .map(|header| header.to_str().map_err(|_| AuthError::BadBase64))
+        .transpose()?
+        .filter(|value| !value.trim().is_empty())
+        .ok_or(AuthError::MissingToken)?;
 
     // Decode base64
-    let decoded = general_purpose::STANDARD.decode(auth_str).ok()?;
-    let json_str = String::from_utf8(decoded).ok()?;
+    let decoded = general_purpose::STANDARD.decode(auth_str.trim()).map_err(|_| AuthError::BadBase64)?;
 
     // Parse JSON
-    let json: Value = serde_json::from_str(&json_str).ok()?;
+    let json: Value = serde_json::from_slice(&decoded).map_err(|_| AuthError::BadJson)?;
 
     // Extract user fields
-    let sub = json.get("sub")?.as_str()?;
-    let u_typ = json.get("u_typ")?.as_str()?;
+    let claim = |name: &str| json.get(name).and_then(Value::as_str).ok_or(AuthError::MissingClaim);
+    let sub = claim("sub")?;
 
-    if u_typ == "0" {
-        let name = json.get("name")?.as_str()?;
-        Some(User::BuyerOrSeller {
+    match claim("u_typ")? {
+        "0" => Ok(User::BuyerOrSeller {
             user_id: sub.to_string(),
-            name: name.to_string(),
-        })
-    } else if u_typ == "1" {
-        Some(User::Support {
+            name: claim("name")?.to_string(),
+        }),
+        "1" => Ok(User::Support {
             user_id: sub.to_string(),
-        })
-    } else {
-        None
+        }),
+        _ => Err(AuthError::MissingClaim),
     }
 }
 
-// Middleware to require authentication
+// Middleware to require authentication, a missing or malformed header is a 401 with the reason as code
 async fn with_auth<F>(req: HttpRequest, f: F) -> Result<HttpResponse>
 where
     F: FnOnce(User) -> Result<HttpResponse>
 {
-    match get_auth_user(&req) {
-        Some(user) => {
-            let result = f(user)?;
-            Ok(result)
+    let user = get_auth_user(&req)?;
+    f(user)
+}
+
+// The language of error messages, from the Accept-Language header
+fn accept_language(req: &HttpRequest) -> Lang {
+    req.headers().get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Lang::from_accept_language)
+        .unwrap_or_default()
+}
+
+// Domain errors are rendered in English as ResponseError has no request to read the language of,
+// so the body of a domain error is rendered again in the language the client asked for
+async fn localize_errors(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<impl MessageBody>> {
+    let lang = accept_language(req.request());
+    let res = next.call(req).await?;
+
+    let localized = res.response().error()
+        .filter(|_| lang != Lang::En)
+        .and_then(|err| err.as_error::<Errors>()
+            .or_else(|| err.as_error::<HandleError>().map(|HandleError::AuctionError(err)| err)))
+        .map(|err| ApiError::localized(err, lang));
+    match localized {
+        Some(error) => {
+            let response = HttpResponse::build(res.status()).json(error);
+            Ok(res.into_response(response).map_into_right_body())
         },
-        None => {
-            Ok(HttpResponse::Unauthorized().body("Unauthorized"))
+        None => Ok(res.map_into_left_body()),
+    }
+}
+
+// Auctions of the seller, ordered by id, looked up in the index
+fn seller_auctions<'a>(repository: &'a Repository, seller: &UserId) -> Vec<(&'a Auction, &'a AuctionState)> {
+    let mut ids: Vec<AuctionId> = repository.index().by_seller(seller)
+        .map(|ids| ids.iter().copied().collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids.into_iter().filter_map(|id| repository.get(id)).collect()
+}
+
+// Auctions of the seller and in the phase of the query when given, ordered by id.
+// The index narrows the auctions down, an auction whose stored state has ended can only be ended as of now,
+// the others are checked against the time.
+fn matching_auctions<'a>(repository: &'a Repository, query: &AuctionsQuery, now: OffsetDateTime) -> Vec<(&'a Auction, &'a AuctionState)> {
+    let candidates = match (&query.seller, query.status) {
+        (Some(seller), _) => seller_auctions(repository, seller),
+        (None, Some(AuctionPhase::AwaitingStart | AuctionPhase::Ongoing)) => repository.index().unended()
+            .filter_map(|id| repository.get(id))
+            .collect(),
+        (None, _) => repository.iter().collect(),
+    };
+    candidates.into_iter()
+        .filter(|(auction, state)| query.status.is_none_or(|status| auction_phase(auction, state, now) == status))
+        .collect()
+}
+
+// The zone of a tz query parameter, an unknown name is answered with a bad request
+fn time_zone(name: Option<&str>) -> std::result::Result<Option<&'static Tz>, HttpResponse> {
+    match name {
+        None => Ok(None),
+        Some(name) => timezones::get_by_name(name).map(Some).ok_or_else(|| {
+            HttpResponse::BadRequest().json(ApiError {
+                message: format!("Unknown time zone: {}", name),
+                code: None,
+            })
+        }),
+    }
+}
+
+// RFC 3339 in the offset the zone has at that instant, so summer and winter times differ
+fn local_time(at: OffsetDateTime, tz: Option<&Tz>) -> Option<String> {
+    tz.and_then(|tz| at.to_timezone(tz).format(&Rfc3339).ok())
+}
+
+fn auction_item(auction: &Auction, state: &AuctionState, now: OffsetDateTime, tz: Option<&Tz>) -> AuctionItem {
+    AuctionItem {
+        local_starts_at: local_time(auction.starts_at, tz),
+        local_expiry: local_time(auction.expiry, tz),
+        ..AuctionItem::new(auction, state, now)
+    }
+}
+
+// Fields of the fields query parameter, None when all fields are wanted
+fn projected_fields(fields: Option<&str>) -> std::result::Result<Option<Vec<String>>, HttpResponse> {
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+    let fields: Vec<String> = fields.split(',').map(|field| field.trim().to_string()).collect();
+    let unknown: Vec<&str> = fields.iter()
+        .map(String::as_str)
+        .filter(|field| !AUCTION_ITEM_FIELDS.contains(field))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(HttpResponse::BadRequest().json(ApiError {
+            message: format!("Unknown fields: {}, valid fields are: {}", unknown.join(", "), AUCTION_ITEM_FIELDS.join(", ")),
+            code: None,
+        }));
+    }
+    Ok(Some(fields))
+}
+
+// The item with only the given fields, fields without a value such as local times without a tz are left out
+fn project(item: &AuctionItem, fields: &[String]) -> serde_json::Result<serde_json::Map<String, Value>> {
+    let Value::Object(mut object) = serde_json::to_value(item)? else {
+        unreachable!("auction items serialize to objects");
+    };
+    object.retain(|key, _| fields.contains(key));
+    Ok(object)
+}
+
+// Get all auctions, optionally of a single seller or in a given phase
+async fn get_auctions(
+    query: web::Query<AuctionsQuery>,
+    data: web::Data<AppState>
+) -> Result<HttpResponse> {
+    let tz = match time_zone(query.tz.as_deref()) {
+        Ok(tz) => tz,
+        Err(response) => return Ok(response),
+    };
+    let fields = match projected_fields(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(response) => return Ok(response),
+    };
+    let now = OffsetDateTime::now_utc();
+    let app_state = lock_recovering(&data);
+    let auction_list: Vec<AuctionItem> = matching_auctions(&app_state, &query, now)
+        .into_iter()
+        .map(|(auction, state)| auction_item(auction, state, now, tz))
+        .collect();
+
+    match fields {
+        None => Ok(HttpResponse::Ok().json(auction_list)),
+        Some(fields) => {
+            let projected = auction_list.iter()
+                .map(|item| project(item, &fields))
+                .collect::<serde_json::Result<Vec<_>>>()?;
+            Ok(HttpResponse::Ok().json(projected))
+        }
+    }
+}
+
+// Auctions serialized per chunk when streaming the listing
+const STREAM_CHUNK_SIZE: usize = 100;
+
+// Same listing as get_auctions written as a chunked JSON array. Only the ids are collected up front,
+// the items are serialized a chunk at a time so memory stays bounded for large catalogs.
+async fn stream_auctions(
+    query: web::Query<AuctionsQuery>,
+    data: web::Data<AppState>
+) -> Result<HttpResponse> {
+    let tz = match time_zone(query.tz.as_deref()) {
+        Ok(tz) => tz,
+        Err(response) => return Ok(response),
+    };
+    let fields = match projected_fields(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(response) => return Ok(response),
+    };
+    let ids: Vec<Vec<AuctionId>> = {
+        let app_state = lock_recovering(&data);
+        let matching = matching_auctions(&app_state, &query, OffsetDateTime::now_utc());
+        matching.chunks(STREAM_CHUNK_SIZE)
+            .map(|chunk| chunk.iter().map(|(auction, _)| auction.auction_id).collect())
+            .collect()
+    };
+
+    let mut is_first = true;
+    let items = stream::iter(ids).map(move |chunk| {
+        let app_state = lock_recovering(&data);
+        let mut json = Vec::new();
+        for (auction, state) in chunk.iter().filter_map(|id| app_state.get(*id)) {
+            if !is_first {
+                json.push(b',');
+            }
+            is_first = false;
+            let item = auction_item(auction, state, OffsetDateTime::now_utc(), tz);
+            match &fields {
+                None => serde_json::to_writer(&mut json, &item)?,
+                Some(fields) => serde_json::to_writer(&mut json, &project(&item, fields)?)?,
+            }
+        }
+        Ok::<Bytes, actix_web::Error>(Bytes::from(json))
+    });
+    let body = stream::once(ready(Ok(Bytes::from_static(b"["))))
+        .chain(items)
+        .chain(stream::once(ready(Ok(Bytes::from_static(b"]")))));
+
+    Ok(HttpResponse::Ok().content_type(ContentType::json()).streaming(body))
+}
+
+// Get the auctions of the calling seller, support users have to name the seller
+async fn get_seller_auctions(
+    req: HttpRequest,
+    query: web::Query<SellerQuery>,
+    data: web::Data<AppState>
+) -> Result<HttpResponse> {
+    with_auth(req, |user| {
+        let seller_id = match (&user, &query.seller_id) {
+            (User::Support { .. }, Some(seller_id)) => seller_id.clone(),
+            (User::BuyerOrSeller { user_id, .. }, None) => user_id.clone(),
+            (User::BuyerOrSeller { user_id, .. }, Some(seller_id)) if user_id == seller_id => seller_id.clone(),
+            _ => {
+                let error = ApiError {
+                    message: "Not allowed to list auctions of another seller".to_string(),
+                    code: None,
+                };
+                return Ok(HttpResponse::Forbidden().json(error));
+            }
+        };
+
+        let app_state = lock_recovering(&data);
+        let now = OffsetDateTime::now_utc();
+        let seller_auctions: Vec<SellerAuctionItem> = seller_auctions(&app_state, &seller_id)
+            .into_iter()
+            .map(|(auction, auction_state)| {
+                // As of now, so that an expired auction that has not been swept yet shows as ended
+                let current_state = State::inc(auction_state, now);
+                SellerAuctionItem {
+                    auction: AuctionItem::new(auction, auction_state, now),
+                    // The leading bid, the lowest one in a reverse auction
+                    high_bid: State::get_bids(&current_state).first().map(|bid| bid.bid_amount),
+                    outcome: State::outcome(&current_state),
+                }
+            })
+            .collect();
+
+        Ok(HttpResponse::Ok().json(seller_auctions))
+    }).await
+}
+
+// Get the bids of the calling buyer across all auctions, sealed bids of others stay hidden
+async fn get_buyer_bids(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse> {
+    with_auth(req, |user| {
+        let app_state = lock_recovering(&data);
+        let buyer_bids: Vec<BuyerBidItem> = bids_by(&app_state, user.user_id(), OffsetDateTime::now_utc())
+            .into_iter()
+            .filter_map(|(bid, status)| Some(BuyerBidItem {
+                auction_id: bid.for_auction,
+                amount: Amount::new(app_state.get_auction(bid.for_auction)?.auction_currency, bid.bid_amount),
+                at: bid.at,
+                status,
+            }))
+            .collect();
+
+        Ok(HttpResponse::Ok().json(buyer_bids))
+    }).await
+}
+
+// Weak ETag for the auction detail, changes when bids are placed, the end moves or the auction changes phase.
+// The state is the one as of now, so the tag changes at the start and the end without any command.
+fn auction_etag(auction: &Auction, current_state: &AuctionState, now: OffsetDateTime, watcher_count: usize) -> String {
+    let mut hasher = DefaultHasher::new();
+    auction.auction_id.hash(&mut hasher);
+    watcher_count.hash(&mut hasher);
+    // The effective end includes extensions by bids and by the seller
+    State::time_left(current_state, now).map(|time_left| now + time_left).hash(&mut hasher);
+    State::get_bids(current_state).len().hash(&mut hasher);
+    State::last_bid_time(current_state).hash(&mut hasher);
+    State::phase_name(current_state).hash(&mut hasher);
+    State::has_ended(current_state).hash(&mut hasher);
+    format!("W/\"{:x}\"", hasher.finish())
+}
+
+fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+        .unwrap_or(false)
+}
+
+// Get a summary of all auctions for operators
+async fn get_stats(
+    req: HttpRequest,
+    data: web::Data<AppState>
+) -> Result<HttpResponse> {
+    with_auth(req, |_| {
+        let app_state = lock_recovering(&data);
+        Ok(HttpResponse::Ok().json(repository_stats(&app_state, OffsetDateTime::now_utc())))
+    }).await
+}
+
+// Replay the audit log, from the snapshot restored at startup if any, and report where it differs from the live one, support only.
+// Commands are logged while the repository is locked, so a copy of it taken under the lock along with
+// where the log ends is consistent with the log up to there. The log is read and replayed after letting go of the lock.
+async fn verify_repository(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    audit_log: Option<web::Data<AuditLog>>
+) -> Result<HttpResponse> {
+    with_auth(req, |user| {
+        if !matches!(user, User::Support { .. }) {
+            let error = ApiError {
+                message: "Only support users can verify the repository".to_string(),
+                code: None,
+            };
+            return Ok(HttpResponse::Forbidden().json(error));
+        }
+        let Some(audit_log) = audit_log else {
+            let error = ApiError {
+                message: "No command log is kept to verify against".to_string(),
+                code: None,
+            };
+            return Ok(HttpResponse::NotFound().json(error));
+        };
+
+        let (live, position) = {
+            let app_state = lock_recovering(&data);
+            let position = audit_log.position().map_err(actix_web::error::ErrorInternalServerError)?;
+            (app_state.clone(), position)
+        };
+        let replayed = audit_log.replay_to(position).map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok(HttpResponse::Ok().json(compare_replayed(&live, &replayed, OffsetDateTime::now_utc())))
+    }).await
+}
+
+// Accepted bids from the network an auction was created from, for support to review
+async fn get_near_misses(
+    req: HttpRequest,
+    shill_guard: Option<web::Data<ShillGuard>>
+) -> Result<HttpResponse> {
+    with_auth(req, |user| {
+        if !matches!(user, User::Support { .. }) {
+            let error = ApiError {
+                message: "Only support users can review near misses".to_string(),
+                code: None,
+            };
+            return Ok(HttpResponse::Forbidden().json(error));
         }
+        let near_misses = shill_guard.map(|shill_guard| shill_guard.near_misses()).unwrap_or_default();
+        Ok(HttpResponse::Ok().json(near_misses))
+    }).await
+}
+
+// Suspend a bidder or lift the suspension, support only. Suspended bidders have their bids refused.
+fn set_suspended(
+    user: User,
+    bidder_id: UserId,
+    suspended: bool,
+    blocklist: Option<web::Data<Blocklist>>
+) -> Result<HttpResponse> {
+    if !matches!(user, User::Support { .. }) {
+        let error = ApiError {
+            message: "Only support users can suspend bidders".to_string(),
+            code: None,
+        };
+        return Ok(HttpResponse::Forbidden().json(error));
+    }
+    let Some(blocklist) = blocklist else {
+        let error = ApiError {
+            message: "No blocklist is kept".to_string(),
+            code: None,
+        };
+        return Ok(HttpResponse::NotFound().json(error));
+    };
+
+    let mut blocklist = lock_recovering(&blocklist);
+    if suspended {
+        info!("Support user {} suspends bidder {}", user.user_id(), bidder_id);
+        blocklist.insert(bidder_id.clone());
+    } else {
+        info!("Support user {} lifts the suspension of bidder {}", user.user_id(), bidder_id);
+        blocklist.remove(&bidder_id);
     }
+    Ok(HttpResponse::Ok().json(SuspensionStatus { user_id: bidder_id, suspended }))
+}
+
+async fn suspend_bidder(
+    req: HttpRequest,
+    path: web::Path<UserId>,
+    blocklist: Option<web::Data<Blocklist>>
+) -> Result<HttpResponse> {
+    let bidder_id = path.into_inner();
+    with_auth(req, |user| set_suspended(user, bidder_id, true, blocklist)).await
+}
+
+async fn unsuspend_bidder(
+    req: HttpRequest,
+    path: web::Path<UserId>,
+    blocklist: Option<web::Data<Blocklist>>
+) -> Result<HttpResponse> {
+    let bidder_id = path.into_inner();
+    with_auth(req, |user| set_suspended(user, bidder_id, false, blocklist)).await
 }
 
-// Get all auctions
-async fn get_auctions(data: web::Data<AppState>) -> Result<HttpResponse> {
-    let app_state = data.lock().unwrap();
-    let auction_list: Vec<AuctionItem> = auctions(&app_state)
+// Get the currencies auctions may be in on this site
+async fn get_currencies(config: web::Data<SiteConfig>) -> Result<HttpResponse> {
+    let currencies: Vec<CurrencyItem> = Currency::ALL
+        .into_iter()
+        .filter(|currency| validate_currency_allowed(*currency, config.allowed_currencies.as_deref()).is_ok())
+        .map(CurrencyItem::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(currencies))
+}
+
+// Get auctions ending within the next minutes
+async fn get_auctions_ending_soon(
+    query: web::Query<EndingSoonQuery>,
+    data: web::Data<AppState>
+) -> Result<HttpResponse> {
+    let minutes = query.minutes.unwrap_or(DEFAULT_ENDING_SOON_MINUTES);
+    if minutes < 0 {
+        let error = ApiError {
+            message: "Minutes must not be negative".to_string(),
+            code: None,
+        };
+        return Ok(HttpResponse::BadRequest().json(error));
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let app_state = lock_recovering(&data);
+    let auction_list: Vec<AuctionItem> = auctions_ending_between(&app_state, now, now + Duration::minutes(minutes))
         .iter()
-        .map(|a| AuctionItem::from(a))
+        .filter_map(|auction| app_state.get(auction.auction_id))
+        .map(|(auction, state)| AuctionItem::new(auction, state, now))
         .collect();
 
     Ok(HttpResponse::Ok().json(auction_list))
@@ -75,25 +570,51 @@ async fn get_auctions(data: web::Data<AppState>) -> Result<HttpResponse> {
 
 // Get auction by ID
 async fn get_auction(
+    req: HttpRequest,
     path: web::Path<AuctionId>,
-    data: web::Data<AppState>
+    query: web::Query<TimeZoneQuery>,
+    data: web::Data<AppState>,
+    config: web::Data<SiteConfig>,
+    watchlist: Option<web::Data<Watchlist>>
 ) -> Result<HttpResponse> {
     let auction_id = path.into_inner();
-    let app_state = data.lock().unwrap();
+    let tz = match time_zone(query.tz.as_deref()) {
+        Ok(tz) => tz,
+        Err(response) => return Ok(response),
+    };
+    let app_state = lock_recovering(&data);
 
-    if let Some((auction, auction_state)) = app_state.get(&auction_id) {
-        let bids = State::get_bids(auction_state);
-        let winner_and_price = State::try_get_amount_and_winner(auction_state);
+    if let Some((auction, auction_state)) = app_state.get(auction_id) {
+        let watcher_count = watchlist.as_ref()
+            .and_then(|watchlist| lock_recovering(watchlist).get(&auction_id).map(HashSet::len))
+            .unwrap_or(0);
+        // The stored state is as of the last command on the auction, the detail is as of now
+        let now = OffsetDateTime::now_utc();
+        let current_state = State::inc(auction_state, now);
+        let etag = auction_etag(auction, &current_state, now, watcher_count);
+        if etag_matches(&req, &etag) {
+            return Ok(HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag))
+                .finish());
+        }
+
+        let bids = State::get_bids(&current_state);
+        let winner_and_price = State::try_get_amount_and_winner(&current_state);
+        let time_left = State::time_left(&current_state, now);
+        let hide_bidders = matches!(&auction.typ, AuctionType::TimedAscending(opts) if opts.hide_bidders)
+            && !State::has_ended(&current_state);
+        let anonymous = if hide_bidders { anonymous_bidders(&bids) } else { HashMap::new() };
 
         let auction_bids = bids.iter().map(|bid| {
             AuctionBid {
-                amount: bid.bid_amount,
-                bidder: bid.bidder.clone(),
+                id: bid.bid_id,
+                amount: Amount::new(auction.auction_currency, bid.bid_amount),
+                bidder: anonymous.get(bid.bidder.user_id()).cloned().unwrap_or_else(|| bid.bidder.to_string()),
             }
         }).collect();
 
         let (winner, winner_price) = match winner_and_price {
-            Some((amount, user_id)) => (Some(user_id), Some(amount)),
+            Some((amount, user_id)) => (Some(user_id), Some(Amount::new(auction.auction_currency, amount))),
             None => (None, None),
         };
 
@@ -103,93 +624,440 @@ async fn get_auction(
             title: auction.title.clone(),
             expiry: auction.expiry,
             currency: auction.auction_currency,
+            description: auction.description.clone(),
+            image_url: auction.image_url.clone(),
+            local_starts_at: local_time(auction.starts_at, tz),
+            local_expiry: local_time(auction.expiry, tz),
             bids: auction_bids,
             winner,
-            winner_price: winner_price.map(|v| Amount::new(auction.auction_currency, v)),
+            winner_price,
+            // No fees are taken of auctions in a virtual currency
+            commission: winner_price.and_then(|price| config.fee_schedule.commission(price).ok()),
+            net_to_seller: winner_price.and_then(|price| config.fee_schedule.net_to_seller(price).ok()),
+            has_started: State::has_started(&current_state),
+            version: app_state.version(auction_id),
+            time_left_seconds: time_left.map(|d| d.whole_seconds()),
+            first_bid_time: State::first_bid_time(&current_state),
+            last_bid_time: State::last_bid_time(&current_state),
+            outcome: State::outcome(&current_state),
+            watcher_count,
         };
 
-        Ok(HttpResponse::Ok().json(detail))
+        Ok(HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(detail))
     } else {
         let error = ApiError {
             message: "Auction not found".to_string(),
+            code: None,
         };
         Ok(HttpResponse::NotFound().json(error))
     }
 }
 
+async fn get_auction_winner(path: web::Path<AuctionId>, data: web::Data<AppState>) -> Result<HttpResponse> {
+    let auction_id = path.into_inner();
+    let app_state = lock_recovering(&data);
+    let (auction, auction_state) = app_state.get(auction_id).ok_or(Errors::UnknownAuction(auction_id))?;
+
+    let current_state = State::inc(auction_state, OffsetDateTime::now_utc());
+    let outcome = State::outcome(&current_state);
+    let (winner, price) = match &outcome {
+        AuctionOutcome::Sold { price, winner } => (Some(winner.clone()), Some(Amount::new(auction.auction_currency, *price))),
+        _ => (None, None),
+    };
+    Ok(HttpResponse::Ok().json(AuctionWinner {
+        ended: State::has_ended(&current_state),
+        winner,
+        price,
+        outcome: outcome.name(),
+    }))
+}
+
+// Bidders are numbered in the order of their first bid so that a token stays the same as more bids arrive
+fn anonymous_bidders(bids: &[Bid]) -> HashMap<UserId, String> {
+    let mut in_order: Vec<&Bid> = bids.iter().collect();
+    in_order.sort_by_key(|bid| bid.at);
+
+    let mut tokens = HashMap::new();
+    for bid in in_order {
+        let next = tokens.len() + 1;
+        tokens.entry(bid.bidder.user_id().clone()).or_insert_with(|| format!("Bidder #{}", next));
+    }
+    tokens
+}
+
+// Address of the connected peer, forwarding headers are not trusted
+fn source_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info().peer_addr().map(str::to_string)
+}
+
+// Hand the event to subscribers and to the event log, when configured
+fn publish(event: &Event, event_bus: &Option<web::Data<EventBus>>, event_log: &Option<web::Data<EventLog>>) {
+    if let Some(event_log) = event_log {
+        // As with the audit log, the command has been handled so a failing event log does not fail the request
+        if let Err(err) = event_log.append(event) {
+            error!("Failed to write event: {}", err);
+        }
+    }
+    if let Some(event_bus) = event_bus {
+        event_bus.publish(event.clone());
+    }
+}
+
+// Handle the command in place and record who submitted it when an audit log is configured
+fn handle_audited(
+    command: Command,
+    repository: &mut Repository,
+    audit_log: &Option<web::Data<AuditLog>>,
+    actor: &User,
+    source_ip: &Option<String>
+) -> Result<Event, HandleError> {
+    let Some(audit_log) = audit_log else {
+        return handle_mut(command, repository);
+    };
+
+    let record_command = command.clone();
+    let result = handle_mut(command, repository);
+    let record = AuditRecord {
+        at: OffsetDateTime::now_utc(),
+        actor: actor.user_id().clone(),
+        source_ip: source_ip.clone(),
+        command: record_command,
+        result: result.as_ref().map(|_| ()).map_err(|err| err.to_string()),
+    };
+    // The command has been handled at this point, so a failing audit log does not fail the request
+    if let Err(err) = audit_log.append(&record) {
+        error!("Failed to write audit record: {}", err);
+    }
+    result
+}
+
+// Events as answered to the client, the auction type of an added auction in its public form
+fn public_event(event: &Event) -> serde_json::Result<Value> {
+    let mut json = serde_json::to_value(event)?;
+    if let Event::AuctionAdded { auction, .. } = event {
+        json["auction"]["type"] = Value::String(auction.typ.public_string_in(auction.auction_currency));
+    }
+    Ok(json)
+}
+
 // Create a new auction
+#[allow(clippy::too_many_arguments)]
 async fn create_auction(
     req: HttpRequest,
     auction_req: web::Json<AddAuctionRequest>,
-    data: web::Data<AppState>
+    data: web::Data<AppState>,
+    config: web::Data<SiteConfig>,
+    event_bus: Option<web::Data<EventBus>>,
+    event_log: Option<web::Data<EventLog>>,
+    audit_log: Option<web::Data<AuditLog>>,
+    shill_guard: Option<web::Data<ShillGuard>>
 ) -> Result<HttpResponse> {
+    let source_ip = source_ip(&req);
     with_auth(req, |user| {
-        let auction = auction_req.to_auction(user);
+        let actor = user.clone();
+        let auction = auction_req.to_auction(user, &config)?;
+        validate_auction_duration(&auction, config.min_auction_duration)?;
+        validate_min_raise(&auction, config.max_min_raise_bps)?;
         let now = OffsetDateTime::now_utc();
         let command = Command::AddAuction {
             timestamp: now,
             auction: auction.clone(),
         };
 
-        let mut app_state = data.lock().unwrap();
+        let mut app_state = lock_recovering(&data);
+        validate_seller_auction_limit(&app_state, &auction, now, config.max_active_auctions_per_seller)?;
 
-        match handle(command, app_state.clone()) {
-            Ok((success, new_state)) => {
-                *app_state = new_state;
-                Ok(HttpResponse::Ok().json(success))
-            },
-            Err(err) => {
-                Ok(HttpResponse::BadRequest().body(format!("{}", err)))
-            }
+        let success = handle_audited(command, &mut app_state, &audit_log, &actor, &source_ip)?;
+        if let (Some(shill_guard), Some(source_ip)) = (&shill_guard, &source_ip) {
+            shill_guard.record_seller_ip(auction.auction_id, source_ip.clone());
         }
+        let response = public_event(&success)?;
+        publish(&success, &event_bus, &event_log);
+        Ok(HttpResponse::Ok().json(response))
+    }).await
+}
+
+// Run the checks of creating an auction without storing it, so that a form can show every problem at once
+async fn validate_auction_request(
+    req: HttpRequest,
+    auction_req: web::Json<AddAuctionRequest>,
+    config: web::Data<SiteConfig>,
+) -> Result<HttpResponse> {
+    with_auth(req, |user| {
+        let errors = auction_req.validation_errors(user, &config);
+        Ok(HttpResponse::Ok().json(AuctionValidation {
+            valid: errors.is_empty(),
+            errors: errors.iter().map(ApiError::from).collect(),
+        }))
     }).await
 }
 
-// Place a bid on an auction
+// Place a bid on an auction, or with dryRun only check whether it would be accepted
+// Actix extracts each piece of shared state as its own argument
+#[allow(clippy::too_many_arguments)]
 async fn place_bid(
     req: HttpRequest,
     path: web::Path<AuctionId>,
     bid_req: web::Json<BidRequest>,
-    data: web::Data<AppState>
+    query: web::Query<BidQuery>,
+    data: web::Data<AppState>,
+    rate_limiter: web::Data<RateLimiter>,
+    blocklist: Option<web::Data<Blocklist>>,
+    event_bus: Option<web::Data<EventBus>>,
+    event_log: Option<web::Data<EventLog>>,
+    audit_log: Option<web::Data<AuditLog>>,
+    shill_guard: Option<web::Data<ShillGuard>>,
+    config: web::Data<SiteConfig>
 ) -> Result<HttpResponse> {
     let auction_id = path.into_inner();
+    let source_ip = source_ip(&req);
+    let lang = accept_language(&req);
 
     with_auth(req, |user| {
+        let actor = user.clone();
+        if let Err(retry_after) = rate_limiter.check(user.user_id(), Instant::now()) {
+            let error = ApiError {
+                message: "Too many bids, try again later".to_string(),
+                code: None,
+            };
+            return Ok(HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.as_secs_f64().ceil().to_string()))
+                .json(error));
+        }
+
         let now = OffsetDateTime::now_utc();
 
+        let (bidder, placed_by) = match (&bid_req.on_behalf_of, user) {
+            (None, user) => (user, None),
+            (Some(buyer_id), User::Support { user_id }) => {
+                info!("Support user {} places bid on behalf of {}", user_id, buyer_id);
+                // There is no user directory to look up the buyer name, so the id is used
+                let buyer = User::BuyerOrSeller {
+                    user_id: buyer_id.clone(),
+                    name: buyer_id.clone(),
+                };
+                (buyer, Some(user_id))
+            },
+            (Some(_), _) => {
+                let error = ApiError {
+                    message: "Only support users can place bids on behalf of a buyer".to_string(),
+                    code: None,
+                };
+                return Ok(HttpResponse::Forbidden().json(error));
+            }
+        };
+
+        let mut app_state = lock_recovering(&data);
+
+        // Bids are placed in the currency of the auction
+        let auction = app_state.get_auction(auction_id).ok_or(Errors::UnknownAuction(auction_id))?;
+        validate_bid_currency(auction, bid_req.currency)?;
+        // Auctions added before the currency was taken off the allowlist take no further bids
+        validate_currency_allowed(auction.auction_currency, config.allowed_currencies.as_deref())?;
+        let amount = match bid_req.amount.to_amount(auction.auction_currency) {
+            Ok(amount) => amount,
+            Err(message) => {
+                let error = ApiError {
+                    message,
+                    code: None,
+                };
+                return Ok(HttpResponse::BadRequest().json(error));
+            }
+        };
+        validate_amount_within_ceiling(amount, &config.max_amounts)?;
+
+        // Compared while holding the lock so the bid is handled against the version the client saw
+        validate_expected_version(auction_id, app_state.version(auction_id), bid_req.expected_version)?;
+
         let bid = Bid {
-            for_auction: auction_id,
-            bidder: user,
-            at: now,
-            bid_amount: bid_req.amount,
+            placed_by,
+            ..Bid::new(auction_id, bidder, now, amount.value())?
         };
 
+        if let Some(blocklist) = &blocklist {
+            validate_bidder_not_suspended(&bid, &lock_recovering(blocklist))?;
+        }
+        let near_miss = match &shill_guard {
+            Some(shill_guard) if config.block_shill_bids => shill_guard.check(&bid, source_ip.as_deref())?,
+            _ => None,
+        };
+
+        let bidder_id = bid.bidder.user_id().clone();
+        if query.dry_run {
+            // The bid is tried on a copy of the state of this auction only, the repository is left as it is
+            let (auction, auction_state) = app_state.get(auction_id).ok_or(Errors::UnknownAuction(auction_id))?;
+            let mut preview_state = auction_state.clone();
+            let preview = match add_bid_to(auction, &mut preview_state, bid) {
+                Ok(()) => bid_preview(auction, &preview_state, &bidder_id, now, None),
+                Err(err) => bid_preview(auction, auction_state, &bidder_id, now, Some(ApiError::localized(&err, lang))),
+            };
+            return Ok(HttpResponse::Ok().json(preview));
+        }
+
         let command = Command::PlaceBid {
             timestamp: now,
             bid,
         };
 
-        let mut app_state = data.lock().unwrap();
-
-        match handle(command, app_state.clone()) {
-            Ok((success, new_state)) => {
-                *app_state = new_state;
-                Ok(HttpResponse::Ok().json(success))
-            },
-            Err(err) => {
-                Ok(HttpResponse::BadRequest().body(format!("{}", err)))
-            }
+        let success = handle_audited(command, &mut app_state, &audit_log, &actor, &source_ip)?;
+        if let (Some(shill_guard), Some(near_miss)) = (&shill_guard, near_miss) {
+            shill_guard.record_near_miss(near_miss);
         }
+        publish(&success, &event_bus, &event_log);
+        let (auction, auction_state) = app_state.get(auction_id).expect("the auction of an accepted bid exists");
+        let preview = bid_preview(auction, auction_state, &bidder_id, now, None);
+        Ok(HttpResponse::Ok().json(BidResult {
+            event: success,
+            accepted: true,
+            new_high_bid: preview.resulting_high_bid,
+            new_expiry: preview.new_expiry,
+            your_position: preview.your_position,
+        }))
     }).await
 }
 
+// Preview of the auction as of now, the leading bid is kept first
+fn bid_preview(auction: &Auction, auction_state: &AuctionState, bidder_id: &UserId, now: OffsetDateTime, error: Option<ApiError>) -> BidPreview {
+    BidPreview {
+        would_accept: error.is_none(),
+        resulting_high_bid: State::get_bids(auction_state).first()
+            .map(|bid| Amount::new(auction.auction_currency, bid.bid_amount)),
+        new_expiry: State::time_left(auction_state, now).map(|time_left| now + time_left),
+        your_position: bid_position(auction_state, bidder_id),
+        error,
+    }
+}
+
+// Whether the bidder leads, None when the bidder has no bids on the auction
+fn bid_position(state: &AuctionState, bidder_id: &UserId) -> Option<BidPosition> {
+    let bids = State::get_bids(state);
+    match bids.first() {
+        Some(leading) if leading.bidder.user_id() == bidder_id => Some(BidPosition::Leading),
+        Some(_) if bids.iter().any(|bid| bid.bidder.user_id() == bidder_id) => Some(BidPosition::Outbid),
+        _ if !State::bids_of(state, bidder_id).is_empty() => Some(BidPosition::Sealed),
+        _ => None,
+    }
+}
+
+// End an auction before its expiry, only the seller or support may close it
+async fn close_auction(
+    req: HttpRequest,
+    path: web::Path<AuctionId>,
+    data: web::Data<AppState>,
+    event_bus: Option<web::Data<EventBus>>,
+    event_log: Option<web::Data<EventLog>>,
+    audit_log: Option<web::Data<AuditLog>>
+) -> Result<HttpResponse> {
+    let auction_id = path.into_inner();
+    let source_ip = source_ip(&req);
+
+    with_auth(req, |user| {
+        let actor = user.clone();
+        let command = Command::CloseAuction {
+            timestamp: OffsetDateTime::now_utc(),
+            auction_id,
+            user,
+        };
+
+        let mut app_state = lock_recovering(&data);
+
+        let success = handle_audited(command, &mut app_state, &audit_log, &actor, &source_ip)?;
+        publish(&success, &event_bus, &event_log);
+        Ok(HttpResponse::Ok().json(success))
+    }).await
+}
+
+// Start or stop watching an auction, watching twice counts once
+fn set_watching(
+    user: User,
+    auction_id: AuctionId,
+    watching: bool,
+    data: &AppState,
+    watchlist: &Watchlist
+) -> Result<HttpResponse> {
+    if !lock_recovering(data).contains(auction_id) {
+        return Err(Errors::UnknownAuction(auction_id).into());
+    }
+
+    let mut watchlist = lock_recovering(watchlist);
+    let watchers = watchlist.entry(auction_id).or_default();
+    if watching {
+        watchers.insert(user.user_id().clone());
+    } else {
+        watchers.remove(user.user_id());
+    }
+    let watcher_count = watchers.len();
+    if watcher_count == 0 {
+        watchlist.remove(&auction_id);
+    }
+    Ok(HttpResponse::Ok().json(WatchStatus { watching, watcher_count }))
+}
+
+async fn watch_auction(
+    req: HttpRequest,
+    path: web::Path<AuctionId>,
+    data: web::Data<AppState>,
+    watchlist: web::Data<Watchlist>
+) -> Result<HttpResponse> {
+    let auction_id = path.into_inner();
+    with_auth(req, |user| set_watching(user, auction_id, true, &data, &watchlist)).await
+}
+
+async fn unwatch_auction(
+    req: HttpRequest,
+    path: web::Path<AuctionId>,
+    data: web::Data<AppState>,
+    watchlist: web::Data<Watchlist>
+) -> Result<HttpResponse> {
+    let auction_id = path.into_inner();
+    with_auth(req, |user| set_watching(user, auction_id, false, &data, &watchlist)).await
+}
+
+// Get the OpenAPI description of the api
+async fn get_openapi() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(openapi_document()))
+}
+
+// A body that cannot be read is answered with an ApiError naming what was wrong, e.g. an unknown field
+fn json_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let message = match &err {
+        JsonPayloadError::Deserialize(err) => format!("Invalid request body: {}", err),
+        err => err.to_string(),
+    };
+    let response = HttpResponse::build(err.status_code()).json(ApiError {
+        message,
+        code: None,
+    });
+    InternalError::from_response(err, response).into()
+}
+
 // Configure routes
 pub fn configure_app(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::JsonConfig::default().error_handler(json_error));
     cfg.service(
         web::scope("")
+            .wrap(from_fn(localize_errors))
             .route("/auctions", web::get().to(get_auctions))
+            .route("/auctions/ending-soon", web::get().to(get_auctions_ending_soon))
+            .route("/auctions/stream", web::get().to(stream_auctions))
             .route("/auctions/{id}", web::get().to(get_auction))
+            .route("/auctions/{id}/winner", web::get().to(get_auction_winner))
             .route("/auctions", web::post().to(create_auction))
+            .route("/auctions/validate", web::post().to(validate_auction_request))
             .route("/auctions/{id}/bids", web::post().to(place_bid))
+            .route("/auctions/{id}/close", web::post().to(close_auction))
+            .route("/auctions/{id}/watch", web::post().to(watch_auction))
+            .route("/auctions/{id}/watch", web::delete().to(unwatch_auction))
+            .route("/my/auctions", web::get().to(get_seller_auctions))
+            .route("/my/bids", web::get().to(get_buyer_bids))
+            .route("/currencies", web::get().to(get_currencies))
+            .route("/stats", web::get().to(get_stats))
+            .route("/admin/verify", web::get().to(verify_repository))
+            .route("/admin/near-misses", web::get().to(get_near_misses))
+            .route("/admin/suspended-bidders/{user_id}", web::put().to(suspend_bidder))
+            .route("/admin/suspended-bidders/{user_id}", web::delete().to(unsuspend_bidder))
+            .route("/openapi.json", web::get().to(get_openapi))
     );
 }
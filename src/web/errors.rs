@@ -0,0 +1,80 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use thiserror::Error;
+
+use crate::domain::{Errors, HandleError};
+use super::types::ApiError;
+
+/// Why the x-jwt-payload header did not give a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("Missing x-jwt-payload header")]
+    MissingToken,
+    #[error("The x-jwt-payload header is not valid base64")]
+    BadBase64,
+    #[error("The x-jwt-payload header is not valid JSON")]
+    BadJson,
+    #[error("The x-jwt-payload header lacks the sub, name or u_typ claim")]
+    MissingClaim,
+}
+
+impl AuthError {
+    /// Reason given in the code of the 401 response
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::MissingToken => "missing_token",
+            AuthError::BadBase64 => "bad_base64",
+            AuthError::BadJson => "bad_json",
+            AuthError::MissingClaim => "missing_claim",
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ApiError {
+            message: self.to_string(),
+            code: Some(self.code().to_string()),
+        })
+    }
+}
+
+// Status of the response for a domain error, anything not listed is a bad request
+pub fn status_for(err: &Errors) -> StatusCode {
+    match err {
+        Errors::UnknownAuction(_) => StatusCode::NOT_FOUND,
+        Errors::CannotCloseAuction(_) | Errors::CannotExtendAuction(_) | Errors::BidderSuspended(_) |
+        Errors::NotInFinalRound(_) | Errors::SuspectedShillBid(_) => StatusCode::FORBIDDEN,
+        Errors::VersionConflict(_) => StatusCode::CONFLICT,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+// Lets handlers return domain errors with ?, the body is the ApiError with the code of the error
+impl ResponseError for Errors {
+    fn status_code(&self) -> StatusCode {
+        status_for(self)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ApiError::from(self))
+    }
+}
+
+impl ResponseError for HandleError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            HandleError::AuctionError(err) => err.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            HandleError::AuctionError(err) => err.error_response(),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use std::sync::{Mutex, MutexGuard};
+use log::warn;
+
+// Handlers only replace the shared state once a command has succeeded, so the state behind a lock that
+// was poisoned by a panicking request is still consistent and later requests can keep using it
+pub fn lock_recovering<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        warn!("Recovering a lock poisoned by a panic");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
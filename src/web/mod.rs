@@ -1,2 +1,7 @@
 pub mod app;
-pub mod types;
\ No newline at end of file
+pub mod errors;
+pub mod lock;
+pub mod openapi;
+pub mod rate_limit;
+pub mod shill;
+pub mod types;
@@ -0,0 +1,595 @@
+use serde_json::{json, Value};
+
+// Hand maintained OpenAPI description of the web api, keep in sync with the routes in app.rs
+pub fn openapi_document() -> Value {
+    let auth = json!([{ "jwtPayload": [] }]);
+    let auction_id = json!({
+        "name": "id", "in": "path", "required": true,
+        "schema": { "type": "integer", "format": "int64" }
+    });
+    let tz = json!({
+        "name": "tz", "in": "query", "required": false,
+        "description": "IANA time zone, e.g. Europe/Stockholm, adds localStartsAt and localExpiry",
+        "schema": { "type": "string" }
+    });
+    let fields = json!({
+        "name": "fields", "in": "query", "required": false,
+        "description": "Comma separated fields of each item to return, e.g. id,title,expiry,currentPrice",
+        "schema": { "type": "string" }
+    });
+    let error_response = |description: &str| json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ApiError" } } }
+    });
+    let json_response = |description: &str, schema: Value| json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Auction site",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": {
+            "/auctions": {
+                "get": {
+                    "summary": "List auctions ordered by id",
+                    "parameters": [
+                        {
+                            "name": "seller", "in": "query", "required": false,
+                            "description": "Only auctions of this seller",
+                            "schema": { "type": "string" }
+                        },
+                        {
+                            "name": "status", "in": "query", "required": false,
+                            "description": "Only auctions in this phase",
+                            "schema": { "type": "string", "enum": ["AwaitingStart", "Ongoing", "Ended"] }
+                        },
+                        tz,
+                        fields
+                    ],
+                    "responses": {
+                        "200": json_response("Auctions", json!({
+                            "type": "array", "items": { "$ref": "#/components/schemas/AuctionItem" }
+                        })),
+                        "400": error_response("Unknown time zone or field")
+                    }
+                },
+                "post": {
+                    "summary": "Create an auction, the caller is the seller",
+                    "security": auth,
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/AddAuctionRequest" } } }
+                    },
+                    "responses": {
+                        "200": json_response("Auction added", json!({ "$ref": "#/components/schemas/Event" })),
+                        "400": error_response("Invalid auction"),
+                        "401": error_response("Missing or malformed x-jwt-payload header")
+                    }
+                }
+            },
+            "/auctions/validate": {
+                "post": {
+                    "summary": "Check an auction request as creating it would, without storing it",
+                    "security": auth,
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/AddAuctionRequest" } } }
+                    },
+                    "responses": {
+                        "200": json_response("Every problem with the request", json!({ "$ref": "#/components/schemas/AuctionValidation" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header")
+                    }
+                }
+            },
+            "/auctions/stream": {
+                "get": {
+                    "summary": "List auctions as GET /auctions does, written in chunks for large catalogs",
+                    "parameters": [
+                        { "name": "seller", "in": "query", "required": false, "schema": { "type": "string" } },
+                        {
+                            "name": "status", "in": "query", "required": false,
+                            "schema": { "type": "string", "enum": ["AwaitingStart", "Ongoing", "Ended"] }
+                        },
+                        tz,
+                        fields
+                    ],
+                    "responses": {
+                        "200": json_response("Auctions", json!({
+                            "type": "array", "items": { "$ref": "#/components/schemas/AuctionItem" }
+                        })),
+                        "400": error_response("Unknown time zone or field")
+                    }
+                }
+            },
+            "/auctions/{id}": {
+                "get": {
+                    "summary": "Get an auction with its bids",
+                    "parameters": [auction_id, tz],
+                    "responses": {
+                        "200": json_response("Auction", json!({ "$ref": "#/components/schemas/AuctionDetail" })),
+                        "304": { "description": "Not modified since the ETag in If-None-Match" },
+                        "400": error_response("Unknown time zone"),
+                        "404": error_response("Auction not found")
+                    }
+                }
+            },
+            "/auctions/{id}/bids": {
+                "post": {
+                    "summary": "Place a bid, or check whether it would be accepted",
+                    "security": auth,
+                    "parameters": [auction_id, {
+                        "name": "dryRun", "in": "query", "required": false,
+                        "description": "Preview the bid without placing it",
+                        "schema": { "type": "boolean", "default": false }
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/BidRequest" } } }
+                    },
+                    "responses": {
+                        "200": json_response("Bid accepted, or the preview of a dry run", json!({
+                            "oneOf": [{ "$ref": "#/components/schemas/BidResult" }, { "$ref": "#/components/schemas/BidPreview" }]
+                        })),
+                        "400": error_response("Bid not accepted"),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "404": error_response("Auction not found"),
+                        "403": error_response("Bidder is suspended or not allowed to bid on behalf of a buyer"),
+                        "409": error_response("The auction has changed since the expected version"),
+                        "429": error_response("Too many bids")
+                    }
+                }
+            },
+            "/auctions/{id}/winner": {
+                "get": {
+                    "summary": "The outcome of an auction, winner and price once it has been sold",
+                    "parameters": [auction_id],
+                    "responses": {
+                        "200": json_response("Outcome", json!({ "$ref": "#/components/schemas/AuctionWinner" })),
+                        "404": error_response("Auction not found")
+                    }
+                }
+            },
+            "/auctions/{id}/close": {
+                "post": {
+                    "summary": "End an auction before its expiry, only for the seller or support",
+                    "security": auth,
+                    "parameters": [auction_id],
+                    "responses": {
+                        "200": json_response("Auction closed", json!({ "$ref": "#/components/schemas/Event" })),
+                        "400": error_response("Auction already ended"),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not allowed to close the auction"),
+                        "404": error_response("Auction not found")
+                    }
+                }
+            },
+            "/auctions/{id}/watch": {
+                "post": {
+                    "summary": "Watch an auction, the detail shows how many users watch it",
+                    "security": auth,
+                    "parameters": [auction_id],
+                    "responses": {
+                        "200": json_response("Watching", json!({ "$ref": "#/components/schemas/WatchStatus" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "404": error_response("Auction not found")
+                    }
+                },
+                "delete": {
+                    "summary": "Stop watching an auction",
+                    "security": auth,
+                    "parameters": [auction_id],
+                    "responses": {
+                        "200": json_response("Not watching", json!({ "$ref": "#/components/schemas/WatchStatus" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "404": error_response("Auction not found")
+                    }
+                }
+            },
+            "/auctions/ending-soon": {
+                "get": {
+                    "summary": "List auctions ending within the next minutes",
+                    "parameters": [{
+                        "name": "minutes", "in": "query", "required": false,
+                        "schema": { "type": "integer", "default": 60 }
+                    }],
+                    "responses": {
+                        "200": json_response("Auctions", json!({
+                            "type": "array", "items": { "$ref": "#/components/schemas/AuctionItem" }
+                        })),
+                        "400": error_response("Invalid window")
+                    }
+                }
+            },
+            "/currencies": {
+                "get": {
+                    "summary": "List the currencies auctions may be in on this site",
+                    "responses": {
+                        "200": json_response("Currencies", json!({
+                            "type": "array", "items": { "$ref": "#/components/schemas/CurrencyItem" }
+                        }))
+                    }
+                }
+            },
+            "/stats": {
+                "get": {
+                    "summary": "Summary of all auctions",
+                    "security": auth,
+                    "responses": {
+                        "200": json_response("Statistics", json!({ "$ref": "#/components/schemas/RepositoryStats" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header")
+                    }
+                }
+            },
+            "/admin/verify": {
+                "get": {
+                    "summary": "Replay the audit log and compare it to the live auctions, support only",
+                    "security": auth,
+                    "responses": {
+                        "200": json_response("Consistency report", json!({ "$ref": "#/components/schemas/ConsistencyReport" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not a support user"),
+                        "404": error_response("No audit log is kept")
+                    }
+                }
+            },
+            "/admin/near-misses": {
+                "get": {
+                    "summary": "List the latest accepted bids from the network an auction was created from, support only",
+                    "security": auth,
+                    "responses": {
+                        "200": json_response("Near misses", json!({ "type": "array", "items": { "type": "object" } })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not a support user")
+                    }
+                }
+            },
+            "/admin/suspended-bidders/{userId}": {
+                "put": {
+                    "summary": "Suspend a bidder so that their bids are refused, support only",
+                    "security": auth,
+                    "parameters": [{ "name": "userId", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": {
+                        "200": json_response("Suspension status", json!({ "$ref": "#/components/schemas/SuspensionStatus" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not a support user"),
+                        "404": error_response("No blocklist is kept")
+                    }
+                },
+                "delete": {
+                    "summary": "Lift the suspension of a bidder, support only",
+                    "security": auth,
+                    "parameters": [{ "name": "userId", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": {
+                        "200": json_response("Suspension status", json!({ "$ref": "#/components/schemas/SuspensionStatus" })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not a support user"),
+                        "404": error_response("No blocklist is kept")
+                    }
+                }
+            },
+            "/my/auctions": {
+                "get": {
+                    "summary": "List the auctions of the calling seller",
+                    "security": auth,
+                    "parameters": [{
+                        "name": "sellerId", "in": "query", "required": false,
+                        "description": "Seller to list, required for support users",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": {
+                        "200": json_response("Auctions", json!({ "type": "array", "items": { "type": "object" } })),
+                        "401": error_response("Missing or malformed x-jwt-payload header"),
+                        "403": error_response("Not allowed to list auctions of another seller")
+                    }
+                }
+            },
+            "/my/bids": {
+                "get": {
+                    "summary": "List the bids of the calling buyer across all auctions",
+                    "security": auth,
+                    "responses": {
+                        "200": json_response("Bids", json!({
+                            "type": "array", "items": { "$ref": "#/components/schemas/BuyerBidItem" }
+                        })),
+                        "401": error_response("Missing or malformed x-jwt-payload header")
+                    }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "jwtPayload": {
+                    "type": "apiKey", "in": "header", "name": "x-jwt-payload",
+                    "description": "Base64 encoded JSON with sub, name and u_typ (0 buyer or seller, 1 support)"
+                }
+            },
+            "schemas": schemas()
+        }
+    })
+}
+
+// Kept apart from the document as one json! invocation would exceed the macro recursion limit
+fn schemas() -> Value {
+    let mut schemas = json!({
+        "ApiError": {
+            "type": "object",
+            "required": ["message"],
+            "properties": {
+                "message": { "type": "string" },
+                "code": { "type": "string", "description": "Stable code of the domain error, e.g. unknown_auction" }
+            }
+        },
+        "BidRequest": {
+            "type": "object",
+            "required": ["amount"],
+            "properties": {
+                "amount": {
+                    "oneOf": [
+                        { "type": "integer", "format": "int64", "description": "Minor units of the auction currency" },
+                        { "type": "number", "description": "Major units, e.g. 10.50" },
+                        { "type": "string", "description": "Major units, e.g. \"10.50\"" }
+                    ]
+                },
+                "currency": { "$ref": "#/components/schemas/Currency", "description": "Must be the auction currency when given" },
+                "onBehalfOf": { "type": "string", "description": "Buyer id, only for support users" },
+                "expectedVersion": { "type": "integer", "description": "Version of the auction detail the bid is based on" }
+            }
+        },
+        "AuctionItem": {
+            "type": "object",
+            "required": ["id", "startsAt", "title", "expiry", "currency", "bidCount"],
+            "properties": {
+                "id": { "type": "integer", "format": "int64" },
+                "startsAt": { "type": "string", "format": "date-time" },
+                "title": { "type": "string" },
+                "expiry": { "type": "string", "format": "date-time" },
+                "currency": { "$ref": "#/components/schemas/Currency" },
+                "bidCount": { "type": "integer", "description": "Visible bids, sealed bids are counted once disclosed" },
+                "currentPrice": { "type": "string", "nullable": true, "description": "Leading bid, e.g. SEK120" },
+                "localStartsAt": { "type": "string", "format": "date-time", "description": "In the tz of the query" },
+                "localExpiry": { "type": "string", "format": "date-time", "description": "In the tz of the query" }
+            }
+        },
+        "BuyerBidItem": {
+            "type": "object",
+            "required": ["auctionId", "amount", "at", "status"],
+            "properties": {
+                "auctionId": { "type": "integer", "format": "int64" },
+                "amount": { "type": "string", "description": "Amount in the auction currency, e.g. SEK100" },
+                "at": { "type": "string", "format": "date-time" },
+                "status": { "type": "string", "enum": ["Winning", "Outbid", "Sealed", "Won", "Lost"] }
+            }
+        },
+        "AuctionBid": {
+            "type": "object",
+            "required": ["amount", "bidder"],
+            "properties": {
+                "id": { "type": "string", "format": "uuid", "nullable": true },
+                "amount": { "type": "string", "description": "Amount in the auction currency, e.g. SEK100" },
+                "bidder": { "type": "string", "description": "User, e.g. BuyerOrSeller|id|name, or Bidder #1 while bidders are hidden" }
+            }
+        },
+        "AuctionDetail": {
+            "type": "object",
+            "required": ["id", "startsAt", "title", "expiry", "currency", "bids", "hasStarted", "version", "outcome"],
+            "properties": {
+                "id": { "type": "integer", "format": "int64" },
+                "startsAt": { "type": "string", "format": "date-time" },
+                "title": { "type": "string" },
+                "expiry": { "type": "string", "format": "date-time" },
+                "currency": { "$ref": "#/components/schemas/Currency" },
+                "description": { "type": "string", "nullable": true },
+                "imageUrl": { "type": "string", "format": "uri", "nullable": true },
+                "localStartsAt": { "type": "string", "format": "date-time", "description": "In the tz of the query" },
+                "localExpiry": { "type": "string", "format": "date-time", "description": "In the tz of the query" },
+                "bids": { "type": "array", "items": { "$ref": "#/components/schemas/AuctionBid" } },
+                "winner": { "type": "string", "nullable": true },
+                "winnerPrice": { "type": "string", "nullable": true, "description": "Amount, e.g. SEK100" },
+                "commission": { "type": "string", "nullable": true, "description": "Commission of the winning price, none for virtual currencies" },
+                "netToSeller": { "type": "string", "nullable": true, "description": "Winning price less the commission" },
+                "hasStarted": { "type": "boolean" },
+                "version": { "type": "integer", "description": "Send as expectedVersion to reject a bid on a stale view" },
+                "timeLeftSeconds": { "type": "integer", "nullable": true },
+                "firstBidTime": { "type": "string", "format": "date-time", "nullable": true },
+                "lastBidTime": { "type": "string", "format": "date-time", "nullable": true },
+                "watcherCount": { "type": "integer", "description": "Distinct users watching the auction" },
+                "outcome": {
+                    "type": "object",
+                    "required": ["$type"],
+                    "properties": {
+                        "$type": { "type": "string", "enum": ["Ongoing", "NoBids", "ReserveNotMet", "Sold"] },
+                        "highest": { "type": "integer", "format": "int64" },
+                        "price": { "type": "integer", "format": "int64" },
+                        "winner": { "type": "string" }
+                    }
+                }
+            }
+        },
+        "AuctionWinner": {
+            "type": "object",
+            "required": ["ended", "outcome"],
+            "properties": {
+                "ended": { "type": "boolean" },
+                "winner": { "type": "string", "nullable": true },
+                "price": { "type": "string", "nullable": true, "description": "Amount, e.g. SEK100" },
+                "outcome": { "type": "string", "enum": ["ongoing", "no_bids", "reserve_not_met", "sold"] }
+            }
+        },
+        "WatchStatus": {
+            "type": "object",
+            "required": ["watching", "watcherCount"],
+            "properties": {
+                "watching": { "type": "boolean" },
+                "watcherCount": { "type": "integer" }
+            }
+        },
+        "SuspensionStatus": {
+            "type": "object",
+            "required": ["userId", "suspended"],
+            "properties": {
+                "userId": { "type": "string" },
+                "suspended": { "type": "boolean" }
+            }
+        },
+        "ConsistencyReport": {
+            "type": "object",
+            "required": ["consistent", "discrepancies"],
+            "properties": {
+                "consistent": { "type": "boolean" },
+                "discrepancies": { "type": "array", "items": {
+                    "type": "object",
+                    "required": ["kind", "auctionId"],
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["MissingFromLog", "MissingFromLive", "Winner", "BidCount", "State"] },
+                        "auctionId": { "type": "integer" },
+                        "live": { "description": "Winner or bid count of the live auction" },
+                        "replayed": { "description": "Winner or bid count of the replayed auction" }
+                    }
+                } }
+            }
+        },
+        "RepositoryStats": {
+            "type": "object",
+            "required": ["totalAuctions", "byType", "byPhase", "totalBids", "highBidTotals"],
+            "properties": {
+                "totalAuctions": { "type": "integer" },
+                "byType": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "byPhase": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "totalBids": { "type": "integer" },
+                "highBidTotals": {
+                    "type": "object",
+                    "description": "Sum of the leading bids per currency, e.g. { \"SEK\": \"SEK100\" }",
+                    "additionalProperties": { "type": "string" }
+                }
+            }
+        },
+        "CurrencyItem": {
+            "type": "object",
+            "required": ["code", "name", "decimals"],
+            "properties": {
+                "code": { "$ref": "#/components/schemas/Currency" },
+                "name": { "type": "string" },
+                "decimals": { "type": "integer", "description": "Digits of the minor unit" }
+            }
+        },
+        "Currency": {
+            "type": "string",
+            "enum": ["VAC", "SEK", "DKK"]
+        },
+        "Event": {
+            "type": "object",
+            "required": ["$type", "at"],
+            "properties": {
+                "$type": { "type": "string", "enum": ["AuctionAdded", "BidAccepted", "AuctionClosed"] },
+                "at": { "type": "string", "format": "date-time" }
+            }
+        }
+    });
+    if let Some(schemas) = schemas.as_object_mut() {
+        for split_off in [auction_request_schemas(), bid_schemas()] {
+            if let Value::Object(split_off) = split_off {
+                schemas.extend(split_off);
+            }
+        }
+    }
+    schemas
+}
+
+// Requests to create an auction, split off for the same reason as schemas
+fn auction_request_schemas() -> Value {
+    json!({
+        "AddAuctionRequest": {
+            "type": "object",
+            "required": ["id", "startsAt", "endsAt", "title"],
+            "properties": {
+                "id": { "type": "integer", "format": "int64" },
+                "startsAt": { "type": "string", "format": "date-time" },
+                "endsAt": { "type": "string", "format": "date-time", "description": "Also accepted as expiry" },
+                "title": { "type": "string", "minLength": 1, "maxLength": 200 },
+                "currency": { "$ref": "#/components/schemas/Currency" },
+                "description": { "type": "string" },
+                "imageUrl": { "type": "string", "format": "uri", "description": "Absolute http or https url" },
+                "typ": {
+                    "description": "Also accepted as type",
+                    "oneOf": [
+                        {
+                            "type": "string",
+                            "description": "Auction type, e.g. English|0|0|0 (amounts in the auction currency), English|SEK0|SEK0|0, Blind, Vickrey or Reverse|VAC0"
+                        },
+                        {
+                            "type": "object",
+                            "required": ["kind"],
+                            "properties": {
+                                "kind": { "type": "string", "enum": ["English", "Blind", "Vickrey", "Reverse"] },
+                                "reservePrice": { "type": "string" },
+                                "minRaise": { "type": "string" },
+                                "timeFrame": { "type": "integer", "description": "Seconds" },
+                                "allowSelfOutbid": { "type": "boolean", "default": true },
+                                "maxExtensions": { "type": "integer", "description": "Times bids may extend the expiry" },
+                                "hideBidders": { "type": "boolean", "default": false },
+                                "strictIncrease": { "type": "boolean", "default": false },
+                                "allowRevision": { "type": "boolean", "default": false, "description": "Blind and Vickrey, a bidder may replace their bid" },
+                                "discloseAll": { "type": "boolean", "default": true, "description": "Blind and Vickrey, all bids are disclosed after the end, otherwise only the winning bid" },
+                                "reserveSecret": { "type": "boolean", "default": false, "description": "Responses show the reserve price as *" },
+                                "clockSkewGrace": { "type": "integer", "description": "Seconds after the expiry in which bids are still accepted" },
+                                "maxHistory": { "type": "integer", "description": "Most bids kept, older and lower bids are dropped" },
+                                "finalRound": {
+                                    "type": "object",
+                                    "description": "A sealed round for the leading bidders after the open bidding",
+                                    "properties": {
+                                        "seconds": { "type": "integer" },
+                                        "bidders": { "type": "integer" }
+                                    }
+                                },
+                                "maxBid": { "type": "string", "description": "Bids above this amount are rejected" },
+                                "maxPrice": { "type": "string" }
+                            }
+                        }
+                    ]
+                }
+            }
+        },
+        "AuctionValidation": {
+            "type": "object",
+            "required": ["valid", "errors"],
+            "properties": {
+                "valid": { "type": "boolean" },
+                "errors": { "type": "array", "items": { "$ref": "#/components/schemas/ApiError" } }
+            }
+        }
+    })
+}
+
+// Responses of placing a bid, split off for the same reason as schemas
+fn bid_schemas() -> Value {
+    json!({
+        "BidPreview": {
+            "type": "object",
+            "required": ["wouldAccept"],
+            "properties": {
+                "wouldAccept": { "type": "boolean" },
+                "resultingHighBid": { "type": "string", "nullable": true, "description": "Amount, e.g. SEK100" },
+                "newExpiry": { "type": "string", "format": "date-time", "nullable": true },
+                "yourPosition": { "type": "string", "enum": ["leading", "outbid", "sealed"] },
+                "error": { "$ref": "#/components/schemas/ApiError" }
+            }
+        },
+        "BidResult": {
+            "description": "The BidAccepted event with the auction as it stands after the bid",
+            "allOf": [{ "$ref": "#/components/schemas/Event" }, {
+                "type": "object",
+                "required": ["accepted"],
+                "properties": {
+                    "accepted": { "type": "boolean" },
+                    "newHighBid": { "type": "string", "nullable": true, "description": "Amount, e.g. SEK100" },
+                    "newExpiry": { "type": "string", "format": "date-time", "nullable": true },
+                    "yourPosition": { "type": "string", "enum": ["leading", "outbid", "sealed"] }
+                }
+            }]
+        }
+    })
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::domain::UserId;
+use super::lock::lock_recovering;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+struct Buckets {
+    by_user: HashMap<UserId, Bucket>,
+    last_eviction: Instant,
+}
+
+// Token bucket per user: each user may do `capacity` requests in a burst,
+// after which one request is allowed per `refill_interval`.
+// A bucket that has refilled is the same as no bucket, so those of idle users are dropped now and then.
+pub struct RateLimiter {
+    capacity: u32,
+    refill_interval: Duration,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        RateLimiter {
+            capacity,
+            refill_interval,
+            buckets: Mutex::new(Buckets {
+                by_user: HashMap::new(),
+                last_eviction: Instant::now(),
+            }),
+        }
+    }
+
+    /// Number of users with a bucket that may not have refilled yet
+    pub fn tracked_users(&self) -> usize {
+        lock_recovering(&self.buckets).by_user.len()
+    }
+
+    // Time for an empty bucket to refill
+    fn idle_time(&self) -> Duration {
+        self.refill_interval.saturating_mul(self.capacity)
+    }
+
+    // Take a token for the user, or return how long to wait before the next one is available
+    pub fn check(&self, user_id: &UserId, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.capacity);
+        let mut buckets = lock_recovering(&self.buckets);
+        let idle_time = self.idle_time();
+        if now.saturating_duration_since(buckets.last_eviction) >= idle_time {
+            buckets.by_user.retain(|_, bucket| now.saturating_duration_since(bucket.last_refill) < idle_time);
+            buckets.last_eviction = now;
+        }
+        let bucket = buckets.by_user.entry(user_id.clone()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        let refilled = elapsed.as_secs_f64() / self.refill_interval.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refilled).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.refill_interval.mul_f64(1.0 - bucket.tokens))
+        }
+    }
+}
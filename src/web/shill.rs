@@ -0,0 +1,90 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use serde::Serialize;
+use time::OffsetDateTime;
+use crate::domain::{AuctionId, Bid, Errors, Repository, State, UserId};
+use super::lock::lock_recovering;
+
+/// An accepted bid from the network the auction was created from, though not from the same address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NearMiss {
+    #[serde(rename = "auctionId")]
+    pub auction_id: AuctionId,
+    pub bidder: UserId,
+    #[serde(rename = "sourceIp")]
+    pub source_ip: String,
+    #[serde(rename = "sellerIp")]
+    pub seller_ip: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+}
+
+/// Most near misses kept for review, the oldest are dropped first
+pub const MAX_NEAR_MISSES: usize = 1000;
+
+// Source addresses the auctions were created from, the same addresses the audit log records,
+// kept in memory so that a bid does not have to read the audit log
+#[derive(Debug, Default)]
+pub struct ShillGuard {
+    seller_ips: Mutex<HashMap<AuctionId, String>>,
+    near_misses: Mutex<VecDeque<NearMiss>>,
+}
+
+impl ShillGuard {
+    pub fn new() -> Self {
+        ShillGuard::default()
+    }
+
+    pub fn record_seller_ip(&self, auction_id: AuctionId, source_ip: String) {
+        lock_recovering(&self.seller_ips).insert(auction_id, source_ip);
+    }
+
+    /// Forget the addresses of auctions that have ended or are gone, they take no more bids
+    pub fn forget_ended(&self, repository: &Repository) {
+        lock_recovering(&self.seller_ips).retain(|auction_id, _| {
+            repository.get_state(*auction_id).is_some_and(|state| !state.has_ended())
+        });
+    }
+
+    /// Reject a bid from the address the auction was created from.
+    /// A bid from elsewhere in the same network is let through and given back to be recorded once accepted.
+    pub fn check(&self, bid: &Bid, source_ip: Option<&str>) -> Result<Option<NearMiss>, Errors> {
+        let seller_ips = lock_recovering(&self.seller_ips);
+        let (Some(seller_ip), Some(source_ip)) = (seller_ips.get(&bid.for_auction), source_ip) else {
+            return Ok(None);
+        };
+        if seller_ip == source_ip {
+            return Err(Errors::SuspectedShillBid(bid.for_auction));
+        }
+        Ok(same_network(seller_ip, source_ip).then(|| NearMiss {
+            auction_id: bid.for_auction,
+            bidder: bid.bidder.user_id().clone(),
+            source_ip: source_ip.to_string(),
+            seller_ip: seller_ip.clone(),
+            at: bid.at,
+        }))
+    }
+
+    pub fn record_near_miss(&self, near_miss: NearMiss) {
+        let mut near_misses = lock_recovering(&self.near_misses);
+        if near_misses.len() == MAX_NEAR_MISSES {
+            near_misses.pop_front();
+        }
+        near_misses.push_back(near_miss);
+    }
+
+    /// The latest near misses in the order the bids were accepted
+    pub fn near_misses(&self) -> Vec<NearMiss> {
+        lock_recovering(&self.near_misses).iter().cloned().collect()
+    }
+}
+
+// The same /24 for IPv4 and the same /64 for IPv6
+fn same_network(a: &str, b: &str) -> bool {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(IpAddr::V4(a)), Ok(IpAddr::V4(b))) => a.octets()[..3] == b.octets()[..3],
+        (Ok(IpAddr::V6(a)), Ok(IpAddr::V6(b))) => a.segments()[..4] == b.segments()[..4],
+        _ => false,
+    }
+}
@@ -1,50 +1,255 @@
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use serde::de::IgnoredAny;
+use time::{OffsetDateTime, UtcOffset};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
-use crate::domain::{Auction, AuctionId, AuctionType, Repository, User};
+use crate::config::SiteConfig;
+use crate::domain::{validate_auction_amounts, validate_auction_currency, validate_auction_duration, validate_min_raise, validate_auction_title, validate_currency_allowed, validate_image_url, Auction, AuctionId, AuctionOutcome, AuctionState, State, AuctionPhase, AuctionType, UnresolvedAuctionType, BidId, BidStatus, Errors, Event, HandleError, Lang, Repository, User, UserId};
 use crate::money::{Currency, Amount, AmountValue};
 use crate::domain::timed_ascending;
 
 pub type AppState = Arc<Mutex<Repository>>;
 
+/// Ids of suspended users that must not place bids
+pub type Blocklist = Arc<Mutex<HashSet<UserId>>>;
+
+/// Users watching each auction, kept in memory only as watching is not a command of the auction
+pub type Watchlist = Arc<Mutex<HashMap<AuctionId, HashSet<UserId>>>>;
+
+/// Answer to watching or unwatching an auction
+#[derive(Debug, Serialize)]
+pub struct WatchStatus {
+    pub watching: bool,
+    #[serde(rename = "watcherCount")]
+    pub watcher_count: usize,
+}
+
+/// Answer to suspending a bidder or lifting the suspension
+#[derive(Debug, Serialize)]
+pub struct SuspensionStatus {
+    #[serde(rename = "userId")]
+    pub user_id: UserId,
+    pub suspended: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiError {
     pub message: String,
+    /// Machine readable code of the domain error, see Errors::code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl ApiError {
+    /// The error with its message in the language, the code stays the same in every language
+    pub fn localized(err: &Errors, lang: Lang) -> Self {
+        ApiError {
+            message: err.message(lang),
+            code: Some(err.code().to_string()),
+        }
+    }
+}
+
+impl From<&Errors> for ApiError {
+    fn from(err: &Errors) -> Self {
+        ApiError::localized(err, Lang::En)
+    }
+}
+
+impl From<&HandleError> for ApiError {
+    fn from(err: &HandleError) -> Self {
+        match err {
+            HandleError::AuctionError(err) => ApiError::from(err),
+        }
+    }
+}
+
+/// Integers are minor units of the auction currency, decimals are major units ("10.50" or 10.50)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BidAmount {
+    MinorUnits(AmountValue),
+    Decimal(f64),
+    Text(String),
 }
 
+impl BidAmount {
+    pub fn to_amount(&self, currency: Currency) -> Result<Amount, String> {
+        match self {
+            BidAmount::MinorUnits(value) => Ok(Amount::new(currency, *value)),
+            // The shortest representation of the number keeps the digits the client sent
+            BidAmount::Decimal(value) => Amount::from_major_units(currency, &value.to_string()),
+            BidAmount::Text(text) => Amount::from_major_units(currency, text),
+        }
+    }
+}
+
+// Unknown fields are rejected so that a misspelled field is not taken as missing
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BidRequest {
-    pub amount: AmountValue,
+    pub amount: BidAmount,
+    /// Currency of the amount, it must be that of the auction when given
+    #[serde(default)]
+    pub currency: Option<Currency>,
+    /// Buyer a support user places the bid for
+    #[serde(rename = "onBehalfOf", default)]
+    pub on_behalf_of: Option<UserId>,
+    /// Version of the auction the bid is based on, the bid is rejected if the auction has changed since
+    #[serde(rename = "expectedVersion", default)]
+    pub expected_version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BidQuery {
+    /// Check the bid without placing it
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+/// Where the bids of the bidder stand after a bid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BidPosition {
+    Leading,
+    Outbid,
+    /// Sealed bids are not compared until the auction has ended
+    Sealed,
+}
+
+/// Outcome of a dry run bid, the high bid and expiry are as they would be had the bid been placed
+#[derive(Debug, Serialize)]
+pub struct BidPreview {
+    #[serde(rename = "wouldAccept")]
+    pub would_accept: bool,
+    #[serde(rename = "resultingHighBid")]
+    pub resulting_high_bid: Option<Amount>,
+    #[serde(with="time::serde::rfc3339::option", rename = "newExpiry")]
+    pub new_expiry: Option<OffsetDateTime>,
+    #[serde(rename = "yourPosition", skip_serializing_if = "Option::is_none")]
+    pub your_position: Option<BidPosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+/// The event of a placed bid along with the auction as it stands after it, so no second request is needed
+#[derive(Debug, Serialize)]
+pub struct BidResult {
+    #[serde(flatten)]
+    pub event: Event,
+    pub accepted: bool,
+    #[serde(rename = "newHighBid")]
+    pub new_high_bid: Option<Amount>,
+    #[serde(with="time::serde::rfc3339::option", rename = "newExpiry")]
+    pub new_expiry: Option<OffsetDateTime>,
+    #[serde(rename = "yourPosition", skip_serializing_if = "Option::is_none")]
+    pub your_position: Option<BidPosition>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AddAuctionRequest {
     pub id: AuctionId,
     #[serde(with="time::serde::rfc3339", rename = "startsAt")]
     pub starts_at: OffsetDateTime,
     pub title: String,
-    #[serde(with="time::serde::rfc3339", rename = "endsAt")]
+    // The names of the auction itself are accepted too, so a fetched auction can be posted back
+    #[serde(with="time::serde::rfc3339", rename = "endsAt", alias = "expiry")]
     pub ends_at: OffsetDateTime,
     pub currency: Option<Currency>,
-    pub typ: Option<AuctionType>,
+    #[serde(alias = "type")]
+    pub typ: Option<UnresolvedAuctionType>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "imageUrl", default)]
+    pub image_url: Option<String>,
+    /// The seller of a posted back auction, the caller is the seller whatever is sent
+    #[serde(rename = "user", default, skip_serializing)]
+    _seller: Option<IgnoredAny>,
 }
 
 impl AddAuctionRequest {
-    pub fn to_auction(&self, seller: User) -> Auction {
-        let currency = self.currency.unwrap_or(Currency::VAC);
-        let typ = self.typ.clone().unwrap_or_else(|| {
-            AuctionType::TimedAscending(timed_ascending::Options::default_options())
-        });
+    pub fn to_auction(&self, seller: User, config: &SiteConfig) -> Result<Auction, Errors> {
+        if let Some(err) = self.request_errors(config).into_iter().next() {
+            return Err(err);
+        }
+        let auction = self.auction(seller, config);
+        validate_auction_amounts(&auction, &config.max_amounts)?;
+        Ok(auction)
+    }
+
+    /// Every error the creation of the auction would run into, in the order they are checked
+    pub fn validation_errors(&self, seller: User, config: &SiteConfig) -> Vec<Errors> {
+        let mut errors = self.request_errors(config);
+        let auction = self.auction(seller, config);
+        errors.extend(validate_auction_duration(&auction, config.min_auction_duration).err());
+        errors.extend(validate_auction_currency(&auction).err());
+        errors.extend(validate_auction_amounts(&auction, &config.max_amounts).err());
+        errors.extend(validate_min_raise(&auction, config.max_min_raise_bps).err());
+        errors
+    }
+
+    fn request_errors(&self, config: &SiteConfig) -> Vec<Errors> {
+        let currency = self.currency.unwrap_or(config.default_currency);
+        [
+            validate_auction_title(self.title.trim(), config.max_title_length),
+            self.image_url.as_deref().map_or(Ok(()), validate_image_url),
+            validate_currency_allowed(currency, config.allowed_currencies.as_deref()),
+        ].into_iter().filter_map(Result::err).collect()
+    }
+
+    fn auction(&self, seller: User, config: &SiteConfig) -> Auction {
+        let currency = self.currency.unwrap_or(config.default_currency);
+        let typ = self.typ.as_ref().or(config.default_auction_type.as_ref())
+            .map(|typ| typ.resolve(currency))
+            .unwrap_or_else(|| AuctionType::TimedAscending(timed_ascending::Options::default_options(currency)));
         
+        // Times are kept in UTC whatever offset the client sent, so that stored auctions compare and print alike
         Auction {
             auction_id: self.id,
-            starts_at: self.starts_at,
-            title: self.title.clone(),
-            expiry: self.ends_at,
+            starts_at: self.starts_at.to_offset(UtcOffset::UTC),
+            title: self.title.trim().to_string(),
+            expiry: self.ends_at.to_offset(UtcOffset::UTC),
             seller,
             typ,
             auction_currency: currency,
+            description: self.description.clone(),
+            image_url: self.image_url.clone(),
+        }
+    }
+}
+
+/// The outcome of an auction without the rest of the detail, winner and price are None until it has been sold
+#[derive(Debug, Serialize)]
+pub struct AuctionWinner {
+    pub ended: bool,
+    pub winner: Option<UserId>,
+    pub price: Option<Amount>,
+    /// ongoing, no_bids, reserve_not_met or sold
+    pub outcome: &'static str,
+}
+
+/// Answer to checking an auction request without creating the auction
+#[derive(Debug, Serialize)]
+pub struct AuctionValidation {
+    pub valid: bool,
+    pub errors: Vec<ApiError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CurrencyItem {
+    pub code: Currency,
+    pub name: &'static str,
+    pub decimals: u8,
+}
+
+impl From<Currency> for CurrencyItem {
+    fn from(currency: Currency) -> Self {
+        CurrencyItem {
+            code: currency,
+            name: currency.name(),
+            decimals: currency.decimals(),
         }
     }
 }
@@ -58,24 +263,95 @@ pub struct AuctionItem {
     #[serde(with="time::serde::rfc3339")]
     pub expiry: OffsetDateTime,
     pub currency: Currency,
+    /// Number of bids visible to everyone, sealed bids are not counted until disclosed
+    #[serde(rename = "bidCount")]
+    pub bid_count: usize,
+    /// The leading bid, None while there are no visible bids
+    #[serde(rename = "currentPrice")]
+    pub current_price: Option<Amount>,
+    /// Start and expiry in the time zone of the tz query parameter, when given
+    #[serde(rename = "localStartsAt", skip_serializing_if = "Option::is_none")]
+    pub local_starts_at: Option<String>,
+    #[serde(rename = "localExpiry", skip_serializing_if = "Option::is_none")]
+    pub local_expiry: Option<String>,
 }
 
-impl From<&Auction> for AuctionItem {
-    fn from(auction: &Auction) -> Self {
+/// Names of the fields of an auction item as serialized, the fields a listing can be projected on
+pub const AUCTION_ITEM_FIELDS: &[&str] = &[
+    "id", "startsAt", "title", "expiry", "currency", "bidCount", "currentPrice", "localStartsAt", "localExpiry",
+];
+
+impl AuctionItem {
+    /// The item as of now, the stored state may not have been moved past the start or the end yet
+    pub fn new(auction: &Auction, state: &AuctionState, now: OffsetDateTime) -> Self {
+        // States keep the leading bid first
+        let bids = state.inc(now).get_bids();
         AuctionItem {
             id: auction.auction_id,
             starts_at: auction.starts_at,
             title: auction.title.clone(),
             expiry: auction.expiry,
             currency: auction.auction_currency,
+            bid_count: bids.len(),
+            current_price: bids.first().map(|bid| Amount::new(auction.auction_currency, bid.bid_amount)),
+            local_starts_at: None,
+            local_expiry: None,
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EndingSoonQuery {
+    pub minutes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuctionsQuery {
+    pub seller: Option<UserId>,
+    pub status: Option<AuctionPhase>,
+    /// IANA time zone, e.g. Europe/Stockholm, to add local times in
+    pub tz: Option<String>,
+    /// Comma separated fields of each item to return, all fields when not given
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeZoneQuery {
+    /// IANA time zone, e.g. Europe/Stockholm, to add local times in
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SellerQuery {
+    #[serde(rename = "sellerId")]
+    pub seller_id: Option<UserId>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SellerAuctionItem {
+    #[serde(flatten)]
+    pub auction: AuctionItem,
+    #[serde(rename = "highBid")]
+    pub high_bid: Option<AmountValue>,
+    pub outcome: AuctionOutcome,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuyerBidItem {
+    #[serde(rename = "auctionId")]
+    pub auction_id: AuctionId,
+    pub amount: Amount,
+    #[serde(with="time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub status: BidStatus,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuctionBid {
-    pub amount: AmountValue,
-    pub bidder: User,
+    pub id: Option<BidId>,
+    pub amount: Amount,
+    /// The user, or an anonymous token such as "Bidder #1" while the auction hides bidders
+    pub bidder: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -88,10 +364,36 @@ pub struct AuctionDetail {
     #[serde(with="time::serde::rfc3339")]
     pub expiry: OffsetDateTime,
     pub currency: Currency,
+    pub description: Option<String>,
+    #[serde(rename = "imageUrl")]
+    pub image_url: Option<String>,
+    /// Start and expiry in the time zone of the tz query parameter, when given
+    #[serde(rename = "localStartsAt", skip_serializing_if = "Option::is_none")]
+    pub local_starts_at: Option<String>,
+    #[serde(rename = "localExpiry", skip_serializing_if = "Option::is_none")]
+    pub local_expiry: Option<String>,
     
     // Additional detail fields
     pub bids: Vec<AuctionBid>,
     pub winner: Option<String>,
     #[serde(rename = "winnerPrice")]
     pub winner_price: Option<Amount>,
+    /// Commission of the winning price, once sold in a real currency
+    pub commission: Option<Amount>,
+    #[serde(rename = "netToSeller")]
+    pub net_to_seller: Option<Amount>,
+    #[serde(rename = "hasStarted")]
+    pub has_started: bool,
+    /// Send as expectedVersion with a bid to have it rejected if the auction has changed
+    pub version: u64,
+    #[serde(rename = "timeLeftSeconds")]
+    pub time_left_seconds: Option<i64>,
+    #[serde(with="time::serde::rfc3339::option", rename = "firstBidTime")]
+    pub first_bid_time: Option<OffsetDateTime>,
+    #[serde(with="time::serde::rfc3339::option", rename = "lastBidTime")]
+    pub last_bid_time: Option<OffsetDateTime>,
+    pub outcome: AuctionOutcome,
+    /// Distinct users watching the auction
+    #[serde(rename = "watcherCount")]
+    pub watcher_count: usize,
 }
\ No newline at end of file
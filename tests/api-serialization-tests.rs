@@ -1,9 +1,12 @@
-use auction_site::domain::{AuctionType, Auction};
+use auction_site::config::SiteConfig;
+use auction_site::domain::{AuctionType, Auction, Errors, UnresolvedAuctionType};
+use auction_site::domain::single_sealed_bid::Options as SBOptions;
 use auction_site::domain::timed_ascending::Options as TAOptions;
-use auction_site::money::Currency;
-use auction_site::web::types::{AddAuctionRequest, BidRequest};
+use auction_site::money::{Amount, Currency};
+use auction_site::web::types::{AddAuctionRequest, BidAmount, BidRequest};
 use serde_json::json;
-#[path="utils/mod.rs"] mod utils;
+use time::UtcOffset;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
 use utils::*;
 
 #[test]
@@ -26,7 +29,7 @@ fn test_auction_request_deserialization() {
     assert_eq!(request.title, "First auction");
 
     // Create an auction from the request
-    let auction = request.to_auction(sample_seller());
+    let auction = request.to_auction(sample_seller(), &SiteConfig::default()).unwrap();
 
     // Verify the auction has correct default values for non-specified fields
     assert_eq!(auction.auction_currency, Currency::VAC);
@@ -34,13 +37,34 @@ fn test_auction_request_deserialization() {
     // Verify auction type is TimedAscending with default options
     match auction.typ {
         AuctionType::TimedAscending(options) => {
-            assert_eq!(options.reserve_price, 0);
-            assert_eq!(options.min_raise, 0);
+            assert_eq!(options.reserve_price, vac(0));
+            assert_eq!(options.min_raise, vac(0));
         },
         _ => panic!("Expected TimedAscending auction type"),
     }
 }
 
+#[test]
+fn test_auction_request_times_are_normalized_to_utc() {
+    let json_data = json!({
+        "id": 1,
+        "startsAt": "2016-01-01T10:28:00+02:00",
+        "endsAt": "2016-02-01T03:28:00-05:00",
+        "title": "First auction"
+    });
+    let request: AddAuctionRequest = serde_json::from_value(json_data).unwrap();
+    let auction = request.to_auction(sample_seller(), &SiteConfig::default()).unwrap();
+
+    assert_eq!(auction.starts_at, sample_starts_at());
+    assert_eq!(auction.starts_at.offset(), UtcOffset::UTC);
+    assert_eq!(auction.expiry, sample_ends_at());
+    assert_eq!(auction.expiry.offset(), UtcOffset::UTC);
+
+    let serialized = serde_json::to_value(&auction).unwrap();
+    assert_eq!(serialized["startsAt"], "2016-01-01T08:28:00Z");
+    assert_eq!(serialized["expiry"], "2016-02-01T08:28:00Z");
+}
+
 #[test]
 fn test_auction_request_with_currency_deserialization() {
     // Create a JSON representation of an auction request with currency
@@ -56,7 +80,7 @@ fn test_auction_request_with_currency_deserialization() {
     let request: AddAuctionRequest = serde_json::from_value(json_data).unwrap();
 
     // Create an auction from the request
-    let auction = request.to_auction(sample_seller());
+    let auction = request.to_auction(sample_seller(), &SiteConfig::default()).unwrap();
 
     // Verify the auction has the specified currency
     assert_eq!(auction.auction_currency, Currency::SEK);
@@ -73,7 +97,30 @@ fn test_bid_request_deserialization() {
     let request: BidRequest = serde_json::from_value(json_data).unwrap();
 
     // Verify fields
-    assert_eq!(request.amount, 10);
+    assert_eq!(request.amount, BidAmount::MinorUnits(10));
+}
+
+#[test]
+fn test_bid_request_decimal_amounts() {
+    let amount_in = |json_data: serde_json::Value, currency: Currency| {
+        let request: BidRequest = serde_json::from_value(json!({ "amount": json_data })).unwrap();
+        request.amount.to_amount(currency)
+    };
+
+    // Integers are minor units
+    assert_eq!(amount_in(json!(1050), Currency::SEK), Ok(sek(1050)));
+    // Decimals are major units
+    assert_eq!(amount_in(json!(10.50), Currency::SEK), Ok(sek(1050)));
+    assert_eq!(amount_in(json!("10.50"), Currency::SEK), Ok(sek(1050)));
+    assert_eq!(amount_in(json!("10.5"), Currency::SEK), Ok(sek(1050)));
+    assert_eq!(amount_in(json!("10"), Currency::VAC), Ok(vac(10)));
+
+    // More decimals than the currency has are rejected
+    assert!(amount_in(json!(10.501), Currency::SEK).is_err());
+    assert!(amount_in(json!("10.501"), Currency::SEK).is_err());
+    assert!(amount_in(json!("10.5"), Currency::VAC).is_err());
+    assert!(amount_in(json!("ten"), Currency::SEK).is_err());
+    assert!(amount_in(json!("-1"), Currency::SEK).is_err());
 }
 
 #[test]
@@ -86,7 +133,9 @@ fn test_auction_serialization() {
         expiry: sample_ends_at(),
         seller: sample_seller(),
         auction_currency: Currency::VAC,
-        typ: AuctionType::TimedAscending(TAOptions::default_options()),
+        description: None,
+        image_url: None,
+        typ: AuctionType::TimedAscending(TAOptions::default_options(Currency::VAC)),
     };
 
     // Serialize to JSON
@@ -101,3 +150,144 @@ fn test_auction_serialization() {
     assert_eq!(json["currency"], json!("VAC"), "currency {:?}", json["currency"]);
     assert!(json["type"].as_str().unwrap().starts_with("English|"));
 }
+
+#[test]
+fn test_auction_type_structured_deserialization() {
+    let english_text: AuctionType = serde_json::from_value(json!("English|SEK10|SEK20|30")).unwrap();
+    let english_object: AuctionType = serde_json::from_value(json!({
+        "kind": "English",
+        "reservePrice": "SEK10",
+        "minRaise": "SEK20",
+        "timeFrame": 30
+    })).unwrap();
+    assert_eq!(english_text, english_object);
+    assert_eq!(english_object, AuctionType::TimedAscending(TAOptions {
+        reserve_price: sek(10),
+        min_raise: sek(20),
+        time_frame: time::Duration::seconds(30),
+        ..TAOptions::default_options(Currency::SEK)
+    }));
+
+    let vickrey_text: AuctionType = serde_json::from_value(json!("Vickrey")).unwrap();
+    let vickrey_object: AuctionType = serde_json::from_value(json!({ "kind": "Vickrey" })).unwrap();
+    assert_eq!(vickrey_text, vickrey_object);
+
+    let revisable: AuctionType = serde_json::from_value(json!({ "kind": "Blind", "allowRevision": true })).unwrap();
+    assert_eq!(revisable, AuctionType::SingleSealedBid(SBOptions { allow_revision: true, ..SBOptions::blind() }));
+    assert_eq!(revisable.to_string(), "Blind|AllowRevision");
+
+    // Serialization stays the string form
+    assert_eq!(serde_json::to_value(&english_object).unwrap(), json!("English|SEK10|SEK20|30"));
+
+    assert!(serde_json::from_value::<AuctionType>(json!({ "kind": "Dutch" })).is_err());
+}
+
+fn request_with_title(title: &str) -> AddAuctionRequest {
+    serde_json::from_value(json!({
+        "id": 1,
+        "startsAt": "2016-01-01T08:28:00.000Z",
+        "endsAt": "2016-02-01T08:28:00.000Z",
+        "title": title
+    })).unwrap()
+}
+
+#[test]
+fn test_auction_request_title_validation() {
+    let config = SiteConfig::default();
+    let is_invalid_title = |title: &str| matches!(
+        request_with_title(title).to_auction(sample_seller(), &config),
+        Err(Errors::InvalidAuctionTitle(_))
+    );
+
+    assert!(is_invalid_title(""));
+    assert!(is_invalid_title("   "));
+    assert!(is_invalid_title(&"a".repeat(201)));
+    assert!(is_invalid_title("first line\nsecond line"));
+
+    let auction = request_with_title(&"a".repeat(200)).to_auction(sample_seller(), &config).unwrap();
+    assert_eq!(auction.title.len(), 200);
+
+    // Surrounding whitespace is trimmed
+    let auction = request_with_title("  Vintage lamp ").to_auction(sample_seller(), &config).unwrap();
+    assert_eq!(auction.title, "Vintage lamp");
+
+    let config = SiteConfig { max_title_length: 5, ..SiteConfig::default() };
+    assert!(request_with_title("Vintage lamp").to_auction(sample_seller(), &config).is_err());
+}
+
+#[test]
+fn test_auction_request_uses_configured_defaults() {
+    let config = SiteConfig { default_currency: Currency::SEK, ..SiteConfig::default() };
+    let auction = request_with_title("auction").to_auction(sample_seller(), &config).unwrap();
+    assert_eq!(auction.auction_currency, Currency::SEK);
+    match auction.typ {
+        AuctionType::TimedAscending(options) => {
+            assert_eq!(options.reserve_price, sek(0));
+            assert_eq!(options.min_raise, sek(0));
+        },
+        _ => panic!("Expected TimedAscending auction type"),
+    }
+
+    let config = SiteConfig {
+        default_currency: Currency::SEK,
+        default_auction_type: Some(UnresolvedAuctionType::Resolved(AuctionType::SingleSealedBid(SBOptions::vickrey()))),
+        ..SiteConfig::default()
+    };
+    let auction = request_with_title("auction").to_auction(sample_seller(), &config).unwrap();
+    assert_eq!(auction.auction_currency, Currency::SEK);
+    assert_eq!(auction.typ, AuctionType::SingleSealedBid(SBOptions::vickrey()));
+
+    // Without configuration new auctions are English in the virtual currency
+    let auction = request_with_title("auction").to_auction(sample_seller(), &SiteConfig::default()).unwrap();
+    assert_eq!(auction.auction_currency, Currency::VAC);
+    assert!(matches!(auction.typ, AuctionType::TimedAscending(_)));
+}
+
+#[test]
+fn test_auction_request_accepts_auction_field_names() {
+    let ends_at: AddAuctionRequest = serde_json::from_value(json!({
+        "id": 1,
+        "startsAt": "2016-01-01T08:28:00.000Z",
+        "endsAt": "2016-02-01T08:28:00.000Z",
+        "title": "auction",
+        "currency": "SEK",
+        "typ": "Vickrey"
+    })).unwrap();
+
+    // An auction as returned by the api, the seller is taken from the caller
+    let auction = sample_vickrey_auction();
+    let expiry: AddAuctionRequest = serde_json::from_value(serde_json::to_value(&auction).unwrap()).unwrap();
+
+    let config = SiteConfig::default();
+    assert_eq!(ends_at.to_auction(sample_seller(), &config).unwrap(), auction);
+    assert_eq!(expiry.to_auction(sample_seller(), &config).unwrap(), auction);
+}
+
+#[test]
+fn test_default_auction_type_is_in_the_auction_currency() {
+    let mut request = request_with_title("auction");
+    request.currency = Some(Currency::DKK);
+    let auction = request.to_auction(sample_seller(), &SiteConfig::default()).unwrap();
+
+    assert_eq!(auction.typ, AuctionType::TimedAscending(TAOptions::default_options(Currency::DKK)));
+    match auction.typ {
+        AuctionType::TimedAscending(options) => {
+            assert_eq!(options.reserve_price, Amount::new(Currency::DKK, 0));
+            assert_eq!(options.min_raise, Amount::new(Currency::DKK, 0));
+        },
+        _ => panic!("Expected TimedAscending auction type"),
+    }
+
+    // A configured default with bare amounts takes the currency of the request as well
+    let config = SiteConfig {
+        default_currency: Currency::SEK,
+        default_auction_type: Some(UnresolvedAuctionType::Text("English|100|10|0".to_string())),
+        ..SiteConfig::default()
+    };
+    let auction = request.to_auction(sample_seller(), &config).unwrap();
+    assert_eq!(auction.typ, AuctionType::TimedAscending(TAOptions {
+        reserve_price: Amount::new(Currency::DKK, 100),
+        min_raise: Amount::new(Currency::DKK, 10),
+        ..TAOptions::default_options(Currency::DKK)
+    }));
+}
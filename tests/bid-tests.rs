@@ -0,0 +1,29 @@
+use auction_site::domain::{core::Errors, Bid, User};
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+#[test]
+fn test_new_bid() {
+    let bid = Bid::new(sample_auction_id(), buyer_1(), sample_bid_time(), 10).unwrap();
+    assert_eq!(bid.for_auction, sample_auction_id());
+    assert_eq!(bid.bidder, buyer_1());
+    assert_eq!(bid.at, sample_bid_time());
+    assert_eq!(bid.bid_amount, 10);
+    assert!(bid.bid_id.is_some());
+    assert_eq!(bid.placed_by, None);
+}
+
+#[test]
+fn test_new_bid_must_have_positive_amount() {
+    assert_eq!(Bid::new(sample_auction_id(), buyer_1(), sample_bid_time(), 0), Err(Errors::InvalidBidAmount(0)));
+    assert_eq!(Bid::new(sample_auction_id(), buyer_1(), sample_bid_time(), -5), Err(Errors::InvalidBidAmount(-5)));
+}
+
+#[test]
+fn test_new_bid_must_name_the_bidder() {
+    let bidder = User::BuyerOrSeller {
+        user_id: String::new(),
+        name: "Buyer".to_string(),
+    };
+    assert!(matches!(Bid::new(sample_auction_id(), bidder, sample_bid_time(), 10), Err(Errors::InvalidUserData(_))));
+}
@@ -1,10 +1,10 @@
 use auction_site::domain::{
-    Bid, single_sealed_bid::{Options as SBOptions, SingleSealedBidState as SBState},
-    states::State,
+    AuctionType, Bid, Errors, single_sealed_bid::{Options as SBOptions, SingleSealedBidState as SBState},
+    states::{AuctionOutcome, State},
     AuctionState, empty_state
 };
 use time::Duration;
-#[path="utils/mod.rs"] mod utils;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
 use utils::*;
 // Sample data for tests
 
@@ -35,7 +35,7 @@ fn test_blind_auction_states() {
             assert_eq!(bids[0], bid_2());
             assert_eq!(bids[1], bid_1());
             assert_eq!(*expiry, sample_ends_at());
-            assert_eq!(*options, SBOptions::Blind);
+            assert_eq!(*options, SBOptions::blind());
         },
         _ => panic!("Expected DisclosingBids state"),
     }
@@ -67,10 +67,12 @@ fn test_cannot_place_duplicate_bids() {
 
     // Same bidder cannot place a second bid
     let duplicate_bid = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_1(), // Same bidder
         at: sample_bid_time(),
         bid_amount: 15, // Different amount
+        placed_by: None,
     };
 
     let (_, result) = state_with_bid.add_bid(duplicate_bid);
@@ -96,10 +98,12 @@ fn test_cannot_bid_after_end() {
 
     // Try to place a bid after end
     let late_bid = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_1(),
         at: sample_ends_at() + Duration::seconds(2),
         bid_amount: 10,
+        placed_by: None,
     };
 
     let (_, result) = ended_state.add_bid(late_bid);
@@ -113,3 +117,160 @@ fn test_cannot_bid_after_end() {
         _ => panic!("Expected AuctionHasEnded error"),
     }
 }
+
+#[test]
+fn test_blind_auction_outcomes() {
+    let empty_blind_auction_state = match empty_state(&sample_blind_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    assert_eq!(empty_blind_auction_state.outcome(), AuctionOutcome::Ongoing);
+
+    // Ended without bids
+    let ended_without_bids = empty_blind_auction_state.inc(sample_ends_at());
+    assert_eq!(ended_without_bids.outcome(), AuctionOutcome::NoBids);
+
+    // Ended with bids, the highest bidder pays their own bid
+    let (state_with_1_bid, _) = empty_blind_auction_state.add_bid(bid_1());
+    let (state_with_2_bids, _) = state_with_1_bid.add_bid(bid_2());
+    assert_eq!(state_with_2_bids.outcome(), AuctionOutcome::Ongoing);
+    assert_eq!(
+        state_with_2_bids.inc(sample_ends_at()).outcome(),
+        AuctionOutcome::Sold { price: bid_amount_2(), winner: buyer_2().user_id().clone() }
+    );
+}
+
+#[test]
+fn test_blind_auction_expiry_boundary() {
+    let state = match empty_state(&sample_blind_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    test_expiry_boundary(&state);
+}
+
+#[test]
+fn test_blind_auction_start_boundary() {
+    let state = match empty_state(&sample_blind_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    test_start_boundary(&state);
+}
+
+#[test]
+fn test_blind_auction_awaits_start() {
+    let state = match empty_state(&sample_blind_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    assert!(matches!(state, SBState::AwaitingStart { .. }));
+
+    // A bid before the start is rejected
+    let early_bid = Bid {
+        at: sample_starts_at() - Duration::seconds(1),
+        ..bid_1()
+    };
+    let (state_before_start, result) = state.add_bid(early_bid);
+    assert_eq!(result, Err(Errors::AuctionHasNotStarted(sample_auction_id())));
+    assert!(matches!(state_before_start, SBState::AwaitingStart { .. }));
+    assert_eq!(state_before_start.outcome(), AuctionOutcome::Ongoing);
+
+    // Bids are accepted once the start has passed
+    let (accepting, result) = state.add_bid(Bid { at: sample_starts_at() + Duration::seconds(1), ..bid_1() });
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(matches!(accepting, SBState::AcceptingBids { .. }));
+
+    // An auction without bids that is never incremented before expiry ends without bids
+    let ended = state.inc(sample_ends_at());
+    assert!(ended.has_ended());
+    assert_eq!(ended.outcome(), AuctionOutcome::NoBids);
+}
+
+#[test]
+fn test_blind_auction_has_started() {
+    let state = match empty_state(&sample_blind_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    assert!(!state.has_started());
+    assert!(!state.inc(sample_starts_at()).has_started());
+    assert!(state.inc(sample_starts_at() + Duration::seconds(1)).has_started());
+}
+
+#[test]
+fn test_blind_auction_phase_names() {
+    let state = empty_state(&sample_blind_auction());
+    assert_eq!(state.phase_name(), "awaiting_start");
+    assert_eq!(state.inc(sample_starts_at()).phase_name(), "awaiting_start");
+    assert_eq!(state.inc(sample_starts_at() + Duration::seconds(1)).phase_name(), "accepting_bids");
+    assert_eq!(state.inc(sample_ends_at()).phase_name(), "disclosing_bids");
+}
+
+#[test]
+fn test_blind_auction_allows_revision() {
+    let auction = sample_auction_of_type(AuctionType::SingleSealedBid(SBOptions {
+        allow_revision: true,
+        ..SBOptions::blind()
+    }));
+    let state = match empty_state(&auction) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    let (state, _) = state.add_bid(bid_1());
+    let (state, _) = state.add_bid(bid_2());
+
+    // The revised bid replaces the earlier amount of the bidder
+    let revised_bid = Bid {
+        at: sample_starts_at() + Duration::seconds(3),
+        bid_amount: 15,
+        ..bid_1()
+    };
+    let (state, result) = state.add_bid(revised_bid.clone());
+    assert!(result.is_ok(), "{:?}", result);
+    assert_eq!(state.bids_of(&buyer_1().user_id().clone()), vec![revised_bid]);
+
+    let ended = state.inc(sample_ends_at());
+    assert_eq!(ended.get_bids().len(), 2);
+    assert_eq!(ended.outcome(), AuctionOutcome::Sold { price: 15, winner: buyer_1().user_id().clone() });
+}
+
+#[test]
+fn test_sealed_bid_options_serialization() {
+    let options = SBOptions { allow_revision: true, ..SBOptions::vickrey() };
+    assert_eq!(options.to_string(), "Vickrey|AllowRevision");
+    assert_eq!("Vickrey|AllowRevision".parse::<SBOptions>().unwrap(), options);
+    assert_eq!("Blind".parse::<SBOptions>().unwrap(), SBOptions::blind());
+    assert!("Blind|Unknown".parse::<SBOptions>().is_err());
+
+    // States keep the string form, states written before the option still read
+    assert_eq!(serde_json::to_value(&options).unwrap(), serde_json::json!("Vickrey|AllowRevision"));
+    assert_eq!(serde_json::from_value::<SBOptions>(serde_json::json!("Blind")).unwrap(), SBOptions::blind());
+}
+
+#[test]
+fn test_sealed_bid_disclosure_after_close() {
+    let bid_sealed = |options: SBOptions| {
+        let auction = sample_auction_of_type(AuctionType::SingleSealedBid(options));
+        let (state, _) = empty_state(&auction).add_bid(bid_1());
+        let (state, _) = state.add_bid(bid_2());
+        state.close(sample_bid_time() + Duration::seconds(3))
+    };
+
+    // All bids are disclosed by default
+    let disclosed = bid_sealed(SBOptions::vickrey());
+    assert_eq!(disclosed.get_bids().len(), 2);
+
+    let options = SBOptions { disclose_all: false, ..SBOptions::vickrey() };
+    assert_eq!(options.to_string(), "Vickrey|DiscloseWinnerOnly");
+    assert_eq!("Vickrey|DiscloseWinnerOnly".parse::<SBOptions>().unwrap(), options);
+
+    // Only the winning bid is disclosed, the price is still the second highest bid
+    let winner_only = bid_sealed(options);
+    let bids = winner_only.get_bids();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].bidder, buyer_2());
+    assert_eq!(winner_only.try_get_amount_and_winner(), disclosed.try_get_amount_and_winner());
+    // The losing bidder still sees their own bid
+    assert_eq!(winner_only.bids_of(buyer_1().user_id()), vec![bid_1()]);
+}
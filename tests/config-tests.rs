@@ -0,0 +1,102 @@
+use auction_site::config::{parse_bind_addr, parse_port, SiteConfig, DEFAULT_BIND_ADDR, DEFAULT_PORT};
+use auction_site::domain::{AuctionType, FeeSchedule, FeeTier};
+use auction_site::domain::timed_ascending::Options as TAOptions;
+use auction_site::money::{Amount, Currency};
+use std::collections::HashMap;
+use time::Duration;
+
+#[test]
+fn test_parse_port() {
+    // Valid port
+    assert_eq!(parse_port(Some("9000")), Ok(9000));
+
+    // Missing port falls back to the default
+    assert_eq!(parse_port(None), Ok(DEFAULT_PORT));
+
+    // Malformed ports are rejected
+    assert!(parse_port(Some("http")).is_err());
+    assert!(parse_port(Some("70000")).is_err());
+}
+
+#[test]
+fn test_parse_bind_addr() {
+    assert_eq!(parse_bind_addr(Some("0.0.0.0")), Ok("0.0.0.0".to_string()));
+    assert_eq!(parse_bind_addr(None), Ok(DEFAULT_BIND_ADDR.to_string()));
+    assert!(parse_bind_addr(Some("localhost:8080")).is_err());
+}
+
+fn config_from(vars: &[(&str, &str)]) -> Result<SiteConfig, String> {
+    let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+    SiteConfig::from_env(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn test_site_config_from_env() {
+    let config = config_from(&[
+        ("MIN_AUCTION_DURATION_SECS", "3600"),
+        ("BID_BURST", "3"),
+        ("BID_REFILL_INTERVAL_MS", "500"),
+        ("MAX_TITLE_LENGTH", "80"),
+        ("DEFAULT_CURRENCY", "SEK"),
+        ("DEFAULT_AUCTION_TYPE", "English|100|10|0"),
+        ("FEE_TIERS", "0:500, 100000:250"),
+        ("MAX_ACTIVE_AUCTIONS_PER_SELLER", "5"),
+        ("ALLOWED_CURRENCIES", "SEK,DKK"),
+        ("MAX_AMOUNTS", "SEK1000000"),
+        ("MAX_MIN_RAISE_BPS", "2000"),
+        ("BLOCK_SHILL_BIDS", "true"),
+        ("SWEEP_INTERVAL_MS", "250"),
+    ]).unwrap();
+
+    assert_eq!(config.min_auction_duration, Duration::hours(1));
+    assert_eq!(config.bid_burst, 3);
+    assert_eq!(config.bid_refill_interval, std::time::Duration::from_millis(500));
+    assert_eq!(config.max_title_length, 80);
+    assert_eq!(config.default_currency, Currency::SEK);
+    // Resolved in the currency of each auction
+    let default_auction_type = config.default_auction_type.as_ref().unwrap();
+    assert_eq!(default_auction_type.resolve(Currency::SEK), AuctionType::TimedAscending(TAOptions {
+        reserve_price: Amount::new(Currency::SEK, 100),
+        min_raise: Amount::new(Currency::SEK, 10),
+        ..TAOptions::default_options(Currency::SEK)
+    }));
+    assert_eq!(default_auction_type.resolve(Currency::DKK), AuctionType::TimedAscending(TAOptions {
+        reserve_price: Amount::new(Currency::DKK, 100),
+        min_raise: Amount::new(Currency::DKK, 10),
+        ..TAOptions::default_options(Currency::DKK)
+    }));
+    assert_eq!(config.fee_schedule, FeeSchedule::tiered(vec![
+        FeeTier { from: 0, rate_bps: 500 },
+        FeeTier { from: 100000, rate_bps: 250 },
+    ]).unwrap());
+    assert_eq!(config.max_active_auctions_per_seller, Some(5));
+    assert_eq!(config.allowed_currencies, Some(vec![Currency::SEK, Currency::DKK]));
+    assert_eq!(config.max_amounts, vec![Amount::new(Currency::SEK, 1000000)]);
+    assert_eq!(config.max_min_raise_bps, Some(2000));
+    assert!(config.block_shill_bids);
+    assert_eq!(config.sweep_interval, std::time::Duration::from_millis(250));
+}
+
+#[test]
+fn test_site_config_from_env_defaults_and_errors() {
+    let config = config_from(&[]).unwrap();
+    let default = SiteConfig::default();
+    assert_eq!(config.max_amounts, default.max_amounts);
+    assert_eq!(config.allowed_currencies, default.allowed_currencies);
+    assert_eq!(config.fee_schedule, default.fee_schedule);
+
+    assert!(config_from(&[("BID_BURST", "many")]).is_err());
+    assert!(config_from(&[("DEFAULT_AUCTION_TYPE", "Dutch")]).is_err());
+    assert!(config_from(&[("ALLOWED_CURRENCIES", "SEK,XYZ")]).is_err());
+    assert!(config_from(&[("FEE_TIERS", "0-500")]).is_err());
+    assert_eq!(
+        config_from(&[("FEE_TIERS", "0:500,100000:10001")]).unwrap_err(),
+        "Invalid FEE_TIERS: 100000:10001, a rate is at most 10000 basis points"
+    );
+    assert_eq!(
+        config_from(&[("FEE_TIERS", "100000:250,0:500")]).unwrap_err(),
+        "Invalid FEE_TIERS: 100000:250,0:500, the tiers must be in increasing order"
+    );
+    assert!(config_from(&[("FEE_TIERS", "0:500,0:250")]).is_err());
+    assert!(config_from(&[("BLOCK_SHILL_BIDS", "yes")]).is_err());
+}
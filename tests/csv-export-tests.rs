@@ -0,0 +1,63 @@
+use auction_site::domain::{handle, handle_all, sweep_ended, Auction, Command, Repository};
+use auction_site::persistence::csv::export_results;
+use time::Duration;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+fn ended_repository() -> Repository {
+    let auction = Auction { title: "Lamp, vintage".to_string(), ..sample_timed_asc_auction() };
+    let ongoing = Auction { auction_id: 2, expiry: sample_ends_at() + Duration::days(1), ..sample_timed_asc_auction() };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction },
+        Command::AddAuction { timestamp: sample_starts_at(), auction: ongoing },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+    ];
+    let mut repository = commands.into_iter().fold(Repository::new(), |repository, command| {
+        let (_, repository) = handle(command, repository).unwrap();
+        repository
+    });
+    sweep_ended(&mut repository, sample_ends_at());
+    repository
+}
+
+#[test]
+fn test_export_results_of_ended_auctions() {
+    let mut written = Vec::new();
+    export_results(&ended_repository(), sample_ends_at(), &mut written).unwrap();
+    let csv = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines[0], "id,title,seller,currency,winner,price,bids,start,end");
+    // Only the ended auction is exported, the title with a comma is quoted
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1], format!(
+        "{},\"Lamp, vintage\",{},SEK,{},SEK{},2,2016-01-01T08:28:00Z,2016-02-01T08:28:00Z",
+        sample_auction_id(), sample_seller().user_id(), buyer_2().user_id(), bid_amount_2()
+    ));
+}
+
+#[test]
+fn test_export_results_of_expired_auction_without_later_commands() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+    ];
+    let (_, repository) = handle_all(commands, Repository::new());
+
+    let mut written = Vec::new();
+    export_results(&repository, sample_ends_at() + Duration::seconds(1), &mut written).unwrap();
+    let csv = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with(&format!(
+        "{},auction,{},SEK,{},SEK{},1,",
+        sample_auction_id(), sample_seller().user_id(), buyer_1().user_id(), bid_amount_1()
+    )), "{}", lines[1]);
+
+    // Not yet ended before the expiry
+    let mut written = Vec::new();
+    export_results(&repository, sample_bid_time(), &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap().lines().count(), 1);
+}
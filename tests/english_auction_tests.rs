@@ -1,14 +1,14 @@
 use auction_site::domain::{
-    AuctionType, Auction, Bid,
+    AuctionType, Auction, Bid, Command, HandleError, Repository,
     timed_ascending::{self, TimedAscendingState as TAState},
-    states::State,
-    AuctionState, empty_state,
+    states::{AuctionOutcome, State},
+    AuctionState, empty_state, handle, validate_auction,
     core::Errors
 };
 use auction_site::money::{Amount, Currency};
 use time::Duration;
 use std::str::FromStr;
-#[path="utils/mod.rs"] mod utils;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
 use utils::*;
 
 #[test]
@@ -84,8 +84,8 @@ fn test_english_auction_states() {
 #[test]
 fn test_english_auction_type_serialization() {
     // Sample type string
-    let sample_type_str = "English|0|0|0";
-    let sample_type = timed_ascending::Options::default_options();
+    let sample_type_str = "English|VAC0|VAC0|0";
+    let sample_type = timed_ascending::Options::default_options(Currency::VAC);
 
     // Can deserialize sample type
     let parsed = timed_ascending::Options::from_str(sample_type_str).unwrap();
@@ -96,12 +96,18 @@ fn test_english_auction_type_serialization() {
     // Can serialize sample type
     assert_eq!(sample_type.to_string(), sample_type_str);
 
+    // Can deserialize the legacy format without currencies
+    let legacy_type_str = "English|0|0|0";
+    let parsed = timed_ascending::Options::from_str(legacy_type_str).unwrap();
+    assert_eq!(parsed, sample_type);
+
     // Sample with values
-    let sample_with_values_type_str = "English|10|20|30";
+    let sample_with_values_type_str = "English|SEK10|SEK20|30";
     let sample_with_values_type = timed_ascending::Options {
-        reserve_price: 10,
-        min_raise: 20,
+        reserve_price: sek(10),
+        min_raise: sek(20),
         time_frame: Duration::seconds(30),
+        ..timed_ascending::Options::default_options(Currency::SEK)
     };
 
     // Can deserialize sample with values type
@@ -114,6 +120,28 @@ fn test_english_auction_type_serialization() {
     assert_eq!(sample_with_values_type.to_string(), sample_with_values_type_str);
 }
 
+#[test]
+fn test_english_auction_options_json_time_frame_in_seconds() {
+    let options = timed_ascending::Options {
+        reserve_price: sek(10),
+        min_raise: sek(20),
+        time_frame: Duration::minutes(5),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    };
+
+    let json = serde_json::to_value(&options).unwrap();
+    assert_eq!(json["time_frame"], 300);
+    assert_eq!(json["reserve_price"], "SEK10");
+
+    let parsed: timed_ascending::Options = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, options);
+
+    // The (seconds, nanoseconds) pair written before is still read
+    let legacy = serde_json::json!({ "reserve_price": "SEK10", "min_raise": "SEK20", "time_frame": [300, 0] });
+    let parsed: timed_ascending::Options = serde_json::from_value(legacy).unwrap();
+    assert_eq!(parsed, options);
+}
+
 #[test]
 fn test_english_auction_with_reserve_price() {
     // Create auction with reserve price
@@ -124,11 +152,14 @@ fn test_english_auction_with_reserve_price() {
         expiry: sample_ends_at(),
         seller: sample_seller(),
         auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
         typ: AuctionType::TimedAscending(
             timed_ascending::Options {
-                reserve_price: 15, // Reserve price higher than bids
-                min_raise: 0,
+                reserve_price: sek(15), // Reserve price higher than bids
+                min_raise: sek(0),
                 time_frame: Duration::seconds(0),
+                ..timed_ascending::Options::default_options(Currency::SEK)
             }
         ),
     };
@@ -162,11 +193,14 @@ fn test_english_auction_with_min_raise() {
         expiry: sample_ends_at(),
         seller: sample_seller(),
         auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
         typ: AuctionType::TimedAscending(
             timed_ascending::Options {
-                reserve_price: 0,
-                min_raise: 5, // Require bids to be at least 5 higher than current
+                reserve_price: sek(0),
+                min_raise: sek(5), // Require bids to be at least 5 higher than current
                 time_frame: Duration::seconds(0),
+                ..timed_ascending::Options::default_options(Currency::SEK)
             }
         ),
     };
@@ -185,10 +219,12 @@ fn test_english_auction_with_min_raise() {
 
     // Second bid must be at least 5 more than first (10 + 5 = 15)
     let small_raise_bid = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_2(),
         at: sample_starts_at() + Duration::seconds(2),
         bid_amount: 14, // Only 4 more than first bid
+        placed_by: None,
     };
 
     let (_, result) = state_with_bid.add_bid(small_raise_bid);
@@ -196,10 +232,12 @@ fn test_english_auction_with_min_raise() {
 
     // Bid with sufficient raise
     let sufficient_raise_bid = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_2(),
         at: sample_starts_at() + Duration::seconds(2),
         bid_amount: 15, // 5 more than first bid
+        placed_by: None,
     };
 
     let (state_with_second_bid, result_s) = state_with_bid.add_bid(sufficient_raise_bid);
@@ -221,11 +259,14 @@ fn test_auction_extends_when_bids_placed_near_end() {
         expiry: sample_ends_at(),
         seller: sample_seller(),
         auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
         typ: AuctionType::TimedAscending(
             timed_ascending::Options {
-                reserve_price: 0,
-                min_raise: 0,
+                reserve_price: sek(0),
+                min_raise: sek(0),
                 time_frame: Duration::minutes(5), // 5 minute extension when bid placed
+                ..timed_ascending::Options::default_options(Currency::SEK)
             }
         ),
     };
@@ -241,10 +282,12 @@ fn test_auction_extends_when_bids_placed_near_end() {
     // Place bid near the end
     let almost_ending_time = sample_ends_at() - Duration::seconds(30);
     let near_end_bid = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_1(),
         at: almost_ending_time,
         bid_amount: 10,
+        placed_by: None,
     };
 
     let (state_with_bid, result) = started_state.add_bid(near_end_bid);
@@ -259,3 +302,601 @@ fn test_auction_extends_when_bids_placed_near_end() {
     let state_after_extension = state_at_original_end.inc(extended_end_time);
     assert!(state_after_extension.has_ended());
 }
+
+#[test]
+fn test_time_left_reflects_extended_expiry() {
+    let auction_with_extension = Auction {
+        auction_id: sample_auction_id(),
+        title: sample_title(),
+        starts_at: sample_starts_at(),
+        expiry: sample_ends_at(),
+        seller: sample_seller(),
+        auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
+        typ: AuctionType::TimedAscending(
+            timed_ascending::Options {
+                reserve_price: sek(0),
+                min_raise: sek(0),
+                time_frame: Duration::minutes(5),
+                ..timed_ascending::Options::default_options(Currency::SEK)
+            }
+        ),
+    };
+
+    let state = match empty_state(&auction_with_extension) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+
+    // Before any bids, time left counts down to the original expiry
+    let started_state = state.inc(sample_starts_at() + Duration::seconds(1));
+    let now = sample_ends_at() - Duration::seconds(30);
+    assert_eq!(started_state.time_left(now), Some(Duration::seconds(30)));
+
+    // A bid near the end pushes out the effective end
+    let near_end_bid = Bid {
+        bid_id: None,
+        for_auction: sample_auction_id(),
+        bidder: buyer_1(),
+        at: now,
+        bid_amount: 10,
+        placed_by: None,
+    };
+    let (state_with_bid, result) = started_state.add_bid(near_end_bid);
+    assert!(result.is_ok());
+    assert_eq!(state_with_bid.time_left(now), Some(Duration::minutes(5)));
+    assert_eq!(state_with_bid.time_left(sample_ends_at()), Some(Duration::seconds(270)));
+
+    // Once ended there is no time left
+    let ended_state = state_with_bid.inc(now + Duration::minutes(5));
+    assert!(ended_state.has_ended());
+    assert_eq!(ended_state.time_left(now + Duration::minutes(5)), None);
+}
+
+#[test]
+fn test_cannot_add_auction_with_reserve_in_other_currency() {
+    let auction = Auction {
+        auction_id: sample_auction_id(),
+        title: sample_title(),
+        starts_at: sample_starts_at(),
+        expiry: sample_ends_at(),
+        seller: sample_seller(),
+        auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
+        typ: AuctionType::TimedAscending(
+            timed_ascending::Options {
+                reserve_price: vac(10), // Reserve in another currency than the auction
+                min_raise: sek(0),
+                time_frame: Duration::seconds(0),
+                ..timed_ascending::Options::default_options(Currency::SEK)
+            }
+        ),
+    };
+
+    assert_eq!(
+        validate_auction(&auction),
+        Err(Errors::AuctionCurrencyMismatch((Currency::SEK, Currency::VAC)))
+    );
+
+    // The auction is rejected before being stored
+    let command = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction,
+    };
+    match handle(command, Repository::new()) {
+        Err(HandleError::AuctionError(Errors::AuctionCurrencyMismatch((expected, actual)))) => {
+            assert_eq!(expected, Currency::SEK);
+            assert_eq!(actual, Currency::VAC);
+        },
+        other => panic!("Expected AuctionCurrencyMismatch error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_first_and_last_bid_time() {
+    let state = match empty_state(&sample_timed_asc_auction()) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+    let started_state = state.inc(sample_starts_at() + Duration::seconds(1));
+
+    // No bids, no bid times
+    assert_eq!(started_state.first_bid_time(), None);
+    assert_eq!(started_state.last_bid_time(), None);
+
+    let (state_with_1_bid, _) = started_state.add_bid(bid_1());
+    let (state_with_2_bids, _) = state_with_1_bid.add_bid(bid_2());
+
+    assert_eq!(state_with_2_bids.first_bid_time(), Some(bid_1().at));
+    assert_eq!(state_with_2_bids.last_bid_time(), Some(bid_2().at));
+
+    // Still available once the auction has ended
+    let ended_state = state_with_2_bids.inc(sample_ends_at());
+    assert_eq!(ended_state.first_bid_time(), Some(bid_1().at));
+    assert_eq!(ended_state.last_bid_time(), Some(bid_2().at));
+}
+
+#[test]
+fn test_english_auction_outcomes() {
+    let auction_with_reserve = Auction {
+        auction_id: sample_auction_id(),
+        title: sample_title(),
+        starts_at: sample_starts_at(),
+        expiry: sample_ends_at(),
+        seller: sample_seller(),
+        auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
+        typ: AuctionType::TimedAscending(
+            timed_ascending::Options {
+                reserve_price: sek(11),
+                min_raise: sek(0),
+                time_frame: Duration::seconds(0),
+                ..timed_ascending::Options::default_options(Currency::SEK)
+            }
+        ),
+    };
+    let state = match empty_state(&auction_with_reserve) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+    let started_state = state.inc(sample_starts_at() + Duration::seconds(1));
+    assert_eq!(started_state.outcome(), AuctionOutcome::Ongoing);
+
+    // Ended without bids
+    let ended_without_bids = started_state.inc(sample_ends_at());
+    assert_eq!(ended_without_bids.outcome(), AuctionOutcome::NoBids);
+
+    // Ended with the highest bid below the reserve
+    let (state_with_1_bid, _) = started_state.add_bid(bid_1());
+    assert_eq!(
+        state_with_1_bid.inc(sample_ends_at()).outcome(),
+        AuctionOutcome::ReserveNotMet { highest: bid_amount_1() }
+    );
+
+    // Ended with the highest bid over the reserve
+    let (state_with_2_bids, _) = state_with_1_bid.add_bid(bid_2());
+    assert_eq!(
+        state_with_2_bids.inc(sample_ends_at()).outcome(),
+        AuctionOutcome::Sold { price: bid_amount_2(), winner: buyer_2().user_id().clone() }
+    );
+}
+
+#[test]
+fn test_english_auction_expiry_boundary() {
+    let state = match empty_state(&sample_timed_asc_auction()) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+    test_expiry_boundary(&state);
+}
+
+#[test]
+fn test_english_auction_start_boundary() {
+    let state = match empty_state(&sample_timed_asc_auction()) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+    test_start_boundary(&state);
+}
+
+#[test]
+fn test_english_auction_self_outbid_option() {
+    let started_state_with = |allow_self_outbid: bool| {
+        let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+            allow_self_outbid,
+            ..timed_ascending::Options::default_options(Currency::SEK)
+        }));
+        let state = match empty_state(&auction) {
+            AuctionState::TimedAscending(state) => state,
+            _ => panic!("Expected TimedAscending state"),
+        };
+        let (state, result) = state.add_bid(bid_1());
+        assert!(result.is_ok());
+        state
+    };
+    let raise_by_buyer_1 = Bid {
+        at: sample_starts_at() + Duration::seconds(2),
+        bid_amount: 15,
+        ..bid_1()
+    };
+
+    // Allowed by default
+    let (_, result) = started_state_with(true).add_bid(raise_by_buyer_1.clone());
+    assert!(result.is_ok());
+
+    let (_, result) = started_state_with(false).add_bid(raise_by_buyer_1);
+    assert_eq!(result, Err(Errors::AlreadyHighestBidder));
+
+    // Another bidder is always allowed
+    let (_, result) = started_state_with(false).add_bid(bid_2());
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_english_auction_self_outbid_serialization() {
+    let options = timed_ascending::Options {
+        allow_self_outbid: false,
+        ..timed_ascending::Options::default_options(Currency::VAC)
+    };
+    assert_eq!(options.to_string(), "English|VAC0|VAC0|0|NoSelfOutbid");
+    assert_eq!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|NoSelfOutbid").unwrap(), options);
+    assert!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|Other").is_err());
+
+    // Older forms allow self outbid
+    assert!(timed_ascending::Options::from_str("English|VAC0|VAC0|0").unwrap().allow_self_outbid);
+    let json = serde_json::json!({ "reserve_price": "VAC0", "min_raise": "VAC0", "time_frame": 0 });
+    assert!(serde_json::from_value::<timed_ascending::Options>(json).unwrap().allow_self_outbid);
+}
+
+#[test]
+fn test_english_auction_has_started() {
+    let state = match empty_state(&sample_timed_asc_auction()) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+    assert!(!state.has_started());
+    assert!(!state.inc(sample_starts_at()).has_started());
+
+    let started_state = state.inc(sample_starts_at() + Duration::seconds(1));
+    assert!(started_state.has_started());
+    assert!(!started_state.has_ended());
+
+    // An ended auction has also started
+    assert!(state.inc(sample_ends_at()).has_started());
+}
+
+#[test]
+fn test_auction_extensions_are_capped() {
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+        reserve_price: sek(0),
+        min_raise: sek(0),
+        time_frame: Duration::minutes(5),
+        max_extensions: Some(2),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    }));
+    let state = match empty_state(&auction) {
+        AuctionState::TimedAscending(state) => state,
+        _ => panic!("Expected TimedAscending state"),
+    };
+
+    // Every bid is placed a minute before the current expiry
+    let mut state = state.inc(sample_starts_at() + Duration::seconds(1));
+    let mut expiries = Vec::new();
+    for amount in 1..=4 {
+        let expiry = sample_starts_at() + state.time_left(sample_starts_at()).unwrap();
+        let bid = Bid {
+            at: expiry - Duration::minutes(1),
+            bid_amount: amount,
+            bidder: if amount % 2 == 0 { buyer_2() } else { buyer_1() },
+            ..bid_1()
+        };
+        let (next, result) = state.add_bid(bid);
+        assert!(result.is_ok(), "{:?}", result);
+        state = next;
+        expiries.push(sample_starts_at() + state.time_left(sample_starts_at()).unwrap());
+    }
+
+    let first_extension = sample_ends_at() + Duration::minutes(4);
+    let second_extension = first_extension + Duration::minutes(4);
+    assert_eq!(expiries, vec![first_extension, second_extension, second_extension, second_extension]);
+    match &state {
+        TAState::OnGoing { extensions, .. } => assert_eq!(*extensions, 2),
+        other => panic!("Expected OnGoing state, got {:?}", other),
+    }
+
+    // The auction ends at the capped expiry
+    assert!(state.inc(second_extension).has_ended());
+}
+
+#[test]
+fn test_max_extensions_serialization() {
+    let options = timed_ascending::Options {
+        max_extensions: Some(3),
+        ..timed_ascending::Options::default_options(Currency::VAC)
+    };
+    assert_eq!(options.to_string(), "English|VAC0|VAC0|0|MaxExtensions=3");
+    assert_eq!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|MaxExtensions=3").unwrap(), options);
+
+    let both = timed_ascending::Options {
+        allow_self_outbid: false,
+        ..options
+    };
+    assert_eq!(timed_ascending::Options::from_str(&both.to_string()).unwrap(), both);
+    assert!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|MaxExtensions=x").is_err());
+}
+
+#[test]
+fn test_hide_bidders_serialization() {
+    let options = timed_ascending::Options {
+        hide_bidders: true,
+        ..timed_ascending::Options::default_options(Currency::VAC)
+    };
+    assert_eq!(options.to_string(), "English|VAC0|VAC0|0|HideBidders");
+    assert_eq!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|HideBidders").unwrap(), options);
+    assert!(!timed_ascending::Options::from_str("English|VAC0|VAC0|0").unwrap().hide_bidders);
+}
+
+#[test]
+fn test_english_auction_strict_increase_option() {
+    let started_state_with = |strict_increase: bool| {
+        let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+            strict_increase,
+            ..timed_ascending::Options::default_options(Currency::SEK)
+        }));
+        let state = match empty_state(&auction) {
+            AuctionState::TimedAscending(state) => state,
+            _ => panic!("Expected TimedAscending state"),
+        };
+        let (state, result) = state.add_bid(bid_1());
+        assert!(result.is_ok());
+        state
+    };
+    let equal_bid = Bid {
+        bid_amount: bid_amount_1(),
+        ..bid_2()
+    };
+
+    // With no min raise an equal bid is accepted by default
+    let (_, result) = started_state_with(false).add_bid(equal_bid.clone());
+    assert!(result.is_ok());
+
+    let (_, result) = started_state_with(true).add_bid(equal_bid);
+    assert_eq!(result, Err(Errors::MustPlaceBidOverHighestBid(bid_amount_1())));
+
+    let (_, result) = started_state_with(true).add_bid(bid_2());
+    assert!(result.is_ok());
+
+    let options = timed_ascending::Options {
+        strict_increase: true,
+        ..timed_ascending::Options::default_options(Currency::VAC)
+    };
+    assert_eq!(options.to_string(), "English|VAC0|VAC0|0|StrictIncrease");
+    assert_eq!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|StrictIncrease").unwrap(), options);
+}
+
+#[test]
+fn test_english_auction_phase_names() {
+    let state = empty_state(&sample_timed_asc_auction());
+    assert_eq!(state.phase_name(), "awaiting_start");
+    assert_eq!(state.inc(sample_starts_at() + Duration::seconds(1)).phase_name(), "on_going");
+    assert_eq!(state.inc(sample_ends_at() + Duration::seconds(1)).phase_name(), "has_ended");
+}
+
+#[test]
+fn test_english_auction_clock_skew_grace() {
+    let started_state_with = |grace: Duration| {
+        let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+            time_frame: Duration::seconds(30),
+            clock_skew_grace: grace,
+            ..timed_ascending::Options::default_options(Currency::SEK)
+        }));
+        let state = match empty_state(&auction) {
+            AuctionState::TimedAscending(state) => state,
+            _ => panic!("Expected TimedAscending state"),
+        };
+        let (state, result) = state.add_bid(bid_1());
+        assert!(result.is_ok());
+        state
+    };
+    let late_bid = |seconds: i64| Bid {
+        at: sample_ends_at() + Duration::seconds(seconds),
+        ..bid_2()
+    };
+
+    // A bid within the grace is accepted and extends the auction
+    let (state, result) = started_state_with(Duration::seconds(5)).add_bid(late_bid(2));
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(!state.has_ended());
+    assert_eq!(state.time_left(late_bid(2).at), Some(Duration::seconds(30)));
+
+    let (_, result) = started_state_with(Duration::seconds(5)).add_bid(late_bid(6));
+    assert_eq!(result, Err(Errors::AuctionHasEnded(sample_auction_id())));
+
+    let (_, result) = started_state_with(Duration::ZERO).add_bid(late_bid(2));
+    assert_eq!(result, Err(Errors::AuctionHasEnded(sample_auction_id())));
+
+    // Without a late bid the auction is only seen as ended once the grace has passed as well
+    assert!(!started_state_with(Duration::seconds(5)).inc(sample_ends_at()).has_ended());
+    assert!(started_state_with(Duration::seconds(5)).inc(sample_ends_at() + Duration::seconds(5)).has_ended());
+
+    let options = timed_ascending::Options {
+        clock_skew_grace: Duration::seconds(5),
+        ..timed_ascending::Options::default_options(Currency::VAC)
+    };
+    assert_eq!(options.to_string(), "English|VAC0|VAC0|0|ClockSkewGrace=5");
+    assert_eq!(timed_ascending::Options::from_str("English|VAC0|VAC0|0|ClockSkewGrace=5").unwrap(), options);
+}
+
+#[test]
+fn test_english_auction_secret_reserve() {
+    let options = timed_ascending::Options {
+        reserve_price: sek(100),
+        reserve_secret: true,
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    };
+    // The full form keeps the reserve for the command log
+    assert_eq!(options.to_string(), "English|SEK100|SEK0|0|ReserveSecret");
+    assert_eq!(timed_ascending::Options::from_str("English|SEK100|SEK0|0|ReserveSecret").unwrap(), options);
+    assert_eq!(options.public_string(), "English|*|SEK0|0|ReserveSecret");
+    assert_eq!(AuctionType::TimedAscending(options.clone()).public_string(), "English|*|SEK0|0|ReserveSecret");
+
+    // The reserve is still enforced
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(options));
+    let state = empty_state(&auction);
+    let (state, result) = state.add_bid(bid_1());
+    assert!(result.is_ok());
+    assert_eq!(state.inc(sample_ends_at()).try_get_amount_and_winner(), None);
+    let (state, result) = state.add_bid(Bid { bid_amount: 150, ..bid_2() });
+    assert!(result.is_ok());
+    assert_eq!(state.inc(sample_ends_at()).try_get_amount_and_winner(), Some((150, buyer_2().user_id().clone())));
+}
+
+#[test]
+fn test_english_auction_bid_history_cap() {
+    let options = timed_ascending::Options {
+        max_history: Some(2),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    };
+    assert_eq!(options.to_string(), "English|SEK0|SEK0|0|MaxHistory=2");
+    assert_eq!(timed_ascending::Options::from_str("English|SEK0|SEK0|0|MaxHistory=2").unwrap(), options);
+
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(options));
+    let bidders = [buyer_1(), buyer_2(), buyer_3(), buyer_1()];
+    let state = bidders.iter().enumerate().fold(empty_state(&auction), |state, (i, bidder)| {
+        let bid = Bid { bidder: bidder.clone(), bid_amount: 10 * (i as i64 + 1), ..bid_1() };
+        let (state, result) = state.add_bid(bid);
+        assert!(result.is_ok(), "{:?}", result);
+        state
+    });
+
+    // Only the latest and highest bids are kept, and the first one for when the bidding started
+    let amounts: Vec<i64> = state.get_bids().iter().map(|bid| bid.bid_amount).collect();
+    assert_eq!(amounts, vec![40, 30, 10]);
+    assert_eq!(state.inc(sample_ends_at()).try_get_amount_and_winner(), Some((40, buyer_1().user_id().clone())));
+
+    // Bids below the evicted ones are still rejected
+    let (_, result) = state.add_bid(Bid { bidder: buyer_2(), bid_amount: 15, ..bid_1() });
+    assert_eq!(result, Err(Errors::MustPlaceBidOverHighestBid(40)));
+}
+
+#[test]
+fn test_english_auction_bid_history_cap_keeps_what_evicted_bids_decide() {
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+        max_history: Some(2),
+        final_round: Some(timed_ascending::FinalRound { duration: Duration::minutes(10), bidders: 2 }),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    }));
+    // Buyer 2 raises their own bid until buyer 1's only bid is beyond the cap
+    let bids = [(buyer_3(), 10, 1), (buyer_1(), 20, 2), (buyer_2(), 30, 3), (buyer_2(), 40, 4), (buyer_2(), 50, 5)];
+    let state = bids.iter().fold(empty_state(&auction), |state, (bidder, amount, minutes)| {
+        let bid = Bid { bidder: bidder.clone(), bid_amount: *amount, at: sample_starts_at() + Duration::minutes(*minutes), ..bid_1() };
+        let (state, result) = state.add_bid(bid);
+        assert!(result.is_ok(), "{:?}", result);
+        state
+    });
+    let amounts: Vec<i64> = state.get_bids().iter().map(|bid| bid.bid_amount).collect();
+    assert_eq!(amounts, vec![50, 40, 20, 10]);
+    assert_eq!(state.first_bid_time(), Some(sample_starts_at() + Duration::minutes(1)));
+
+    // Buyer 1 still leads the final round along with buyer 2
+    let final_round = state.inc(sample_ends_at());
+    let sealed_bid = Bid { bidder: buyer_1(), bid_amount: 60, at: sample_ends_at() + Duration::minutes(1), ..bid_1() };
+    let (state, result) = final_round.add_bid(sealed_bid);
+    assert!(result.is_ok(), "{:?}", result);
+    let (_, result) = state.add_bid(Bid { bidder: buyer_3(), bid_amount: 70, at: sample_ends_at() + Duration::minutes(1), ..bid_1() });
+    assert_eq!(result, Err(Errors::NotInFinalRound(buyer_3().user_id().clone())));
+}
+
+#[test]
+fn test_english_auction_transitions_between() {
+    let auction = sample_timed_asc_auction();
+    let state = empty_state(&auction);
+    let before_start = sample_starts_at() - Duration::hours(1);
+    let after_end = sample_ends_at() + Duration::hours(1);
+
+    assert_eq!(state.transitions_between(before_start, after_end), vec![
+        (sample_starts_at(), "on_going"),
+        (sample_ends_at(), "has_ended"),
+    ]);
+    // Only the changes within the interval are reported
+    assert_eq!(state.transitions_between(sample_bid_time(), after_end), vec![(sample_ends_at(), "has_ended")]);
+    assert_eq!(state.transitions_between(before_start, sample_bid_time()), vec![(sample_starts_at(), "on_going")]);
+    assert!(state.transitions_between(after_end, after_end + Duration::days(1)).is_empty());
+}
+
+#[test]
+fn test_english_auction_final_round() {
+    let options = timed_ascending::Options {
+        final_round: Some(timed_ascending::FinalRound { duration: Duration::minutes(10), bidders: 2 }),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    };
+    assert_eq!(options.to_string(), "English|SEK0|SEK0|0|FinalRound=600/2");
+    assert_eq!(timed_ascending::Options::from_str("English|SEK0|SEK0|0|FinalRound=600/2").unwrap(), options);
+    assert!(timed_ascending::Options::from_str("English|SEK0|SEK0|0|FinalRound=600/0").is_err());
+
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(options));
+    let bids = [(buyer_1(), 10), (buyer_2(), 20), (buyer_3(), 30)];
+    let state = bids.iter().fold(empty_state(&auction), |state, (bidder, amount)| {
+        let (state, result) = state.add_bid(Bid { bidder: bidder.clone(), bid_amount: *amount, ..bid_1() });
+        assert!(result.is_ok(), "{:?}", result);
+        state
+    });
+    assert_eq!(state.scheduled_phase_changes(), vec![
+        (sample_ends_at(), "final_round"),
+        (sample_ends_at() + Duration::minutes(10), "has_ended"),
+    ]);
+    // The open bidding leads into the final round, so the auction ends after the round
+    assert_eq!(state.time_left(sample_ends_at() - Duration::minutes(1)), Some(Duration::minutes(11)));
+    let extended = state.extend(sample_ends_at() + Duration::minutes(20));
+    assert_eq!(extended.time_left(sample_ends_at()), Some(Duration::minutes(20)));
+
+    let final_round = state.inc(sample_ends_at());
+    assert_eq!(final_round.phase_name(), "final_round");
+    assert!(!final_round.has_ended());
+    let final_bid = |bidder, bid_amount| Bid { bidder, bid_amount, at: sample_ends_at() + Duration::minutes(1), ..bid_1() };
+
+    // Only the leading bidders of the open bidding take part
+    let (_, result) = final_round.add_bid(final_bid(buyer_1(), 50));
+    assert_eq!(result, Err(Errors::NotInFinalRound(buyer_1().user_id().clone())));
+    // A sealed bid still has to beat the open bidding
+    let (_, result) = final_round.add_bid(final_bid(buyer_2(), 25));
+    assert_eq!(result, Err(Errors::MustPlaceBidOverHighestBid(30)));
+
+    let (state, result) = final_round.add_bid(final_bid(buyer_2(), 45));
+    assert!(result.is_ok(), "{:?}", result);
+    let (state, result) = state.add_bid(final_bid(buyer_3(), 40));
+    assert!(result.is_ok(), "{:?}", result);
+    let (_, result) = state.add_bid(final_bid(buyer_3(), 60));
+    assert_eq!(result, Err(Errors::AlreadyPlacedBid));
+
+    // The sealed bids are not disclosed until the auction has ended
+    assert_eq!(state.get_bids().len(), 3);
+    assert_eq!(state.bids_of(buyer_2().user_id()).first().map(|bid| bid.bid_amount), Some(45));
+    assert_eq!(state.try_get_amount_and_winner(), None);
+
+    let ended = state.inc(sample_ends_at() + Duration::minutes(10));
+    assert!(ended.has_ended());
+    assert_eq!(ended.try_get_amount_and_winner(), Some((45, buyer_2().user_id().clone())));
+}
+
+#[test]
+fn test_english_auction_max_bid() {
+    let options = timed_ascending::Options {
+        max_bid: Some(Amount::new(Currency::SEK, 1000)),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    };
+    assert_eq!(options.to_string(), "English|SEK0|SEK0|0|MaxBid=SEK1000");
+    assert_eq!(timed_ascending::Options::from_str("English|SEK0|SEK0|0|MaxBid=SEK1000").unwrap(), options);
+
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(options));
+    let (state, result) = empty_state(&auction).add_bid(Bid { bid_amount: 1000, ..bid_1() });
+    assert!(result.is_ok(), "{:?}", result);
+    let (_, result) = state.add_bid(Bid { bid_amount: 1001, ..bid_2() });
+    assert_eq!(result, Err(Errors::BidExceedsMaximum(Amount::new(Currency::SEK, 1000))));
+
+    // The cap has to be in the auction currency
+    let mismatched = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+        max_bid: Some(Amount::new(Currency::VAC, 1000)),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    }));
+    assert_eq!(validate_auction(&mismatched), Err(Errors::AuctionCurrencyMismatch((Currency::SEK, Currency::VAC))));
+}
+
+#[test]
+fn test_english_auction_ended_view() {
+    let auction = sample_timed_asc_auction();
+    let (state, _) = empty_state(&auction).add_bid(bid_1());
+    let (state, _) = state.add_bid(bid_2());
+
+    assert_eq!(state.ended_view(sample_bid_time()), None);
+    assert_eq!(state.ended_view(sample_ends_at() - Duration::seconds(1)), None);
+
+    let ended = state.ended_view(sample_ends_at() + Duration::minutes(10)).unwrap();
+    assert_eq!(ended.winner.as_ref(), Some(buyer_2().user_id()));
+    assert_eq!(ended.price, Some(bid_amount_2()));
+    assert_eq!(ended.bid_count, 2);
+    assert_eq!(ended.outcome, AuctionOutcome::Sold { price: bid_amount_2(), winner: buyer_2().user_id().clone() });
+}
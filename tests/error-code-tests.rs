@@ -0,0 +1,95 @@
+use actix_web::body::to_bytes;
+use actix_web::ResponseError;
+use auction_site::domain::core::Errors;
+use auction_site::domain::{HandleError, Lang};
+use auction_site::web::errors::status_for;
+use auction_site::web::types::ApiError;
+use auction_site::money::{Amount, Currency};
+use std::collections::HashSet;
+
+fn all_errors() -> Vec<Errors> {
+    vec![
+        Errors::UnknownAuction(1),
+        Errors::AuctionAlreadyExists(1),
+        Errors::AuctionHasEnded(1),
+        Errors::AuctionHasNotStarted(1),
+        Errors::SellerCannotPlaceBids(("Sample_Seller".to_string(), 1)),
+        Errors::InvalidUserData("data".to_string()),
+        Errors::InvalidBidAmount(0),
+        Errors::AmountAboveCeiling(Amount::new(Currency::SEK, 100)),
+        Errors::MustPlaceBidOverHighestBid(10),
+        Errors::MustPlaceBidUnderLowestBid(10),
+        Errors::AlreadyPlacedBid,
+        Errors::AuctionCurrencyMismatch((Currency::SEK, Currency::VAC)),
+        Errors::BidCurrencyMismatch((Currency::SEK, Currency::DKK)),
+        Errors::InvalidAuctionDuration(1),
+        Errors::CannotCloseAuction(("Buyer_1".to_string(), 1)),
+        Errors::InvalidAuctionTitle("title must not be empty".to_string()),
+        Errors::BidderSuspended("Buyer_1".to_string()),
+        Errors::InvalidImageUrl("table.jpg".to_string()),
+        Errors::AlreadyHighestBidder,
+        Errors::BidOutOfOrder(1),
+        Errors::VersionConflict((1, 0)),
+        Errors::SellerAuctionLimitReached("Sample_Seller".to_string()),
+        Errors::SuspectedShillBid(1),
+        Errors::MinRaiseTooLarge(Amount::new(Currency::SEK, 100)),
+        Errors::BidExceedsMaximum(Amount::new(Currency::SEK, 100)),
+        Errors::NotInFinalRound("Buyer_3".to_string()),
+        Errors::CurrencyNotAllowed(Currency::SEK),
+        Errors::CannotExtendAuction(("Buyer_1".to_string(), 1)),
+        Errors::CannotShortenAuction(1),
+        Errors::BidAboveMaxPrice(Amount::new(Currency::SEK, 100)),
+    ]
+}
+
+#[test]
+fn test_error_codes_are_unique_and_non_empty() {
+    let errors = all_errors();
+
+    let codes: HashSet<&str> = errors.iter().map(|err| err.code()).collect();
+    assert_eq!(codes.len(), errors.len());
+    assert!(codes.iter().all(|code| !code.is_empty()));
+}
+
+#[test]
+fn test_error_code_does_not_depend_on_values() {
+    assert_eq!(Errors::UnknownAuction(1).code(), "unknown_auction");
+    assert_eq!(Errors::UnknownAuction(2).code(), "unknown_auction");
+    assert_eq!(Errors::AlreadyPlacedBid.code(), "already_placed_bid");
+}
+
+#[actix_web::test]
+async fn test_error_responses() {
+    for err in all_errors() {
+        let handle_err = HandleError::from(err.clone());
+        for resp in [err.error_response(), handle_err.error_response()] {
+            assert_eq!(resp.status(), status_for(&err), "{:?}", err);
+            let body: ApiError = serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+            assert_eq!(body.message, err.to_string());
+            assert_eq!(body.code.as_deref(), Some(err.code()));
+        }
+    }
+
+    assert_eq!(status_for(&Errors::UnknownAuction(1)), 404);
+    assert_eq!(status_for(&Errors::CannotCloseAuction(("Buyer_1".to_string(), 1))), 403);
+    assert_eq!(status_for(&Errors::VersionConflict((1, 0))), 409);
+    assert_eq!(status_for(&Errors::AuctionHasEnded(1)), 400);
+}
+
+#[test]
+fn test_localized_error_messages() {
+    assert_eq!(Errors::AuctionHasEnded(1).message(Lang::Sv), "Auktionen har avslutats: 1");
+    assert_eq!(Errors::AlreadyPlacedBid.message(Lang::Sv), "Du har redan lagt ett bud");
+    assert_eq!(Errors::AuctionHasEnded(1).message(Lang::En), Errors::AuctionHasEnded(1).to_string());
+    // Every error has a Swedish message
+    for err in all_errors() {
+        assert_ne!(err.message(Lang::Sv), err.to_string(), "{:?}", err);
+    }
+
+    assert_eq!(Lang::from_accept_language("sv-SE,sv;q=0.9,en;q=0.8"), Lang::Sv);
+    assert_eq!(Lang::from_accept_language("en;q=0.5, sv"), Lang::Sv);
+    // Languages without messages fall back to English
+    assert_eq!(Lang::from_accept_language("fr-FR"), Lang::En);
+    assert_eq!(Lang::from_accept_language("fr, sv;q=0.1"), Lang::Sv);
+    assert_eq!(Lang::from_accept_language(""), Lang::En);
+}
@@ -0,0 +1,19 @@
+use auction_site::domain::{Event, EventBus};
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+#[test]
+fn test_event_bus_without_runtime() {
+    let event_bus = EventBus::default();
+    // Publishing without subscribers drops the event
+    event_bus.publish(Event::BidAccepted { timestamp: sample_bid_time(), bid: bid_1() });
+
+    let mut first = event_bus.subscribe();
+    let mut second = event_bus.subscribe();
+    event_bus.publish(Event::BidAccepted { timestamp: sample_bid_time(), bid: bid_2() });
+
+    let expected = Event::BidAccepted { timestamp: sample_bid_time(), bid: bid_2() };
+    assert_eq!(first.try_recv().unwrap(), expected);
+    assert_eq!(second.try_recv().unwrap(), expected);
+    assert!(first.try_recv().is_err());
+}
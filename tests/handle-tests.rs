@@ -0,0 +1,480 @@
+use auction_site::domain::{
+    empty_state, handle, handle_all, handle_mut, sweep_ended, sweep_started, EventBus, RepositoryIndex, validate_auction_duration, Auction, Bid, BidId, Command, Event, HandleError, Repository, User,
+    auctions::AuctionType,
+    core::Errors,
+    states::State,
+    timed_ascending,
+};
+use auction_site::money::Currency;
+use std::collections::HashSet;
+use time::Duration;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+#[test]
+fn test_auction_must_end_after_it_starts() {
+    // Expiry equal to start
+    let mut auction = sample_vickrey_auction();
+    auction.expiry = auction.starts_at;
+    let command = Command::AddAuction { timestamp: sample_starts_at(), auction };
+    match handle(command, Repository::new()) {
+        Err(HandleError::AuctionError(Errors::InvalidAuctionDuration(id))) => {
+            assert_eq!(id, sample_auction_id());
+        },
+        other => panic!("Expected InvalidAuctionDuration error, got {:?}", other),
+    }
+
+    // Expiry before start
+    let mut auction = sample_vickrey_auction();
+    auction.expiry = auction.starts_at - Duration::seconds(1);
+    let command = Command::AddAuction { timestamp: sample_starts_at(), auction };
+    match handle(command, Repository::new()) {
+        Err(HandleError::AuctionError(Errors::InvalidAuctionDuration(id))) => {
+            assert_eq!(id, sample_auction_id());
+        },
+        other => panic!("Expected InvalidAuctionDuration error, got {:?}", other),
+    }
+
+    // Expiry after start
+    let command = Command::AddAuction { timestamp: sample_starts_at(), auction: sample_vickrey_auction() };
+    let result = handle(command, Repository::new());
+    assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn test_auction_minimum_duration() {
+    // The sample auction runs for a month
+    let auction = sample_vickrey_auction();
+    assert_eq!(validate_auction_duration(&auction, Duration::days(7)), Ok(()));
+    assert_eq!(
+        validate_auction_duration(&auction, Duration::days(60)),
+        Err(Errors::InvalidAuctionDuration(sample_auction_id()))
+    );
+}
+
+#[test]
+fn test_bids_are_assigned_unique_ids() {
+    let add_auction = Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() };
+    let (_, repository) = handle(add_auction, Repository::new()).unwrap();
+
+    let mut ids = Vec::new();
+    let mut repository = repository;
+    for bid in [bid_1(), bid_2()] {
+        let command = Command::PlaceBid { timestamp: bid.at, bid };
+        let (event, next) = handle(command, repository).unwrap();
+        match event {
+            Event::BidAccepted { bid, .. } => ids.push(bid.bid_id.expect("Expected bid id")),
+            _ => panic!("Expected BidAccepted event"),
+        }
+        repository = next;
+    }
+    assert_ne!(ids[0], ids[1]);
+
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    let stored_ids: Vec<_> = state.get_bids().iter().map(|bid| bid.bid_id.unwrap()).collect();
+    assert_eq!(stored_ids, vec![ids[1], ids[0]]);
+}
+
+#[test]
+fn test_replay_keeps_bid_ids() {
+    let bid_id = BidId::from_u128(42);
+    let add_auction = Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() };
+    let place_bid = Command::PlaceBid { timestamp: bid_1().at, bid: Bid { bid_id: Some(bid_id), ..bid_1() } };
+
+    // Round trip through the command log format
+    let log = serde_json::to_string(&vec![add_auction, place_bid]).unwrap();
+    assert!(log.contains(&bid_id.to_string()));
+    let commands: Vec<Command> = serde_json::from_str(&log).unwrap();
+
+    let mut repository = Repository::new();
+    for command in commands {
+        let (_, next) = handle(command, repository).unwrap();
+        repository = next;
+    }
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    assert_eq!(state.get_bids()[0].bid_id, Some(bid_id));
+}
+
+#[test]
+fn test_replay_of_bids_without_ids_gives_the_same_ids() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: Bid { bid_id: None, ..bid_1() } },
+        Command::PlaceBid { timestamp: bid_2().at, bid: Bid { bid_id: None, ..bid_2() } },
+    ];
+
+    let (first_results, first) = handle_all(commands.clone(), Repository::new());
+    let (second_results, second) = handle_all(commands, Repository::new());
+    let events = |results: Vec<Result<Event, HandleError>>| results.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(events(first_results), events(second_results));
+    let ids: Vec<_> = first.get_state(sample_auction_id()).unwrap().get_bids().iter().map(|bid| bid.bid_id).collect();
+    assert!(ids.iter().all(Option::is_some));
+    assert_ne!(ids[0], ids[1]);
+    assert_eq!(first.get_state(sample_auction_id()), second.get_state(sample_auction_id()));
+}
+
+#[test]
+fn test_handle_all_collects_results_and_keeps_going() {
+    let unknown_auction_bid = Bid {
+        for_auction: 99,
+        ..bid_2()
+    };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::PlaceBid { timestamp: unknown_auction_bid.at, bid: unknown_auction_bid },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+    ];
+
+    let (results, repository) = handle_all(commands, Repository::new());
+
+    assert_eq!(results.len(), 4);
+    assert!(matches!(results[0], Ok(Event::AuctionAdded { .. })));
+    assert!(matches!(results[1], Ok(Event::BidAccepted { .. })));
+    match &results[2] {
+        Err(HandleError::AuctionError(Errors::UnknownAuction(id))) => assert_eq!(*id, 99),
+        other => panic!("Expected UnknownAuction error, got {:?}", other),
+    }
+    assert!(matches!(results[3], Ok(Event::BidAccepted { .. })));
+
+    // The failing command did not stop the later ones
+    assert_eq!(repository.len(), 1);
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    let amounts: Vec<_> = state.get_bids().iter().map(|bid| bid.bid_amount).collect();
+    assert_eq!(amounts, vec![bid_amount_2(), bid_amount_1()]);
+}
+
+#[test]
+fn test_close_auction_determines_winner_at_close_time() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+    ];
+    let (_, repository) = handle_all(commands, Repository::new());
+
+    let closed_at = sample_bid_time();
+    // Only the seller or support may close
+    let close_as_buyer = Command::CloseAuction { timestamp: closed_at, auction_id: sample_auction_id(), user: buyer_1() };
+    match handle(close_as_buyer, repository.clone()) {
+        Err(HandleError::AuctionError(Errors::CannotCloseAuction((user_id, _)))) => assert_eq!(&user_id, buyer_1().user_id()),
+        other => panic!("Expected CannotCloseAuction error, got {:?}", other),
+    }
+
+    let close = Command::CloseAuction { timestamp: closed_at, auction_id: sample_auction_id(), user: sample_seller() };
+    let (event, repository) = handle(close.clone(), repository).unwrap();
+    assert!(matches!(event, Event::AuctionClosed { .. }));
+
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    assert!(state.has_ended());
+    assert_eq!(state.try_get_amount_and_winner(), Some((bid_amount_2(), buyer_2().user_id().clone())));
+    assert_eq!(state.time_left(closed_at), None);
+
+    // An ended auction cannot be closed again
+    match handle(close, repository) {
+        Err(HandleError::AuctionError(Errors::AuctionHasEnded(_))) => {},
+        other => panic!("Expected AuctionHasEnded error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_close_sealed_auction_discloses_bids() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_vickrey_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+        Command::CloseAuction {
+            timestamp: sample_bid_time(),
+            auction_id: sample_auction_id(),
+            user: User::Support { user_id: "Support_1".to_string() },
+        },
+    ];
+    let (results, repository) = handle_all(commands, Repository::new());
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    assert!(state.has_ended());
+    assert_eq!(state.get_bids().len(), 2);
+    assert_eq!(state.try_get_amount_and_winner(), Some((bid_amount_1(), buyer_2().user_id().clone())));
+}
+
+#[test]
+fn test_bid_before_latest_bid_is_rejected() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+    ];
+    let (_, repository) = handle_all(commands, Repository::new());
+
+    // bid_1 is placed a second before bid_2
+    let earlier_bid = Bid { bid_amount: 20, ..bid_1() };
+    match handle(Command::PlaceBid { timestamp: earlier_bid.at, bid: earlier_bid }, repository.clone()) {
+        Err(HandleError::AuctionError(Errors::BidOutOfOrder(id))) => assert_eq!(id, sample_auction_id()),
+        other => panic!("Expected BidOutOfOrder error, got {:?}", other),
+    }
+
+    // A bid at the same instant as the latest bid is in order
+    let same_time_bid = Bid { bid_amount: 20, at: bid_2().at, ..bid_1() };
+    assert!(handle(Command::PlaceBid { timestamp: same_time_bid.at, bid: same_time_bid }, repository).is_ok());
+}
+
+#[test]
+fn test_replayed_state_equals_state_built_directly() {
+    let auction = sample_timed_asc_auction();
+    // Ids are set up front since handling assigns an id to a bid without one
+    let bids = [
+        Bid { bid_id: Some(BidId::new_v4()), ..bid_1() },
+        Bid { bid_id: Some(BidId::new_v4()), ..bid_2() },
+    ];
+
+    let (direct, results) = empty_state(&auction).apply_bids(bids.clone());
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let commands = std::iter::once(Command::AddAuction { timestamp: sample_starts_at(), auction: auction.clone() })
+        .chain(bids.into_iter().map(|bid| Command::PlaceBid { timestamp: bid.at, bid }));
+    let (results, repository) = handle_all(commands, Repository::new());
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let replayed = repository.get_state(auction.auction_id).unwrap();
+    assert_eq!(replayed, &direct);
+    assert_ne!(replayed, &empty_state(&auction));
+}
+
+#[test]
+fn test_repository_index_follows_commands() {
+    let other_auction = Auction { auction_id: 2, seller: buyer_1(), ..sample_blind_auction() };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::AddAuction { timestamp: sample_starts_at(), auction: other_auction },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::CloseAuction { timestamp: sample_bid_time(), auction_id: 2, user: buyer_1() },
+    ];
+    let mut repository = Repository::new();
+    for (i, command) in commands.into_iter().enumerate() {
+        let (_, next) = handle(command, repository).unwrap();
+        repository = next;
+        // The index is the same as one built from the repository after every command
+        assert_eq!(repository.index(), &RepositoryIndex::build(&repository), "after command {}", i);
+    }
+
+    let index = repository.index();
+    assert_eq!(index.by_seller(sample_seller().user_id()), Some(&HashSet::from([sample_auction_id()])));
+    assert_eq!(index.by_seller(buyer_1().user_id()), Some(&HashSet::from([2])));
+    assert_eq!(index.by_phase("on_going"), Some(&HashSet::from([sample_auction_id()])));
+    assert_eq!(index.by_phase("disclosing_bids"), Some(&HashSet::from([2])));
+    assert_eq!(index.by_phase("awaiting_start"), None);
+    assert_eq!(index.unended().collect::<Vec<_>>(), vec![sample_auction_id()]);
+
+    // A rejected command leaves the index as it was
+    let before = repository.index().clone();
+    let late_bid = Bid { at: sample_starts_at(), ..bid_2() };
+    assert!(handle_mut(Command::PlaceBid { timestamp: late_bid.at, bid: late_bid }, &mut repository).is_err());
+    assert_eq!(repository.index(), &before);
+
+    // Sweeps move the auction in the index too
+    sweep_ended(&mut repository, sample_ends_at());
+    assert_eq!(repository.index(), &RepositoryIndex::build(&repository));
+    assert_eq!(repository.index().unended().count(), 0);
+
+    repository.remove(2);
+    assert_eq!(repository.index(), &RepositoryIndex::build(&repository));
+    assert_eq!(repository.index().by_seller(buyer_1().user_id()), None);
+    assert_eq!(repository.index().by_phase("disclosing_bids"), None);
+}
+
+#[test]
+fn test_sweep_reports_ended_auctions_once() {
+    let ongoing = Auction { auction_id: 2, expiry: sample_ends_at() + Duration::days(1), ..sample_blind_auction() };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::AddAuction { timestamp: sample_starts_at(), auction: ongoing },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() },
+    ];
+    let (_, mut repository) = handle_all(commands, Repository::new());
+
+    // Nothing has ended before the expiry
+    assert!(sweep_ended(&mut repository, sample_bid_time()).is_empty());
+
+    let events = sweep_ended(&mut repository, sample_ends_at());
+    assert_eq!(events, vec![Event::AuctionEnded {
+        timestamp: sample_ends_at(),
+        auction_id: sample_auction_id(),
+        winner: Some(buyer_2().user_id().clone()),
+        price: Some(sek(bid_amount_2())),
+    }]);
+    assert!(repository.get_state(sample_auction_id()).unwrap().has_ended());
+
+    // An auction that has been reported is not reported again
+    let events = sweep_ended(&mut repository, sample_ends_at() + Duration::days(2));
+    assert!(matches!(events.as_slice(), [Event::AuctionEnded { auction_id: 2, winner: None, price: None, .. }]));
+}
+
+#[test]
+fn test_sweep_keeps_auction_open_for_clock_skew_grace() {
+    let auction = sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+        clock_skew_grace: Duration::seconds(5),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    }));
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+    ];
+    let (_, mut repository) = handle_all(commands, Repository::new());
+
+    // A sweep at the expiry leaves the auction open for a late bid within the grace
+    assert!(sweep_ended(&mut repository, sample_ends_at() + Duration::seconds(1)).is_empty());
+    let late_bid = Bid { at: sample_ends_at() + Duration::seconds(2), ..bid_2() };
+    let result = handle_mut(Command::PlaceBid { timestamp: late_bid.at, bid: late_bid }, &mut repository);
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_extend_auction_moves_expiry() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+    ];
+    let (_, repository) = handle_all(commands, Repository::new());
+
+    let now = sample_bid_time();
+    let new_expiry = sample_ends_at() + Duration::hours(1);
+    let extend = Command::ExtendAuction { timestamp: now, auction_id: sample_auction_id(), new_expiry, user: sample_seller() };
+    let (event, repository) = handle(extend, repository).unwrap();
+    assert!(matches!(event, Event::AuctionExtended { .. }));
+
+    let state = repository.get_state(sample_auction_id()).unwrap();
+    assert_eq!(state.time_left(now), Some(new_expiry - now));
+    assert!(!state.inc(sample_ends_at()).has_ended());
+    assert!(state.inc(new_expiry).has_ended());
+}
+
+#[test]
+fn test_extend_auction_rejects_shortening() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+    ];
+    let (_, repository) = handle_all(commands, Repository::new());
+
+    let now = sample_bid_time();
+    let earlier = sample_ends_at() - Duration::minutes(5);
+    let shorten = Command::ExtendAuction { timestamp: now, auction_id: sample_auction_id(), new_expiry: earlier, user: sample_seller() };
+    match handle(shorten, repository.clone()) {
+        Err(HandleError::AuctionError(Errors::CannotShortenAuction(auction_id))) => assert_eq!(auction_id, sample_auction_id()),
+        other => panic!("Expected CannotShortenAuction error, got {:?}", other),
+    }
+
+    let as_buyer = Command::ExtendAuction { timestamp: now, auction_id: sample_auction_id(), new_expiry: sample_ends_at() + Duration::hours(1), user: buyer_1() };
+    match handle(as_buyer, repository) {
+        Err(HandleError::AuctionError(Errors::CannotExtendAuction(_))) => {},
+        other => panic!("Expected CannotExtendAuction error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_sweep_reports_started_auctions_once() {
+    let later = Auction { auction_id: 2, starts_at: sample_bid_time(), ..sample_blind_auction() };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at() - Duration::days(1), auction: sample_timed_asc_auction() },
+        Command::AddAuction { timestamp: sample_starts_at() - Duration::days(1), auction: later },
+    ];
+    let (_, mut repository) = handle_all(commands, Repository::new());
+    let event_bus = EventBus::default();
+    let mut subscriber = event_bus.subscribe();
+
+    let mut started = Vec::new();
+    for now in [sample_starts_at() - Duration::hours(1), sample_starts_at() + Duration::seconds(1), sample_starts_at() + Duration::days(1),
+                sample_bid_time() + Duration::seconds(1), sample_ends_at() + Duration::days(2)] {
+        for event in sweep_started(&mut repository, now) {
+            event_bus.publish(event);
+        }
+    }
+    while let Ok(event) = subscriber.try_recv() {
+        started.push(event);
+    }
+
+    assert_eq!(started, vec![
+        Event::AuctionStarted { timestamp: sample_starts_at() + Duration::seconds(1), auction_id: sample_auction_id() },
+        Event::AuctionStarted { timestamp: sample_bid_time() + Duration::seconds(1), auction_id: 2 },
+    ]);
+}
+
+#[test]
+fn test_in_place_transitions_match_the_immutable_ones() {
+    let bids = [
+        bid_1(),
+        bid_2(),
+        // Accepted by some types and rejected by others
+        Bid { bid_amount: 1, ..bid_1() },
+        Bid { bidder: buyer_3(), bid_amount: 5, at: sample_starts_at() + Duration::seconds(3), ..bid_1() },
+    ];
+    for auction in [sample_timed_asc_auction(), sample_vickrey_auction(), sample_blind_auction(), sample_reverse_auction()] {
+        let (immutable, immutable_results) = bids.iter().fold((empty_state(&auction), Vec::new()), |(state, mut results), bid| {
+            let (state, result) = state.add_bid(bid.clone());
+            results.push(result);
+            (state, results)
+        });
+
+        let mut mutable = empty_state(&auction);
+        let mutable_results: Vec<_> = bids.iter().map(|bid| mutable.add_bid_mut(bid.clone())).collect();
+
+        assert_eq!(mutable_results, immutable_results);
+        assert_eq!(mutable, immutable);
+        mutable.inc_mut(sample_ends_at());
+        assert_eq!(mutable, immutable.inc(sample_ends_at()));
+    }
+
+    // Handling in place yields the repository that handling a copy does
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() },
+        Command::PlaceBid { timestamp: bid_1().at, bid: Bid { bid_id: Some(BidId::from_u128(1)), ..bid_1() } },
+        Command::PlaceBid { timestamp: bid_2().at, bid: Bid { bid_id: Some(BidId::from_u128(2)), ..bid_2() } },
+    ];
+    let copied = commands.iter().fold(Repository::new(), |repository, command| handle(command.clone(), repository).unwrap().1);
+    let mut in_place = Repository::new();
+    for command in commands {
+        handle_mut(command, &mut in_place).unwrap();
+    }
+    assert_eq!(in_place, copied);
+}
+
+#[test]
+fn test_version_counts_accepted_commands() {
+    // Sealed bids are not visible, the version still moves with each of them
+    let mut repository = Repository::new();
+    handle_mut(Command::AddAuction { timestamp: sample_starts_at(), auction: sample_blind_auction() }, &mut repository).unwrap();
+    assert_eq!(repository.version(sample_auction_id()), 0);
+
+    handle_mut(Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() }, &mut repository).unwrap();
+    handle_mut(Command::PlaceBid { timestamp: bid_2().at, bid: bid_2() }, &mut repository).unwrap();
+    assert_eq!(repository.get_state(sample_auction_id()).unwrap().get_bids(), Vec::<Bid>::new());
+    assert_eq!(repository.version(sample_auction_id()), 2);
+
+    // A rejected command leaves the version as is
+    let again = Bid { at: bid_2().at + Duration::seconds(1), ..bid_1() };
+    assert!(handle_mut(Command::PlaceBid { timestamp: again.at, bid: again }, &mut repository).is_err());
+    assert_eq!(repository.version(sample_auction_id()), 2);
+
+    // Moving the state forward in time keeps the version
+    sweep_ended(&mut repository, sample_ends_at() + Duration::seconds(1));
+    assert_eq!(repository.version(sample_auction_id()), 2);
+}
+
+#[test]
+fn test_sweep_reports_start_of_auctions_moved_past_it() {
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at() - Duration::days(1), auction: sample_timed_asc_auction() },
+        Command::AddAuction { timestamp: sample_starts_at() - Duration::days(1), auction: Auction { auction_id: 2, ..sample_blind_auction() } },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() },
+    ];
+    let (_, mut repository) = handle_all(commands, Repository::new());
+
+    // The first auction started with a bid and the second has ended before either sweep ran
+    let now = sample_ends_at() + Duration::seconds(1);
+    assert_eq!(sweep_ended(&mut repository, now).len(), 2);
+    assert_eq!(sweep_started(&mut repository, now), vec![
+        Event::AuctionStarted { timestamp: now, auction_id: sample_auction_id() },
+        Event::AuctionStarted { timestamp: now, auction_id: 2 },
+    ]);
+    assert!(sweep_started(&mut repository, now + Duration::days(1)).is_empty());
+}
@@ -0,0 +1,145 @@
+use auction_site::domain::{compute_commission, compute_commission_rounded, FeeSchedule, FeeTier};
+use auction_site::money::{Amount, Currency, MoneyError, RoundingMode};
+use std::collections::{HashMap, HashSet};
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+fn total(amounts: &[Amount]) -> Amount {
+    amounts.iter().fold(Amount::new(amounts[0].currency(), 0), |sum, amount| (sum + *amount).unwrap())
+}
+
+#[test]
+fn test_split_amount() {
+    let parts = sek(10).split(3);
+    assert_eq!(parts, vec![sek(4), sek(3), sek(3)]);
+    assert_eq!(total(&parts), sek(10));
+
+    assert_eq!(sek(9).split(3), vec![sek(3), sek(3), sek(3)]);
+    assert_eq!(sek(2).split(4), vec![sek(1), sek(1), sek(0), sek(0)]);
+    assert_eq!(vac(7).split(1), vec![vac(7)]);
+    assert!(sek(10).split(0).is_empty());
+}
+
+#[test]
+fn test_split_amount_sums_to_original() {
+    for value in [-7, 0, 1, 99, 10_001] {
+        for parts in 1..=7 {
+            let split = Amount::new(Currency::DKK, value).split(parts);
+            assert_eq!(split.len(), parts as usize);
+            assert_eq!(total(&split), Amount::new(Currency::DKK, value));
+
+            let values: Vec<i64> = split.iter().map(Amount::value).collect();
+            assert!(values.iter().max().unwrap() - values.iter().min().unwrap() <= 1, "{:?}", values);
+        }
+    }
+}
+
+#[test]
+fn test_commission_in_basis_points() {
+    assert_eq!(compute_commission(sek(10000), 250), Ok(sek(250)));
+    assert_eq!(compute_commission(sek(10000), 0), Ok(sek(0)));
+    // Rounded half up to whole minor units
+    assert_eq!(compute_commission(sek(20), 250), Ok(sek(1)));
+    assert_eq!(compute_commission(sek(19), 250), Ok(sek(0)));
+
+    let flat = FeeSchedule::flat(250).unwrap();
+    assert_eq!(flat.commission(sek(10000)), Ok(sek(250)));
+    assert_eq!(flat.net_to_seller(sek(10000)), Ok(sek(9750)));
+}
+
+#[test]
+fn test_commission_rounding_modes() {
+    // 250 bps of SEK100 is 2.5 minor units, of SEK140 it is 3.5
+    assert_eq!(compute_commission_rounded(sek(100), 250, RoundingMode::HalfUp), Ok(sek(3)));
+    assert_eq!(compute_commission_rounded(sek(100), 250, RoundingMode::HalfEven), Ok(sek(2)));
+    assert_eq!(compute_commission_rounded(sek(140), 250, RoundingMode::HalfEven), Ok(sek(4)));
+    assert_eq!(compute_commission_rounded(sek(100), 250, RoundingMode::Floor), Ok(sek(2)));
+    assert_eq!(compute_commission_rounded(sek(139), 250, RoundingMode::Floor), Ok(sek(3)));
+
+    let schedule = FeeSchedule::flat(250).unwrap();
+    assert_eq!(schedule.commission(sek(100)), Ok(sek(3)));
+    let bankers = FeeSchedule::flat(250).unwrap().with_rounding(RoundingMode::HalfEven);
+    assert_eq!(bankers.commission(sek(100)), Ok(sek(2)));
+    assert_eq!(bankers.net_to_seller(sek(100)), Ok(sek(98)));
+}
+
+#[test]
+fn test_no_commission_in_virtual_currency() {
+    assert!(Currency::VAC.is_virtual());
+    assert!(!Currency::SEK.is_virtual());
+    assert!(!Currency::DKK.is_virtual());
+
+    let vac = Amount::new(Currency::VAC, 10000);
+    assert_eq!(compute_commission(vac, 250), Err(MoneyError::VirtualCurrency(Currency::VAC)));
+    assert_eq!(FeeSchedule::flat(250).unwrap().commission(vac), Err(MoneyError::VirtualCurrency(Currency::VAC)));
+    assert_eq!(FeeSchedule::flat(250).unwrap().net_to_seller(vac), Err(MoneyError::VirtualCurrency(Currency::VAC)));
+    assert_eq!(MoneyError::VirtualCurrency(Currency::VAC).to_string(), "VAC is a virtual currency, fees only apply to real currencies");
+}
+
+#[test]
+fn test_tiered_commission() {
+    let schedule = FeeSchedule::tiered(vec![
+        FeeTier { from: 10000, rate_bps: 250 },
+        FeeTier { from: 0, rate_bps: 500 },
+    ]).unwrap();
+
+    assert_eq!(schedule.commission(sek(8000)), Ok(sek(400)));
+    // Up to the boundary only the first rate applies, beyond it the lower rate applies to the excess
+    assert_eq!(schedule.commission(sek(10000)), Ok(sek(500)));
+    assert_eq!(schedule.commission(sek(10040)), Ok(sek(501)));
+    assert_eq!(schedule.commission(sek(20000)), Ok(sek(750)));
+    assert_eq!(schedule.net_to_seller(sek(20000)), Ok(sek(19250)));
+
+    // Prices below the first tier are free of commission
+    let above_threshold = FeeSchedule::tiered(vec![FeeTier { from: 1000, rate_bps: 1000 }]).unwrap();
+    assert_eq!(above_threshold.commission(sek(500)), Ok(sek(0)));
+    assert_eq!(above_threshold.commission(sek(1500)), Ok(sek(50)));
+}
+
+#[test]
+fn test_commission_rate_is_at_most_the_whole_price() {
+    assert_eq!(compute_commission(sek(10000), 10_000), Ok(sek(10000)));
+    assert_eq!(compute_commission(sek(i64::MAX), 10_001), Err(MoneyError::RateAboveWholePrice(10_001)));
+    assert_eq!(FeeSchedule::flat(10_001), Err(MoneyError::RateAboveWholePrice(10_001)));
+    assert_eq!(
+        FeeSchedule::tiered(vec![FeeTier { from: 0, rate_bps: 250 }, FeeTier { from: 1000, rate_bps: u32::MAX }]),
+        Err(MoneyError::RateAboveWholePrice(u32::MAX))
+    );
+    // The whole of the largest price is still in range
+    assert_eq!(FeeSchedule::flat(10_000).unwrap().commission(sek(i64::MAX)), Ok(sek(i64::MAX)));
+}
+
+#[test]
+fn test_unknown_currency_suggests_closest() {
+    assert_eq!(Currency::try_from("SEK"), Ok(Currency::SEK));
+    assert_eq!("XEK".parse::<Currency>(), Err("Unknown currency: XEK, did you mean SEK?".to_string()));
+    assert_eq!(Currency::try_from("dkk"), Err("Unknown currency: dkk, did you mean DKK?".to_string()));
+    assert_eq!(Currency::try_from("USD"), Err("Unknown currency: USD".to_string()));
+    assert_eq!(Currency::try_from("Swedish crowns"), Err("Unknown currency: Swedish crowns".to_string()));
+}
+
+#[test]
+fn test_totals_keyed_by_currency() {
+    let amounts = [sek(100), Amount::new(Currency::DKK, 50), sek(25), Amount::new(Currency::VAC, 7), Amount::new(Currency::DKK, 5)];
+    let mut totals: HashMap<Currency, Amount> = HashMap::new();
+    for amount in amounts {
+        let total = totals.entry(amount.currency()).or_insert_with(|| Amount::new(amount.currency(), 0));
+        *total = (*total + amount).unwrap();
+    }
+
+    assert_eq!(totals.len(), 3);
+    assert_eq!(totals[&Currency::SEK], sek(125));
+    assert_eq!(totals[&Currency::DKK], Amount::new(Currency::DKK, 55));
+    assert_eq!(totals[&Currency::VAC], Amount::new(Currency::VAC, 7));
+
+    // Equal amounts hash alike, amounts of another currency are distinct
+    let distinct: HashSet<Amount> = [sek(10), sek(10), Amount::new(Currency::DKK, 10)].into_iter().collect();
+    assert_eq!(distinct.len(), 2);
+}
+
+#[test]
+fn test_parsing_has_no_ceiling() {
+    // Amounts read back from logs parse whatever their size, ceilings are checked where clients send amounts
+    let amount: Amount = "SEK99999999999999".parse().unwrap();
+    assert_eq!(amount, Amount::new(Currency::SEK, 99999999999999));
+}
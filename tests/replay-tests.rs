@@ -0,0 +1,73 @@
+use auction_site::domain::{states::State, AuctionState, Bid, empty_state};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use time::Duration;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+// Counts allocations so that replay strategies can be compared
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn many_bids(count: i64) -> Vec<Bid> {
+    let buyers = [buyer_1(), buyer_2(), buyer_3()];
+    (0..count).map(|i| Bid {
+        bid_id: None,
+        for_auction: sample_auction_id(),
+        bidder: buyers[(i % 3) as usize].clone(),
+        at: sample_starts_at() + Duration::seconds(i + 1),
+        // Every tenth bid is too low and gets rejected
+        bid_amount: if i % 10 == 9 { 1 } else { i + 1 },
+        placed_by: None,
+    }).collect()
+}
+
+#[test]
+fn test_apply_bids_matches_naive_replay_with_fewer_allocations() {
+    let state = empty_state(&sample_timed_asc_auction());
+    let bids = many_bids(10_000);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut naive_state = state.clone();
+    let mut naive_results = Vec::new();
+    for bid in bids.clone() {
+        let (next, result) = naive_state.add_bid(bid);
+        naive_state = next;
+        naive_results.push(result);
+    }
+    let naive_allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let (batch_state, batch_results) = state.apply_bids(bids.clone());
+    let batch_allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(batch_results, naive_results);
+    assert_eq!(batch_results.iter().filter(|r| r.is_err()).count(), 1_000);
+    match (&batch_state, &naive_state) {
+        (AuctionState::TimedAscending(batch), AuctionState::TimedAscending(naive)) => {
+            assert_eq!(batch, naive);
+        },
+        _ => panic!("Expected TimedAscending states"),
+    }
+    assert_eq!(batch_state.get_bids().len(), 9_000);
+
+    assert!(
+        batch_allocations * 100 < naive_allocations,
+        "batch: {} allocations, naive: {} allocations", batch_allocations, naive_allocations
+    );
+}
@@ -0,0 +1,250 @@
+use auction_site::domain::{
+    auctions, auctions_ending_between, empty_state, handle_all, repository_stats, summarize, timed_ascending, Auction,
+    AuctionPhase, AuctionType, Bid, Command, Repository, State,
+};
+use auction_site::money::{Amount, Currency};
+use time::{Duration, OffsetDateTime};
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+fn auction_ending_at(auction_id: i64, expiry: OffsetDateTime, typ: AuctionType) -> Auction {
+    Auction {
+        auction_id,
+        expiry,
+        ..sample_auction_of_type(typ)
+    }
+}
+
+fn english_with_time_frame(time_frame: Duration) -> AuctionType {
+    AuctionType::TimedAscending(timed_ascending::Options {
+        reserve_price: sek(0),
+        min_raise: sek(0),
+        time_frame,
+        allow_self_outbid: true,
+        max_extensions: None,
+        hide_bidders: false,
+        strict_increase: false,
+        clock_skew_grace: Duration::ZERO,
+        reserve_secret: false,
+        max_history: None,
+        final_round: None,
+        max_bid: None,
+    })
+}
+
+#[test]
+fn test_auctions_ending_between() {
+    let now = sample_bid_time();
+    let vickrey = sample_vickrey_auction().typ;
+    let auctions = vec![
+        auction_ending_at(1, now + Duration::minutes(10), vickrey.clone()),
+        auction_ending_at(2, now + Duration::minutes(20), english_with_time_frame(Duration::minutes(30))),
+        auction_ending_at(3, now + Duration::hours(2), vickrey.clone()),
+        auction_ending_at(4, now + Duration::minutes(25), english_with_time_frame(Duration::minutes(1))),
+        auction_ending_at(5, now - Duration::minutes(1), vickrey),
+    ];
+    let mut commands: Vec<Command> = auctions
+        .into_iter()
+        .map(|auction| Command::AddAuction { timestamp: sample_starts_at(), auction })
+        .collect();
+
+    // A bid on auction 2 extends it to 45 minutes from now, out of the window
+    let extending_bid = Bid {
+        for_auction: 2,
+        at: now + Duration::minutes(15),
+        ..bid_1()
+    };
+    commands.push(Command::PlaceBid { timestamp: extending_bid.at, bid: extending_bid });
+    // A bid on auction 4 is too early to extend it
+    let bid = Bid {
+        for_auction: 4,
+        at: now,
+        ..bid_1()
+    };
+    commands.push(Command::PlaceBid { timestamp: bid.at, bid });
+
+    let (results, repository) = handle_all(commands, Repository::new());
+    assert!(results.iter().all(|result| result.is_ok()), "{:?}", results);
+
+    let ending: Vec<i64> = auctions_ending_between(&repository, now, now + Duration::minutes(30))
+        .iter()
+        .map(|auction| auction.auction_id)
+        .collect();
+    assert_eq!(ending, vec![1, 4]);
+
+    // A wider window includes the extended auction
+    let ending: Vec<i64> = auctions_ending_between(&repository, now, now + Duration::hours(1))
+        .iter()
+        .map(|auction| auction.auction_id)
+        .collect();
+    assert_eq!(ending, vec![1, 4, 2]);
+}
+
+#[test]
+fn test_auctions_ending_between_include_final_round() {
+    let now = sample_bid_time();
+    let typ = match english_with_time_frame(Duration::ZERO) {
+        AuctionType::TimedAscending(options) => AuctionType::TimedAscending(timed_ascending::Options {
+            final_round: Some(timed_ascending::FinalRound { duration: Duration::minutes(10), bidders: 2 }),
+            ..options
+        }),
+        _ => unreachable!(),
+    };
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: auction_ending_at(1, now + Duration::minutes(25), typ.clone()) },
+        Command::AddAuction { timestamp: sample_starts_at(), auction: auction_ending_at(2, now + Duration::minutes(25), typ) },
+        Command::PlaceBid { timestamp: now, bid: Bid { for_auction: 1, at: now, ..bid_1() } },
+    ];
+    let (results, repository) = handle_all(commands, Repository::new());
+    assert!(results.iter().all(|result| result.is_ok()), "{:?}", results);
+
+    // The bid leads auction 1 into a final round that ends after the window
+    let ending: Vec<i64> = auctions_ending_between(&repository, now, now + Duration::minutes(30))
+        .iter()
+        .map(|auction| auction.auction_id)
+        .collect();
+    assert_eq!(ending, vec![2]);
+}
+
+#[test]
+fn test_repository_stats() {
+    let now = sample_bid_time();
+    let auction_for = |auction_id: i64, auction: Auction| Auction { auction_id, ..auction };
+    let bid_for = |auction_id: i64, bid: Bid| Bid { for_auction: auction_id, ..bid };
+
+    let commands = vec![
+        Command::AddAuction { timestamp: sample_starts_at(), auction: auction_for(1, sample_timed_asc_auction()) },
+        Command::AddAuction { timestamp: sample_starts_at(), auction: auction_for(2, sample_reverse_auction()) },
+        Command::AddAuction {
+            timestamp: sample_starts_at(),
+            auction: Auction {
+                auction_currency: Currency::DKK,
+                expiry: sample_starts_at() + Duration::minutes(1),
+                ..auction_for(3, sample_vickrey_auction())
+            },
+        },
+        Command::AddAuction {
+            timestamp: sample_starts_at(),
+            auction: Auction {
+                starts_at: now + Duration::days(1),
+                expiry: now + Duration::days(2),
+                ..auction_for(4, sample_blind_auction())
+            },
+        },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_for(1, bid_1()) },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_for(1, bid_2()) },
+        Command::PlaceBid { timestamp: bid_2().at, bid: bid_for(2, Bid { bid_amount: 8, ..bid_2() }) },
+        Command::PlaceBid { timestamp: bid_1().at, bid: bid_for(3, bid_1()) },
+    ];
+    let (results, repository) = handle_all(commands, Repository::new());
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let stats = repository_stats(&repository, now);
+
+    assert_eq!(stats.total_auctions, 4);
+    for kind in ["English", "Reverse", "Vickrey", "Blind"] {
+        assert_eq!(stats.by_type[kind], 1, "{}", kind);
+    }
+    assert_eq!(stats.by_phase[&AuctionPhase::Ongoing], 2);
+    assert_eq!(stats.by_phase[&AuctionPhase::Ended], 1);
+    assert_eq!(stats.by_phase[&AuctionPhase::AwaitingStart], 1);
+    assert_eq!(stats.total_bids, 4);
+
+    // English leads with 12 and reverse with 8, both in SEK, the disclosed Vickrey bid is in DKK
+    assert_eq!(stats.high_bid_totals[&Currency::SEK], sek(20));
+    assert_eq!(stats.high_bid_totals[&Currency::DKK], Amount::new(Currency::DKK, bid_amount_1()));
+    assert_eq!(stats.high_bid_totals.len(), 2);
+}
+
+#[test]
+fn test_summarize_sold_auctions() {
+    let english = sample_timed_asc_auction();
+    let (state, _) = empty_state(&english).apply_bids([bid_1(), bid_2()]);
+    assert_eq!(
+        summarize(&english, &state.inc(sample_ends_at())),
+        "#1 \"auction\" — SOLD to Buyer_2 for SEK12"
+    );
+
+    // The Vickrey winner pays the second highest bid
+    let vickrey = sample_vickrey_auction();
+    let (state, _) = empty_state(&vickrey).apply_bids([bid_1(), bid_2()]);
+    assert_eq!(
+        summarize(&vickrey, &state.inc(sample_ends_at())),
+        "#1 \"auction\" — SOLD to Buyer_2 for SEK10"
+    );
+}
+
+#[test]
+fn test_summarize_reserve_not_met() {
+    let rug = Auction {
+        auction_id: 2,
+        title: "rug".to_string(),
+        ..sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+            reserve_price: sek(20),
+            ..timed_ascending::Options::default_options(Currency::SEK)
+        }))
+    };
+    let bid = Bid { for_auction: 2, ..bid_2() };
+    let (state, _) = empty_state(&rug).add_bid(bid);
+    assert_eq!(
+        summarize(&rug, &state.inc(sample_ends_at())),
+        "#2 \"rug\" — UNSOLD (reserve not met, high SEK12)"
+    );
+}
+
+#[test]
+fn test_summarize_without_bids() {
+    for auction in [sample_timed_asc_auction(), sample_blind_auction(), sample_reverse_auction()] {
+        let state = empty_state(&auction);
+        assert_eq!(summarize(&auction, &state), "#1 \"auction\" — ONGOING");
+        assert_eq!(
+            summarize(&auction, &state.inc(sample_ends_at())),
+            "#1 \"auction\" — UNSOLD (no bids)"
+        );
+    }
+}
+
+#[test]
+fn test_repository_accessors() {
+    let mut repository = Repository::new();
+    assert!(repository.is_empty());
+
+    let english = sample_timed_asc_auction();
+    let vickrey = Auction { auction_id: 2, ..sample_vickrey_auction() };
+    repository.insert(english.clone(), empty_state(&english));
+    repository.insert(vickrey.clone(), empty_state(&vickrey));
+
+    assert_eq!(repository.len(), 2);
+    assert!(repository.contains(2));
+    assert!(!repository.contains(3));
+    assert_eq!(repository.get_auction(2), Some(&vickrey));
+    assert_eq!(repository.get_state(sample_auction_id()), Some(&empty_state(&english)));
+    assert_eq!(repository.get(3), None);
+
+    let ids: Vec<i64> = repository.iter_auctions().map(|auction| auction.auction_id).collect();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(auctions(&repository).len(), 2);
+
+    // Inserting under an existing id replaces the auction
+    let renamed = Auction { title: "renamed".to_string(), ..vickrey.clone() };
+    repository.insert(renamed.clone(), empty_state(&renamed));
+    assert_eq!(repository.len(), 2);
+    assert_eq!(repository.get_auction(2).map(|auction| auction.title.as_str()), Some("renamed"));
+
+    assert_eq!(repository.remove(2).map(|(auction, _)| auction), Some(renamed));
+    assert!(!repository.contains(2));
+    assert_eq!(repository, [(english.clone(), empty_state(&english))].into_iter().collect());
+}
+
+#[test]
+fn test_auctions_are_ordered_by_id() {
+    let mut repository = Repository::new();
+    for auction_id in [7, 3, 11, 1, 5] {
+        let auction = Auction { auction_id, ..sample_timed_asc_auction() };
+        let state = empty_state(&auction);
+        repository.insert(auction, state);
+    }
+
+    let ids: Vec<i64> = auctions(&repository).iter().map(|auction| auction.auction_id).collect();
+    assert_eq!(ids, vec![1, 3, 5, 7, 11]);
+}
@@ -0,0 +1,143 @@
+use auction_site::domain::{
+    AuctionType, Auction, Bid,
+    reverse::{self, ReverseState},
+    states::{AuctionOutcome, State},
+    AuctionState, empty_state,
+    core::Errors
+};
+use auction_site::money::Currency;
+use time::Duration;
+use std::str::FromStr;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+fn reverse_state(auction: &Auction) -> ReverseState {
+    match empty_state(auction) {
+        AuctionState::Reverse(state) => state,
+        _ => panic!("Expected Reverse state"),
+    }
+}
+
+#[test]
+fn test_reverse_auction_states() {
+    let empty_reverse_auction_state = reverse_state(&sample_reverse_auction());
+
+    // Start the auction
+    let started_state = empty_reverse_auction_state.inc(sample_starts_at() + Duration::seconds(1));
+
+    // Can add bid to empty state
+    let (state_with_1_bid, result_1) = started_state.add_bid(bid_2()); // 12
+    assert!(result_1.is_ok(), "{:?}", result_1);
+
+    // Can add a lower second bid
+    let lower_bid = Bid {
+        at: bid_2().at + Duration::seconds(1),
+        ..bid_1() // 10
+    };
+    let (state_with_2_bids, result_2) = state_with_1_bid.add_bid(lower_bid.clone());
+    assert!(result_2.is_ok(), "{:?}", result_2);
+
+    // Can't place a bid that is not lower than the lowest bid
+    let (_, maybe_fail) = state_with_2_bids.add_bid(bid_less_than_2()); // 11
+    match maybe_fail {
+        Err(Errors::MustPlaceBidUnderLowestBid(amount)) => {
+            assert_eq!(amount, bid_amount_1());
+        },
+        _ => panic!("Expected MustPlaceBidUnderLowestBid error"),
+    }
+
+    // Can end empty auction
+    let empty_ended_state = empty_reverse_auction_state.inc(sample_ends_at() + Duration::seconds(1));
+    assert!(empty_ended_state.get_bids().is_empty());
+    assert_eq!(empty_ended_state.outcome(), AuctionOutcome::NoBids);
+
+    // Can end auction with bids
+    let state_ended_after_two_bids = state_with_2_bids.inc(sample_ends_at());
+    match state_ended_after_two_bids {
+        ReverseState::HasEnded { ref bids, .. } => {
+            assert_eq!(bids.len(), 2);
+            assert_eq!(bids[0], lower_bid);
+            assert_eq!(bids[1], bid_2());
+        },
+        _ => panic!("Expected HasEnded state"),
+    }
+
+    // Can't bid after auction has ended
+    let (_, err_after_ended) = state_ended_after_two_bids.add_bid(bid_less_than_2());
+    match err_after_ended {
+        Err(Errors::AuctionHasEnded(id)) => {
+            assert_eq!(id, sample_auction_id());
+        },
+        _ => panic!("Expected AuctionHasEnded error"),
+    }
+
+    // The lowest bidder wins
+    let (amount, winner) = state_ended_after_two_bids.try_get_amount_and_winner().unwrap();
+    assert_eq!(amount, bid_amount_1());
+    assert_eq!(winner, buyer_1().user_id().clone());
+
+    // Test the increment spec
+    test_increment_spec(&empty_reverse_auction_state);
+}
+
+#[test]
+fn test_reverse_auction_with_max_price() {
+    let auction_with_max_price = sample_auction_of_type(
+        AuctionType::Reverse(reverse::Options { max_price: sek(11) })
+    );
+    let started_state = reverse_state(&auction_with_max_price)
+        .inc(sample_starts_at() + Duration::seconds(1));
+
+    // Bids over the max price are rejected
+    let (_, result) = started_state.add_bid(bid_2()); // 12
+    assert_eq!(result, Err(Errors::BidAboveMaxPrice(sek(11))));
+    assert_eq!(Errors::BidAboveMaxPrice(sek(11)).code(), "bid_above_max_price");
+
+    // Bids at or below are accepted
+    let (_, result) = started_state.add_bid(bid_less_than_2()); // 11
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_reverse_auction_type_serialization() {
+    let sample_type_str = "Reverse|SEK100";
+    let sample_type = AuctionType::Reverse(reverse::Options { max_price: sek(100) });
+
+    // Can deserialize sample type
+    assert_eq!(AuctionType::from_str(sample_type_str).unwrap(), sample_type);
+
+    // Can serialize sample type
+    assert_eq!(sample_type.to_string(), sample_type_str);
+
+    // Must have a max price
+    assert!(reverse::Options::from_str("Reverse").is_err());
+    assert!(reverse::Options::from_str("Reverse|abc").is_err());
+}
+
+#[test]
+fn test_reverse_auction_max_price_currency_must_match() {
+    let mut auction = sample_reverse_auction();
+    auction.typ = AuctionType::Reverse(reverse::Options { max_price: vac(100) });
+    assert_eq!(
+        auction_site::domain::validate_auction(&auction),
+        Err(Errors::AuctionCurrencyMismatch((Currency::SEK, Currency::VAC)))
+    );
+}
+
+#[test]
+fn test_reverse_auction_expiry_boundary() {
+    test_expiry_boundary(&reverse_state(&sample_reverse_auction()));
+}
+
+#[test]
+fn test_reverse_auction_start_boundary() {
+    test_start_boundary(&reverse_state(&sample_reverse_auction()));
+}
+
+#[test]
+fn test_reverse_auction_phase_names() {
+    let state = reverse_state(&sample_reverse_auction());
+    assert_eq!(state.phase_name(), "awaiting_start");
+    assert_eq!(state.inc(sample_starts_at() + Duration::seconds(1)).phase_name(), "on_going");
+    assert_eq!(state.inc(sample_ends_at() + Duration::seconds(1)).phase_name(), "has_ended");
+}
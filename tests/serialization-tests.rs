@@ -1,16 +1,16 @@
 use auction_site::domain::{
-    AuctionType, User, Command, Event,
+    AuctionType, Bid, BidId, User, Command, Event, Repository, handle_all,
     timed_ascending::Options as TAOptions,
 };
-use auction_site::money::Amount;
-use auction_site::persistence::json_file::{read_commands, write_commands};
+use auction_site::money::{Amount, Currency};
+use auction_site::persistence::json_file::{read_commands, read_commands_from, read_commands_paged, read_events, write_commands, write_event};
 use serde_json::{from_str, to_string};
 use time::format_description::well_known::Rfc3339;
 use time::Duration;
 use std::str::FromStr;
 use std::fs;
 use std::path::Path;
-#[path="utils/mod.rs"] mod utils;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
 use utils::*;
 // Sample data for tests
 #[test]
@@ -27,9 +27,10 @@ fn test_auction_type_serialization() {
     // TimedAscending auction type
     let timed_ascending = AuctionType::TimedAscending(
         TAOptions {
-            reserve_price: 0,
-            min_raise: 0,
+            reserve_price: vac(0),
+            min_raise: vac(0),
             time_frame: Duration::seconds(0),
+            ..TAOptions::default_options(Currency::VAC)
         }
     );
 
@@ -42,8 +43,8 @@ fn test_auction_type_serialization() {
     // Types should match
     match deserialized {
         AuctionType::TimedAscending(opts) => {
-            assert_eq!(opts.reserve_price, 0);
-            assert_eq!(opts.min_raise, 0);
+            assert_eq!(opts.reserve_price, vac(0));
+            assert_eq!(opts.min_raise, vac(0));
             assert_eq!(opts.time_frame, Duration::seconds(0));
         },
         _ => panic!("Expected TimedAscending type"),
@@ -55,8 +56,8 @@ fn test_auction_type_serialization() {
 
     match parsed {
         AuctionType::TimedAscending(opts) => {
-            assert_eq!(opts.reserve_price, 0);
-            assert_eq!(opts.min_raise, 0);
+            assert_eq!(opts.reserve_price, vac(0));
+            assert_eq!(opts.min_raise, vac(0));
             assert_eq!(opts.time_frame, Duration::seconds(0));
         },
         _ => panic!("Expected TimedAscending type"),
@@ -193,6 +194,20 @@ fn test_command_success_serialization() {
     }
 }
 
+#[test]
+fn test_event_matches_command_format() {
+    let bid_accepted = to_string(&Event::BidAccepted { timestamp: sample_bid_time(), bid: bid_1() }).unwrap();
+    let place_bid = to_string(&Command::PlaceBid { timestamp: sample_bid_time(), bid: bid_1() }).unwrap();
+    let event = serde_json::from_str::<serde_json::Value>(&bid_accepted).unwrap();
+    let command = serde_json::from_str::<serde_json::Value>(&place_bid).unwrap();
+
+    assert_eq!(event["$type"], "BidAccepted");
+    assert_eq!(event["at"], sample_bid_time().format(&Rfc3339).unwrap());
+    // Apart from the discriminator, the event carries the same fields as the command it succeeded
+    assert_eq!(event["at"], command["at"]);
+    assert_eq!(event["bid"], command["bid"]);
+}
+
 #[test]
 fn test_write_and_read_commands() {
     let test_file = "./test_commands.jsonl";
@@ -229,6 +244,73 @@ fn test_write_and_read_commands() {
     }
 }
 
+#[test]
+fn test_write_and_read_events() {
+    let test_file = "./test_events.jsonl";
+    let _ = fs::remove_file(test_file);
+
+    let events = vec![
+        Event::AuctionAdded { timestamp: sample_starts_at(), auction: sample_vickrey_auction() },
+        Event::BidAccepted { timestamp: bid_1().at, bid: bid_1() },
+        Event::BidAccepted { timestamp: bid_2().at, bid: bid_2() },
+    ];
+    for event in &events {
+        write_event(test_file, event).unwrap();
+    }
+
+    // Each event is appended on a line of its own
+    assert_eq!(fs::read_to_string(test_file).unwrap().lines().count(), 3);
+    assert_eq!(read_events(test_file).unwrap(), events);
+
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_read_commands_from_reader() {
+    let add_auction = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction: sample_vickrey_auction(),
+    };
+    let place_bid = Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid: bid_1(),
+    };
+    let input = format!(
+        "{}\n{}\n",
+        to_string(&vec![add_auction.clone()]).unwrap(),
+        to_string(&vec![place_bid.clone()]).unwrap()
+    );
+
+    let commands = read_commands_from(std::io::Cursor::new(input)).unwrap();
+    assert_eq!(commands, vec![add_auction, place_bid]);
+
+    assert!(read_commands_from(std::io::Cursor::new("not json\n")).is_err());
+}
+
+#[test]
+fn test_replay_legacy_auction_in_other_currency() {
+    // Older logs wrote the amounts of an English auction bare, they are in the currency of the auction
+    let input = concat!(
+        r#"[{"$type":"AddAuction","at":"2020-05-17T08:15:16.464Z","auction":{"id":1,"startsAt":"2018-12-01T10:00:00.000Z","title":"Some auction","expiry":"2020-05-18T10:00:00.000Z","user":"BuyerOrSeller|a1|Test","type":"English|100|10|0","currency":"SEK"}}]"#, "\n",
+        r#"[{"$type":"PlaceBid","at":"2020-05-17T08:15:22.948Z","bid":{"id":"32e692cc3fdb451da9647d6eeca5b2e3","auction":1,"user":"BuyerOrSeller|a2|Buyer","amount":11,"at":"2020-05-17T08:15:22.940Z"}}]"#, "\n",
+    );
+    let commands = read_commands_from(std::io::Cursor::new(input)).unwrap();
+
+    let (results, repository) = handle_all(commands.clone(), Repository::new());
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let auction = repository.get_auction(sample_auction_id()).unwrap();
+    assert_eq!(auction.typ, AuctionType::TimedAscending(TAOptions {
+        reserve_price: sek(100),
+        min_raise: sek(10),
+        ..TAOptions::default_options(Currency::SEK)
+    }));
+
+    // The auction is written back in the same form
+    let json_value = serde_json::to_value(&commands[0]).unwrap();
+    assert_eq!(json_value["auction"]["type"], "English|100|10|0");
+    assert_eq!(Command::from_str(&commands[0].to_string()).unwrap(), commands[0]);
+}
+
 #[test]
 fn test_user_serialization() {
     // BuyerOrSeller
@@ -271,3 +353,183 @@ fn test_user_serialization() {
         _ => panic!("Expected Support"),
     }
 }
+
+#[test]
+fn test_add_auction_command_line_form() {
+    let add_auction = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction: sample_vickrey_auction(),
+    };
+
+    let line = add_auction.to_string();
+    assert_eq!(
+        line,
+        "AddAuction|2016-01-01T08:28:00Z|1|2016-01-01T08:28:00Z|2016-02-01T08:28:00Z|auction|BuyerOrSeller\\|Sample_Seller\\|Seller|Vickrey|SEK"
+    );
+
+    // Round trip
+    assert_eq!(Command::from_str(&line).unwrap(), add_auction);
+
+    // Titles with separators, escapes and line breaks stay on a single line and round trip
+    let mut auction = sample_timed_asc_auction();
+    auction.title = "Chairs | tables \\ lamps\nand more".to_string();
+    let add_auction = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction,
+    };
+    let line = add_auction.to_string();
+    assert!(!line.contains('\n'));
+    assert_eq!(Command::from_str(&line).unwrap(), add_auction);
+}
+
+#[test]
+fn test_add_auction_with_description_and_image() {
+    let mut auction = sample_vickrey_auction();
+    auction.description = Some("Oak, seats four".to_string());
+    auction.image_url = Some("https://example.com/table.jpg".to_string());
+    let add_auction = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction,
+    };
+
+    let line = add_auction.to_string();
+    assert!(line.ends_with("|SEK|Oak, seats four|https://example.com/table.jpg"));
+    assert_eq!(Command::from_str(&line).unwrap(), add_auction);
+
+    let json = to_string(&add_auction).unwrap();
+    assert!(json.contains("\"imageUrl\":\"https://example.com/table.jpg\""));
+    assert_eq!(from_str::<Command>(&json).unwrap(), add_auction);
+
+    // Commands written before the fields existed still read
+    let json = to_string(&Command::AddAuction { timestamp: sample_starts_at(), auction: sample_vickrey_auction() }).unwrap();
+    assert!(!json.contains("description"));
+    match from_str::<Command>(&json).unwrap() {
+        Command::AddAuction { auction, .. } => {
+            assert_eq!(auction.description, None);
+            assert_eq!(auction.image_url, None);
+        },
+        other => panic!("Expected AddAuction, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_place_bid_command_line_form() {
+    let place_bid = Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid: bid_1(),
+    };
+
+    // The name and the command time differ from what the short form implies, so they follow as flags
+    let line = place_bid.to_string_in(Currency::SEK);
+    assert_eq!(line, "PlaceBid|1|Buyer_1|SEK10|2016-01-01T08:28:01Z|Name=Buyer 1|At=2016-01-15T08:28:00Z");
+    assert_eq!(Command::parse_in(&line, Currency::SEK).unwrap(), place_bid);
+    assert_eq!(Command::parse_in("PlaceBid|1|Buyer_1|10|2016-01-01T08:28:01Z|Name=Buyer 1|At=2016-01-15T08:28:00Z", Currency::SEK).unwrap(), place_bid);
+
+    // Without a currency the amount is in the virtual currency
+    let line = place_bid.to_string();
+    assert_eq!(line, "PlaceBid|1|Buyer_1|VAC10|2016-01-01T08:28:01Z|Name=Buyer 1|At=2016-01-15T08:28:00Z");
+    assert_eq!(Command::from_str(&line).unwrap(), place_bid);
+
+    // Malformed lines are rejected
+    assert!(Command::from_str("PlaceBid|1|Buyer_1").is_err());
+    assert!(Command::from_str("PlaceBid|1|Buyer_1|SEK10|not a time").is_err());
+    assert!(Command::from_str("PlaceBid|1|Buyer_1|ten|2016-01-15T08:28:00Z").is_err());
+    assert!(Command::from_str("PlaceBid|1|Buyer_1|SEK10|2016-01-15T08:28:00Z|Retracted").is_err());
+}
+
+#[test]
+fn test_place_bid_command_compact_form() {
+    let line = "PlaceBid|1|Buyer_1|SEK10|2016-01-15T08:28:00Z";
+    let command = Command::from_str(line).unwrap();
+    assert_eq!(command, Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid: Bid {
+            bid_id: None,
+            for_auction: 1,
+            bidder: User::BuyerOrSeller { user_id: "Buyer_1".to_string(), name: "Buyer_1".to_string() },
+            at: sample_bid_time(),
+            bid_amount: 10,
+            placed_by: None,
+        },
+    });
+    assert_eq!(command.to_string_in(Currency::SEK), line);
+
+    // Ids, support bidders and names with separators round trip as flags
+    let command = Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid: Bid {
+            bid_id: Some(BidId::from_u64_pair(1, 2)),
+            bidder: User::BuyerOrSeller { user_id: "Buyer_1".to_string(), name: "A|B".to_string() },
+            ..bid_1()
+        },
+    };
+    let line = command.to_string_in(Currency::SEK);
+    assert!(line.contains("|Name=A\\|B|"), "{}", line);
+    assert_eq!(Command::parse_in(&line, Currency::SEK).unwrap(), command);
+    let command = Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid: Bid { bidder: User::Support { user_id: "Support_1".to_string() }, at: sample_bid_time(), ..bid_1() },
+    };
+    assert_eq!(command.to_string_in(Currency::SEK), "PlaceBid|1|Support_1|SEK10|2016-01-15T08:28:00Z|Support");
+    assert_eq!(Command::parse_in(&command.to_string_in(Currency::SEK), Currency::SEK).unwrap(), command);
+}
+
+#[test]
+fn test_place_bid_on_behalf_command_line_form() {
+    let mut bid = bid_1();
+    bid.placed_by = Some("Support_1".to_string());
+    let place_bid = Command::PlaceBid {
+        timestamp: sample_bid_time(),
+        bid,
+    };
+
+    let line = place_bid.to_string();
+    assert!(line.ends_with("|PlacedBy=Support_1"));
+    assert_eq!(Command::from_str(&line).unwrap(), place_bid);
+}
+
+#[test]
+fn test_close_auction_command_forms() {
+    let close = Command::CloseAuction {
+        timestamp: sample_bid_time(),
+        auction_id: sample_auction_id(),
+        user: sample_seller(),
+    };
+
+    let line = close.to_string();
+    assert_eq!(line, "CloseAuction|2016-01-15T08:28:00Z|1|BuyerOrSeller\\|Sample_Seller\\|Seller");
+    assert_eq!(Command::from_str(&line).unwrap(), close);
+
+    let json = to_string(&close).unwrap();
+    assert!(json.contains("\"$type\":\"CloseAuction\""));
+    assert_eq!(from_str::<Command>(&json).unwrap(), close);
+}
+
+#[test]
+fn test_read_commands_paged() {
+    let test_file = "./test_commands_paged.jsonl";
+
+    // Five commands over two lines, pages do not have to line up with the lines
+    let bid_at = |seconds: i64| Command::PlaceBid {
+        timestamp: sample_starts_at() + Duration::seconds(seconds),
+        bid: bid_1(),
+    };
+    let add_auction = Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() };
+    let first_line = to_string(&vec![add_auction, bid_at(1), bid_at(2)]).unwrap();
+    let second_line = to_string(&vec![bid_at(3), bid_at(4)]).unwrap();
+    fs::write(test_file, format!("{}\n{}\n", first_line, second_line)).unwrap();
+    let all = read_commands(test_file).unwrap();
+    assert_eq!(all.len(), 5);
+
+    let pages: Vec<(Vec<Command>, bool)> = [0, 2, 4]
+        .into_iter()
+        .map(|offset| read_commands_paged(test_file, offset, 2).unwrap())
+        .collect();
+    let past_end = read_commands_paged(test_file, 6, 2).unwrap();
+    fs::remove_file(test_file).unwrap();
+
+    assert_eq!(pages[0], (all[0..2].to_vec(), true));
+    assert_eq!(pages[1], (all[2..4].to_vec(), true));
+    assert_eq!(pages[2], (all[4..5].to_vec(), false));
+    assert_eq!(past_end, (Vec::new(), false));
+}
@@ -0,0 +1,86 @@
+use actix_web::{web, App, HttpServer};
+use auction_site::domain::{handle, states::State, Bid, BidId, Command, Repository};
+use auction_site::persistence::snapshot::{read_snapshot, write_snapshot};
+use auction_site::web::app::{configure_app, init_app_state_from, run_until_stopped};
+use std::fs;
+use std::path::Path;
+use time::Duration;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+
+fn sample_bid() -> Bid {
+    Bid {
+        bid_id: Some(BidId::from_u128(1)),
+        ..bid_1()
+    }
+}
+
+fn sample_repository() -> Repository {
+    let add_auction = Command::AddAuction {
+        timestamp: sample_starts_at(),
+        auction: sample_vickrey_auction(),
+    };
+    let (_, repository) = handle(add_auction, Repository::new()).unwrap();
+
+    let place_bid = Command::PlaceBid {
+        timestamp: sample_bid().at,
+        bid: sample_bid(),
+    };
+    let (_, repository) = handle(place_bid, repository).unwrap();
+    repository
+}
+
+#[test]
+fn test_write_and_read_snapshot() {
+    let test_file = "./test_snapshot.json";
+    let repository = sample_repository();
+
+    let write_result = write_snapshot(test_file, &repository);
+    assert!(write_result.is_ok(), "{:?}", write_result);
+
+    let read_result = read_snapshot(test_file);
+    assert!(read_result.is_ok(), "{:?}", read_result.err());
+    let restored = read_result.unwrap();
+
+    let (auction, state) = restored.get(sample_auction_id()).unwrap();
+    assert_eq!(*auction, sample_vickrey_auction());
+    assert_eq!(restored.version(sample_auction_id()), 1);
+
+    // Sealed bids are kept in the snapshot and disclosed once the auction ends
+    let ended = state.inc(sample_ends_at() + Duration::seconds(1));
+    assert_eq!(ended.get_bids(), vec![sample_bid()]);
+
+    if Path::new(test_file).exists() {
+        fs::remove_file(test_file).unwrap();
+    }
+}
+
+#[actix_web::test]
+async fn test_shutdown_writes_snapshot() {
+    let test_file = "./test_shutdown_snapshot.json";
+    let app_state = init_app_state_from(sample_repository());
+
+    let server_state = app_state.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(server_state.clone()))
+            .configure(configure_app)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap()
+    .run();
+
+    // Stop the server the same way a SIGTERM would
+    let handle = server.handle();
+    let running = actix_web::rt::spawn(run_until_stopped(server, app_state, Some(test_file)));
+    handle.stop(true).await;
+    running.await.unwrap().unwrap();
+
+    assert!(Path::new(test_file).exists());
+    let restored = read_snapshot(test_file).unwrap();
+    assert_eq!(restored.len(), 1);
+    assert_eq!(*restored.get_auction(sample_auction_id()).unwrap(), sample_vickrey_auction());
+
+    fs::remove_file(test_file).unwrap();
+}
@@ -1,5 +1,6 @@
 use auction_site::{domain::{
-    single_sealed_bid::Options as SBOptions, states::State, timed_ascending::{self}, Auction, AuctionType, Bid, User
+    core::Errors, reverse, single_sealed_bid::Options as SBOptions, states::State, timed_ascending::{self}, Auction,
+    AuctionType, Bid, User
 }, AmountValue};
 use auction_site::money::{Amount, Currency};
 use time::{macros::datetime, Duration, OffsetDateTime};
@@ -69,10 +70,12 @@ pub fn bid_amount_1() -> AmountValue {
 
 pub fn bid_1() -> Bid {
     Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_1(),
         at: sample_starts_at() + Duration::seconds(1),
         bid_amount: bid_amount_1(),
+        placed_by: None,
     }
 }
 
@@ -82,19 +85,23 @@ pub fn bid_amount_2() -> AmountValue {
 
 pub fn bid_2() -> Bid {
     Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_2(),
         at: sample_starts_at() + Duration::seconds(2),
         bid_amount: bid_amount_2(),
+        placed_by: None,
     }
 }
 
 pub fn bid_less_than_2() -> Bid {
     Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_3(),
         at: sample_starts_at() + Duration::seconds(3),
         bid_amount: 11, // Less than bid_2
+        placed_by: None,
     }
 }
 
@@ -106,21 +113,28 @@ pub fn sample_auction_of_type(typ: AuctionType) -> Auction {
         expiry: sample_ends_at(),
         seller: sample_seller(),
         auction_currency: Currency::SEK,
+        description: None,
+        image_url: None,
         typ,
     }
 }
 
 pub fn sample_timed_asc_auction() -> Auction {
-    sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options::default_options()))
+    sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options::default_options(Currency::SEK)))
+}
+
+pub fn sample_reverse_auction() -> Auction {
+    sample_auction_of_type(AuctionType::Reverse(reverse::Options { max_price: sek(0) }))
 }
 
 pub fn sample_vickrey_auction() -> Auction {
-    sample_auction_of_type(AuctionType::SingleSealedBid(SBOptions::Vickrey))
+    sample_auction_of_type(AuctionType::SingleSealedBid(SBOptions::vickrey()))
 }
 pub fn sample_blind_auction() -> Auction {
-    sample_auction_of_type(AuctionType::SingleSealedBid(SBOptions::Blind))
+    sample_auction_of_type(AuctionType::SingleSealedBid(SBOptions::blind()))
 }
 
+#[allow(clippy::bool_assert_comparison)]
 pub fn test_increment_state<S: State + Clone + PartialEq+ std::fmt::Debug>(base_state: &S) {
     // Can increment twice
     let s = base_state.inc(sample_bid_time());
@@ -145,6 +159,7 @@ pub fn test_increment_state<S: State + Clone + PartialEq+ std::fmt::Debug>(base_
 }
 
 // Test that verifies state increment behavior
+#[allow(clippy::bool_assert_comparison)]
 pub fn test_increment_spec<T: State + Clone+ PartialEq + std::fmt::Debug>(state: &T) {
     // Can increment twice
     let s = state.inc(sample_bid_time());
@@ -167,3 +182,43 @@ pub fn test_increment_spec<T: State + Clone+ PartialEq + std::fmt::Debug>(state:
     let state = state.inc(sample_ends_at() + Duration::seconds(1));
     assert_eq!(state.has_ended(), true);
 }
+
+// Test that bids are rejected at the start instant and accepted once the start has passed, alike for every auction type
+pub fn test_start_boundary<T: State + std::fmt::Debug>(state: &T) {
+    let bid_at = |at: OffsetDateTime| Bid {
+        at,
+        ..bid_1()
+    };
+
+    let (at_start, result) = state.add_bid(bid_at(sample_starts_at()));
+    assert_eq!(result, Err(Errors::AuctionHasNotStarted(sample_auction_id())));
+    assert!(!at_start.has_started());
+
+    let (_, result) = state.add_bid(bid_at(sample_starts_at() + Duration::seconds(1)));
+    assert!(result.is_ok(), "{:?}", result);
+
+    assert!(!state.inc(sample_starts_at()).has_started());
+    assert!(state.inc(sample_starts_at() + Duration::seconds(1)).has_started());
+}
+
+// Test that bids are accepted strictly before expiry and rejected from the expiry instant
+pub fn test_expiry_boundary<T: State + std::fmt::Debug>(state: &T) {
+    let bid_at = |at: OffsetDateTime| Bid {
+        at,
+        ..bid_1()
+    };
+
+    let (_, result) = state.add_bid(bid_at(sample_ends_at() - Duration::seconds(1)));
+    assert!(result.is_ok(), "{:?}", result);
+
+    let (at_expiry, result) = state.add_bid(bid_at(sample_ends_at()));
+    assert_eq!(result, Err(Errors::AuctionHasEnded(sample_auction_id())));
+    assert!(at_expiry.has_ended());
+
+    let (_, result) = state.add_bid(bid_at(sample_ends_at() + Duration::seconds(1)));
+    assert_eq!(result, Err(Errors::AuctionHasEnded(sample_auction_id())));
+
+    // The state is ended at the expiry instant but not a moment before
+    assert!(!state.inc(sample_ends_at() - Duration::seconds(1)).has_ended());
+    assert!(state.inc(sample_ends_at()).has_ended());
+}
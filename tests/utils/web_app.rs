@@ -0,0 +1,56 @@
+// Harness for tests of the web api, boots the app in-process with configure_app.
+// Include it next to the shared sample data with
+// #[path="utils/web_app.rs"] #[macro_use] mod web_app;
+#![allow(dead_code, unused_macros)]
+use auction_site::domain::User;
+use base64::{Engine as _, engine::general_purpose};
+use serde_json::{json, Value};
+
+// Encode a user the way the API gateway forwards it in the x-jwt-payload header
+pub fn jwt_payload(user: &User) -> (&'static str, String) {
+    let payload = match user {
+        User::BuyerOrSeller { user_id, name } => json!({ "sub": user_id, "name": name, "u_typ": "0" }),
+        User::Support { user_id } => json!({ "sub": user_id, "u_typ": "1" }),
+    };
+    ("x-jwt-payload", general_purpose::STANDARD.encode(payload.to_string()))
+}
+
+pub fn support() -> User {
+    User::Support {
+        user_id: "Support_1".to_string(),
+    }
+}
+
+// Request body for an auction that is ongoing for the foreseeable future
+pub fn ongoing_auction_request(id: i64) -> Value {
+    json!({
+        "id": id,
+        "startsAt": "2016-01-01T08:28:00.000Z",
+        "endsAt": "2999-01-01T08:28:00.000Z",
+        "title": "auction"
+    })
+}
+
+// Initialize the app as a test service, optionally with a seeded state, a site config
+// and further app data such as an event bus or blocklist
+macro_rules! init_app {
+    () => {
+        init_app!(::auction_site::web::app::init_app_state())
+    };
+    ($app_state:expr) => {
+        init_app!($app_state, ::auction_site::config::SiteConfig::default())
+    };
+    ($app_state:expr, $config:expr $(, $data:expr)*) => {{
+        let config: ::auction_site::config::SiteConfig = $config;
+        ::actix_web::test::init_service(
+            ::actix_web::App::new()
+                .app_data(::actix_web::web::Data::new($app_state))
+                .app_data(::actix_web::web::Data::new(
+                    ::auction_site::web::rate_limit::RateLimiter::new(config.bid_burst, config.bid_refill_interval)
+                ))
+                .app_data(::actix_web::web::Data::new(config))
+                $(.app_data(::actix_web::web::Data::new($data)))*
+                .configure(::auction_site::web::app::configure_app)
+        ).await
+    }};
+}
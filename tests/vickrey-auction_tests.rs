@@ -1,12 +1,12 @@
 use auction_site::domain::{
     Bid, User,
-    single_sealed_bid::{Options as SBOptions, SingleSealedBidState as SBState},
-    states::State,
+    single_sealed_bid::{vickrey_price, Options as SBOptions, SingleSealedBidState as SBState},
+    states::{AuctionOutcome, State},
     AuctionState, empty_state
 };
 use time::Duration;
 use std::str::FromStr;
-#[path="utils/mod.rs"] mod utils;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
 use utils::*;
 
 #[test]
@@ -36,7 +36,7 @@ fn test_vickrey_auction_states() {
             assert_eq!(bids[0], bid_2());
             assert_eq!(bids[1], bid_1());
             assert_eq!(*expiry, sample_ends_at());
-            assert_eq!(*options, SBOptions::Vickrey);
+            assert_eq!(*options, SBOptions::vickrey());
         },
         _ => panic!("Expected DisclosingBids state"),
     }
@@ -86,17 +86,17 @@ fn test_vickrey_auction_type_serialization() {
 
     // Can deserialize Vickrey option
     let parsed_vickrey = SBOptions::from_str(vickrey_str).unwrap();
-    assert_eq!(parsed_vickrey, SBOptions::Vickrey);
+    assert_eq!(parsed_vickrey, SBOptions::vickrey());
 
     // Can deserialize Blind option
     let parsed_blind = SBOptions::from_str(blind_str).unwrap();
-    assert_eq!(parsed_blind, SBOptions::Blind);
+    assert_eq!(parsed_blind, SBOptions::blind());
 
     // Can serialize Vickrey option
-    assert_eq!(SBOptions::Vickrey.to_string(), vickrey_str);
+    assert_eq!(SBOptions::vickrey().to_string(), vickrey_str);
 
     // Can serialize Blind option
-    assert_eq!(SBOptions::Blind.to_string(), blind_str);
+    assert_eq!(SBOptions::blind().to_string(), blind_str);
 }
 
 #[test]
@@ -111,14 +111,17 @@ fn test_vickrey_auction_with_multiple_bids() {
     let (state_with_1_bid, _) = empty_vickrey_auction_state.add_bid(bid_1()); // 10
 
     let bid_highest = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: buyer_2(),
         at: sample_starts_at() + Duration::seconds(2),
         bid_amount: 20, // Highest bid
+        placed_by: None,
     };
     let (state_with_2_bids, _) = state_with_1_bid.add_bid(bid_highest);
 
     let bid_middle = Bid {
+        bid_id: None,
         for_auction: sample_auction_id(),
         bidder: User::BuyerOrSeller {
             user_id: "Buyer_3".to_string(),
@@ -126,6 +129,7 @@ fn test_vickrey_auction_with_multiple_bids() {
         },
         at: sample_starts_at() + Duration::seconds(3),
         bid_amount: 15, // Middle bid
+        placed_by: None,
     };
     let (state_with_3_bids, _) = state_with_2_bids.add_bid(bid_middle);
 
@@ -140,3 +144,68 @@ fn test_vickrey_auction_with_multiple_bids() {
     assert_eq!(amount, 15); // Second highest bid
     assert_eq!(winner, buyer_2().user_id().clone()); // Highest bidder
 }
+
+#[test]
+fn test_vickrey_auction_outcomes() {
+    let empty_vickrey_auction_state = match empty_state(&sample_vickrey_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    assert_eq!(empty_vickrey_auction_state.outcome(), AuctionOutcome::Ongoing);
+    assert_eq!(empty_vickrey_auction_state.inc(sample_ends_at()).outcome(), AuctionOutcome::NoBids);
+
+    // The highest bidder pays the second highest bid
+    let (state_with_1_bid, _) = empty_vickrey_auction_state.add_bid(bid_1());
+    let (state_with_2_bids, _) = state_with_1_bid.add_bid(bid_2());
+    assert_eq!(
+        state_with_2_bids.inc(sample_ends_at()).outcome(),
+        AuctionOutcome::Sold { price: bid_amount_1(), winner: buyer_2().user_id().clone() }
+    );
+}
+
+#[test]
+fn test_vickrey_auction_expiry_boundary() {
+    let state = match empty_state(&sample_vickrey_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    test_expiry_boundary(&state);
+}
+
+#[test]
+fn test_vickrey_auction_start_boundary() {
+    let state = match empty_state(&sample_vickrey_auction()) {
+        AuctionState::SingleSealedBid(state) => state,
+        _ => panic!("Expected SingleSealedBid state"),
+    };
+    test_start_boundary(&state);
+}
+
+#[test]
+fn test_vickrey_price() {
+    // No bids, no winner
+    assert_eq!(vickrey_price(&[]), None);
+    // A single bidder pays their own bid
+    assert_eq!(vickrey_price(&[bid(buyer_1(), 10, 1)]), Some((10, buyer_1().user_id().clone())));
+    // The highest bidder pays the second highest bid
+    assert_eq!(vickrey_price(&[bid(buyer_2(), 12, 2), bid(buyer_1(), 10, 1)]), Some((10, buyer_2().user_id().clone())));
+}
+
+#[test]
+fn test_vickrey_auction_with_tied_top_bids() {
+    let auction = sample_vickrey_auction();
+    let bids = [bid(buyer_2(), 12, 2), bid(buyer_1(), 12, 1), bid(buyer_3(), 5, 3)];
+    let state = bids.into_iter().fold(empty_state(&auction), |state, bid| {
+        let (state, result) = state.add_bid(bid);
+        assert!(result.is_ok(), "{:?}", result);
+        state
+    });
+
+    // The earlier of the tied bidders wins and pays the tied amount
+    let ended = state.inc(sample_ends_at());
+    assert_eq!(ended.try_get_amount_and_winner(), Some((12, buyer_1().user_id().clone())));
+}
+
+fn bid(bidder: User, bid_amount: i64, seconds: i64) -> Bid {
+    Bid { bidder, bid_amount, at: sample_starts_at() + Duration::seconds(seconds), ..bid_1() }
+}
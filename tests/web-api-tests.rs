@@ -0,0 +1,1922 @@
+use actix_web::test;
+use base64::{Engine as _, engine::general_purpose};
+use auction_site::config::SiteConfig;
+use auction_site::domain::{empty_state, timed_ascending, Auction, AuctionType, Bid, Command, Event, EventBus, FeeSchedule, Repository, State, User};
+use auction_site::money::{Amount, Currency};
+use auction_site::persistence::audit::{AuditLog, AuditRecord};
+use auction_site::persistence::event_log::EventLog;
+use auction_site::web::app::{init_app_state, init_app_state_from, run_sweeps};
+use auction_site::web::rate_limit::RateLimiter;
+use auction_site::web::shill::{NearMiss, ShillGuard, MAX_NEAR_MISSES};
+use auction_site::web::types::{Blocklist, Watchlist};
+use serde_json::{json, Value};
+use time::{Duration, OffsetDateTime};
+use time::macros::datetime;
+#[path="utils/mod.rs"] #[allow(dead_code)] mod utils;
+use utils::*;
+#[path="utils/web_app.rs"] #[macro_use] mod web_app;
+use web_app::*;
+
+#[actix_web::test]
+async fn test_support_can_place_bid_on_behalf_of_buyer() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&support()))
+        .set_json(json!({ "amount": 10, "onBehalfOf": "Buyer_1" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    // The bid is recorded as the buyer and audited with the acting support user
+    let event: Value = test::read_body_json(resp).await;
+    assert_eq!(event["$type"], "BidAccepted");
+    assert_eq!(event["bid"]["user"], "BuyerOrSeller|Buyer_1|Buyer_1");
+    assert_eq!(event["bid"]["placedBy"], "Support_1");
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["bids"][0]["bidder"], "BuyerOrSeller|Buyer_1|Buyer_1");
+}
+
+#[actix_web::test]
+async fn test_buyer_cannot_place_bid_on_behalf_of_another_buyer() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10, "onBehalfOf": "Buyer_2" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+
+    // No bid was stored
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["bids"], json!([]));
+}
+
+#[actix_web::test]
+async fn test_auction_detail_is_conditional_on_etag() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+    assert!(etag.starts_with("W/"));
+
+    // Nothing changed
+    let req = test::TestRequest::get().uri("/auctions/1")
+        .insert_header(("if-none-match", etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 304);
+
+    // A new bid changes the ETag
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::get().uri("/auctions/1")
+        .insert_header(("if-none-match", etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let new_etag = resp.headers().get("etag").unwrap().to_str().unwrap();
+    assert_ne!(new_etag, etag);
+}
+
+#[actix_web::test]
+async fn test_sellers_list_their_own_auctions() {
+    let app = init_app!();
+
+    let other_seller = User::BuyerOrSeller {
+        user_id: "Other_Seller".to_string(),
+        name: "Other".to_string(),
+    };
+    for (id, seller) in [(1, sample_seller()), (2, sample_seller()), (3, other_seller.clone())] {
+        let req = test::TestRequest::post().uri("/auctions")
+            .insert_header(jwt_payload(&seller))
+            .set_json(ongoing_auction_request(id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+    }
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::get().uri("/my/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<i64> = auctions.as_array().unwrap().iter().map(|a| a["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(auctions[0]["highBid"], 10);
+    assert_eq!(auctions[0]["outcome"]["$type"], "Ongoing");
+    assert_eq!(auctions[1]["highBid"], Value::Null);
+
+    let req = test::TestRequest::get().uri("/my/auctions")
+        .insert_header(jwt_payload(&other_seller))
+        .to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<i64> = auctions.as_array().unwrap().iter().map(|a| a["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![3]);
+}
+
+#[actix_web::test]
+async fn test_seller_auctions_as_of_now() {
+    // Expired auctions that no command or sweep has moved past their end
+    let english = sample_timed_asc_auction();
+    let (english_state, results) = empty_state(&english).apply_bids([bid_1(), bid_2()]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let reverse = Auction { auction_id: 2, ..sample_reverse_auction() };
+    let (reverse_state, results) = empty_state(&reverse).apply_bids([
+        Bid { for_auction: 2, bid_amount: 20, ..bid_1() },
+        Bid { for_auction: 2, bid_amount: 15, ..bid_2() },
+    ]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let mut repository = Repository::new();
+    repository.insert(english, english_state);
+    repository.insert(reverse, reverse_state);
+    let app = init_app!(init_app_state_from(repository));
+
+    let req = test::TestRequest::get().uri("/my/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(auctions[0]["outcome"]["$type"], "Sold");
+    assert_eq!(auctions[0]["highBid"], bid_amount_2());
+    // The leading bid of a reverse auction is the lowest
+    assert_eq!(auctions[1]["outcome"]["$type"], "Sold");
+    assert_eq!(auctions[1]["highBid"], 15);
+}
+
+#[actix_web::test]
+async fn test_support_must_name_the_seller() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::get().uri("/my/auctions")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = test::TestRequest::get().uri("/my/auctions?sellerId=Sample_Seller")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(auctions.as_array().unwrap().len(), 1);
+
+    // Sellers cannot look at the auctions of someone else
+    let req = test::TestRequest::get().uri("/my/auctions?sellerId=Sample_Seller")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[actix_web::test]
+async fn test_auction_shorter_than_configured_minimum_is_rejected() {
+    let app = init_app!(init_app_state(), SiteConfig {
+        min_auction_duration: Duration::days(1),
+        ..SiteConfig::default()
+    });
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({
+            "id": 1,
+            "startsAt": "2016-01-01T08:28:00.000Z",
+            "endsAt": "2016-01-01T09:28:00.000Z",
+            "title": sample_title()
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "invalid_auction_duration");
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(2))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+}
+
+#[actix_web::test]
+async fn test_rapid_bids_are_rate_limited_per_user() {
+    let app = init_app!(init_app_state(), SiteConfig {
+        bid_burst: 3,
+        bid_refill_interval: std::time::Duration::from_secs(60),
+        ..SiteConfig::default()
+    });
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let mut statuses = Vec::new();
+    for amount in 1..=5 {
+        let req = test::TestRequest::post().uri("/auctions/1/bids")
+            .insert_header(jwt_payload(&buyer_1()))
+            .set_json(json!({ "amount": amount }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        if resp.status() == 429 {
+            assert!(resp.headers().contains_key("retry-after"));
+        }
+        statuses.push(resp.status().as_u16());
+    }
+    assert_eq!(statuses, vec![200, 200, 200, 429, 429]);
+
+    // Another user still has their own budget
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    // Reading is not limited
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_auctions_ending_soon() {
+    let app = init_app!();
+
+    let soon = (time::OffsetDateTime::now_utc() + Duration::minutes(5))
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({
+            "id": 1,
+            "startsAt": "2016-01-01T08:28:00.000Z",
+            "endsAt": soon,
+            "title": sample_title()
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(2))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+
+    let req = test::TestRequest::get().uri("/auctions/ending-soon?minutes=10").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<i64> = auctions.as_array().unwrap().iter().map(|a| a["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![1]);
+
+    let req = test::TestRequest::get().uri("/auctions/ending-soon?minutes=-1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_auctions_ending_soon_after_final_round() {
+    let soon = OffsetDateTime::now_utc() + Duration::minutes(5);
+    let typ = AuctionType::TimedAscending(timed_ascending::Options {
+        final_round: Some(timed_ascending::FinalRound { duration: Duration::minutes(10), bidders: 2 }),
+        ..timed_ascending::Options::default_options(Currency::SEK)
+    });
+    let with_final_round = Auction { expiry: soon, typ: typ.clone(), ..sample_timed_asc_auction() };
+    let (with_bids, result) = empty_state(&with_final_round).add_bid(bid_1());
+    assert!(result.is_ok(), "{:?}", result);
+    let without_bids = Auction { auction_id: 2, expiry: soon, typ, ..sample_timed_asc_auction() };
+    let without_bids_state = empty_state(&without_bids);
+    let repository: Repository = [(with_final_round, with_bids), (without_bids, without_bids_state)].into_iter().collect();
+    let app = init_app!(init_app_state_from(repository));
+
+    // The bid leads auction 1 into a final round that ends in 15 minutes
+    let req = test::TestRequest::get().uri("/auctions/ending-soon?minutes=10").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<i64> = auctions.as_array().unwrap().iter().map(|a| a["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![2]);
+
+    let req = test::TestRequest::get().uri("/auctions/ending-soon?minutes=20").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<i64> = auctions.as_array().unwrap().iter().map(|a| a["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![2, 1]);
+}
+
+#[actix_web::test]
+async fn test_openapi_document() {
+    let app = init_app!();
+
+    let req = test::TestRequest::get().uri("/openapi.json").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body = test::read_body(resp).await;
+    let document: Value = serde_json::from_slice(&body).expect("Expected the document to be JSON");
+
+    assert_eq!(document["openapi"], "3.0.3");
+    let paths = &document["paths"];
+    assert!(paths["/auctions"]["get"].is_object());
+    assert!(paths["/auctions"]["post"].is_object());
+    assert!(paths["/auctions/{id}"]["get"].is_object());
+    assert!(paths["/auctions/{id}/bids"]["post"].is_object());
+    for schema in ["AddAuctionRequest", "BidRequest", "AuctionItem", "AuctionDetail"] {
+        assert!(document["components"]["schemas"][schema].is_object(), "Missing schema {}", schema);
+    }
+}
+
+#[actix_web::test]
+async fn test_accepted_bid_is_published_on_event_bus() {
+    let event_bus = EventBus::default();
+    let mut events = event_bus.subscribe();
+    let app = init_app!(init_app_state(), SiteConfig::default(), event_bus);
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert!(matches!(events.try_recv(), Ok(Event::AuctionAdded { .. })));
+
+    let req = test::TestRequest::post().uri(&format!("/auctions/{}/bids", sample_auction_id()))
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 11 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    match events.try_recv() {
+        Ok(Event::BidAccepted { bid, .. }) => {
+            assert_eq!(bid.bidder, buyer_1());
+            assert_eq!(bid.bid_amount, 11);
+        },
+        other => panic!("Expected BidAccepted, got {:?}", other),
+    }
+}
+
+#[actix_web::test]
+async fn test_seller_can_close_auction_early() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    for (buyer, amount) in [(buyer_1(), 10), (buyer_2(), 12)] {
+        let req = test::TestRequest::post().uri("/auctions/1/bids")
+            .insert_header(jwt_payload(&buyer))
+            .set_json(json!({ "amount": amount }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    // A buyer may not close the auction
+    let req = test::TestRequest::post().uri("/auctions/1/close")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "cannot_close_auction");
+
+    let req = test::TestRequest::post().uri("/auctions/1/close")
+        .insert_header(jwt_payload(&sample_seller()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let event: Value = test::read_body_json(resp).await;
+    assert_eq!(event["$type"], "AuctionClosed");
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["winner"], "Buyer_2");
+    assert_eq!(detail["winnerPrice"], "VAC12");
+    assert_eq!(detail["outcome"]["$type"], "Sold");
+    assert_eq!(detail["timeLeftSeconds"], Value::Null);
+
+    // Bids are no longer accepted
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 20 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_auction_with_invalid_title_is_rejected() {
+    let app = init_app!();
+
+    let mut request = ongoing_auction_request(sample_auction_id());
+    request["title"] = json!("line\nbreak");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "invalid_auction_title");
+}
+
+#[actix_web::test]
+async fn test_currencies_are_listed() {
+    let app = init_app!();
+
+    let req = test::TestRequest::get().uri("/currencies").to_request();
+    let currencies: Value = test::call_and_read_body_json(&app, req).await;
+    let currencies = currencies.as_array().unwrap();
+
+    assert_eq!(currencies.len(), Currency::ALL.len());
+    for currency in Currency::ALL {
+        assert!(currencies.iter().any(|item| item["code"] == currency.to_string()), "Missing {}", currency);
+    }
+    let sek = currencies.iter().find(|item| item["code"] == "SEK").unwrap();
+    assert_eq!(sek["decimals"], 2);
+    assert_eq!(sek["name"], "Swedish krona");
+}
+
+#[actix_web::test]
+async fn test_bids_are_placed_in_the_auction_currency() {
+    let app = init_app!();
+
+    let mut request = ongoing_auction_request(sample_auction_id());
+    request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["bids"][0]["amount"], "SEK10");
+
+    // Unknown auctions are not found before any bid is constructed
+    let req = test::TestRequest::post().uri("/auctions/99/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "unknown_auction");
+}
+
+#[actix_web::test]
+async fn test_suspended_users_cannot_place_bids() {
+    let blocklist = Blocklist::default();
+    blocklist.lock().unwrap().insert(buyer_2().user_id().clone());
+    let app = init_app!(init_app_state(), SiteConfig::default(), blocklist);
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "bidder_suspended");
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_support_can_suspend_bidders() {
+    let app = init_app!(init_app_state(), SiteConfig::default(), Blocklist::default());
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let suspend_uri = format!("/admin/suspended-bidders/{}", buyer_2().user_id());
+    let req = test::TestRequest::put().uri(&suspend_uri)
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::put().uri(&suspend_uri)
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let status: Value = test::read_body_json(resp).await;
+    assert_eq!(status, json!({ "userId": buyer_2().user_id(), "suspended": true }));
+
+    let bid = || test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, bid()).await;
+    assert_eq!(resp.status(), 403);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "bidder_suspended");
+
+    let req = test::TestRequest::delete().uri(&suspend_uri)
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert_eq!(test::call_service(&app, bid()).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_stats_require_authentication() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/stats").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let req = test::TestRequest::get().uri("/stats")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let stats: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stats["totalAuctions"], 1);
+    assert_eq!(stats["byType"]["English"], 1);
+    assert_eq!(stats["totalBids"], 0);
+}
+
+#[actix_web::test]
+async fn test_auction_description_and_image() {
+    let app = init_app!();
+
+    let mut request = ongoing_auction_request(1);
+    request["description"] = json!("Oak, seats four");
+    request["imageUrl"] = json!("https://example.com/table.jpg");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(2))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["description"], "Oak, seats four");
+    assert_eq!(detail["imageUrl"], "https://example.com/table.jpg");
+
+    let req = test::TestRequest::get().uri("/auctions/2").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["description"], Value::Null);
+    assert_eq!(detail["imageUrl"], Value::Null);
+
+    // The list stays lightweight
+    let req = test::TestRequest::get().uri("/auctions").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(auctions[0].get("description").is_none());
+
+    for image_url in ["ftp://example.com/table.jpg", "https://", "table.jpg", "https://example.com/a table.jpg"] {
+        let mut request = ongoing_auction_request(3);
+        request["imageUrl"] = json!(image_url);
+        let req = test::TestRequest::post().uri("/auctions")
+            .insert_header(jwt_payload(&sample_seller()))
+            .set_json(request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{}", image_url);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["code"], "invalid_image_url");
+    }
+}
+
+#[actix_web::test]
+async fn test_harness_with_seeded_state() {
+    // Seed the state with an auction and add another through the api
+    let seeded = sample_timed_asc_auction();
+    let mut repository = Repository::new();
+    repository.insert(seeded.clone(), empty_state(&seeded));
+    let app = init_app!(init_app_state_from(repository));
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(2))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/2").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["id"], 2);
+    assert_eq!(detail["title"], "auction");
+    assert_eq!(detail["hasStarted"], true);
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", seeded.auction_id)).to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["title"], seeded.title);
+    assert_eq!(detail["currency"], "SEK");
+}
+
+#[actix_web::test]
+async fn test_bid_in_major_units() {
+    let app = init_app!();
+
+    let mut request = ongoing_auction_request(sample_auction_id());
+    request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10.501 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": "10.50" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["bids"][0]["amount"], "SEK1050");
+}
+
+fn auction_hiding_bidders(expiry: OffsetDateTime) -> Repository {
+    let auction = Auction {
+        expiry,
+        ..sample_auction_of_type(AuctionType::TimedAscending(timed_ascending::Options {
+            hide_bidders: true,
+            ..timed_ascending::Options::default_options(Currency::SEK)
+        }))
+    };
+    let raise = Bid { at: sample_starts_at() + Duration::seconds(3), bid_amount: 14, ..bid_1() };
+    let (state, results) = empty_state(&auction).apply_bids([bid_1(), bid_2(), raise]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let mut repository = Repository::new();
+    repository.insert(auction, state);
+    repository
+}
+
+#[actix_web::test]
+async fn test_bidders_are_anonymized_while_ongoing() {
+    let app = init_app!(init_app_state_from(auction_hiding_bidders(OffsetDateTime::now_utc() + Duration::days(1))));
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", sample_auction_id())).to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    let bidders: Vec<(&str, &str)> = detail["bids"].as_array().unwrap().iter()
+        .map(|bid| (bid["bidder"].as_str().unwrap(), bid["amount"].as_str().unwrap()))
+        .collect();
+    // Tokens follow the order of the first bid and stay the same for a bidder
+    assert_eq!(bidders.len(), 3);
+    assert!(bidders.contains(&("Bidder #1", "SEK10")));
+    assert!(bidders.contains(&("Bidder #2", "SEK12")));
+    assert!(bidders.contains(&("Bidder #1", "SEK14")));
+}
+
+#[actix_web::test]
+async fn test_bidders_are_revealed_once_ended() {
+    let app = init_app!(init_app_state_from(auction_hiding_bidders(sample_ends_at())));
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", sample_auction_id())).to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    let bidders: Vec<&str> = detail["bids"].as_array().unwrap().iter()
+        .map(|bid| bid["bidder"].as_str().unwrap())
+        .collect();
+    assert!(bidders.contains(&"BuyerOrSeller|Buyer_1|Buyer 1"));
+    assert!(bidders.contains(&"BuyerOrSeller|Buyer_2|Buyer 2"));
+}
+
+fn auctions_of_two_sellers() -> Repository {
+    let other_seller = User::BuyerOrSeller { user_id: "Other_Seller".to_string(), name: "Other".to_string() };
+    let ongoing = OffsetDateTime::now_utc() + Duration::days(1);
+    let auctions = [
+        Auction { auction_id: 3, expiry: ongoing, ..sample_timed_asc_auction() },
+        Auction { auction_id: 1, ..sample_timed_asc_auction() },
+        Auction { auction_id: 2, expiry: ongoing, seller: other_seller, ..sample_timed_asc_auction() },
+        Auction { auction_id: 4, expiry: ongoing, ..sample_vickrey_auction() },
+    ];
+    auctions.into_iter()
+        .map(|auction| {
+            let state = empty_state(&auction);
+            (auction, state)
+        })
+        .collect()
+}
+
+async fn auction_ids(uri: &str) -> Vec<i64> {
+    let app = init_app!(init_app_state_from(auctions_of_two_sellers()));
+    let req = test::TestRequest::get().uri(uri).to_request();
+    let items: Value = test::call_and_read_body_json(&app, req).await;
+    items.as_array().unwrap().iter().map(|item| item["id"].as_i64().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn test_get_auctions_by_seller() {
+    assert_eq!(auction_ids("/auctions").await, vec![1, 2, 3, 4]);
+    assert_eq!(auction_ids("/auctions?seller=Sample_Seller").await, vec![1, 3, 4]);
+    assert_eq!(auction_ids("/auctions?seller=Other_Seller").await, vec![2]);
+    assert!(auction_ids("/auctions?seller=Nobody").await.is_empty());
+}
+
+#[actix_web::test]
+async fn test_get_auctions_by_seller_and_status() {
+    assert_eq!(auction_ids("/auctions?seller=Sample_Seller&status=Ongoing").await, vec![3, 4]);
+    assert_eq!(auction_ids("/auctions?seller=Sample_Seller&status=Ended").await, vec![1]);
+    assert!(auction_ids("/auctions?seller=Other_Seller&status=Ended").await.is_empty());
+}
+
+#[actix_web::test]
+async fn test_dry_run_bid_is_not_placed() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids?dryRun=true")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let preview: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(preview["wouldAccept"], true);
+    assert_eq!(preview["resultingHighBid"], "VAC10");
+    assert_eq!(preview["newExpiry"], "2999-01-01T08:28:00Z");
+    assert!(preview.get("error").is_none());
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["bids"], json!([]));
+}
+
+#[actix_web::test]
+async fn test_dry_run_bid_reports_error() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids?dryRun=true")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 5 }))
+        .to_request();
+    let preview: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(preview["wouldAccept"], false);
+    assert_eq!(preview["resultingHighBid"], "VAC10");
+    assert_eq!(preview["error"]["code"], "must_place_bid_over_highest_bid");
+}
+
+#[actix_web::test]
+async fn test_commands_are_audited() {
+    let audit_file = "./test_audit_log.jsonl";
+    let _ = std::fs::remove_file(audit_file);
+    let app = init_app!(init_app_state(), SiteConfig::default(), AuditLog::new(audit_file));
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .peer_addr("10.0.0.7:4711".parse().unwrap())
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Rejected commands are audited as well
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({ "amount": 12 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let content = std::fs::read_to_string(audit_file).unwrap();
+    std::fs::remove_file(audit_file).unwrap();
+    let records: Vec<AuditRecord> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 3);
+
+    assert_eq!(records[0].actor, "Sample_Seller");
+    assert!(matches!(records[0].command, Command::AddAuction { .. }));
+
+    assert_eq!(records[1].actor, "Buyer_1");
+    assert_eq!(records[1].source_ip.as_deref(), Some("10.0.0.7"));
+    assert_eq!(records[1].result, Ok(()));
+    match &records[1].command {
+        Command::PlaceBid { bid, .. } => {
+            assert_eq!(bid.bidder, buyer_1());
+            assert_eq!(bid.bid_amount, 10);
+        },
+        other => panic!("Expected PlaceBid, got {:?}", other),
+    }
+
+    assert_eq!(records[2].actor, "Sample_Seller");
+    assert!(records[2].result.is_err());
+}
+
+#[actix_web::test]
+async fn test_sold_auction_shows_commission() {
+    let auction = sample_timed_asc_auction();
+    let winning_bid = Bid { bid_amount: 10000, ..bid_2() };
+    let (state, _) = empty_state(&auction).apply_bids([bid_1(), winning_bid]);
+    let mut repository = Repository::new();
+    repository.insert(auction.clone(), state.inc(sample_ends_at()));
+    let config = SiteConfig { fee_schedule: FeeSchedule::flat(250).unwrap(), ..SiteConfig::default() };
+    let app = init_app!(init_app_state_from(repository), config);
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", auction.auction_id)).to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["winnerPrice"], "SEK10000");
+    assert_eq!(detail["commission"], "SEK250");
+    assert_eq!(detail["netToSeller"], "SEK9750");
+}
+
+#[actix_web::test]
+async fn test_unsold_auction_has_no_commission() {
+    let app = init_app!(init_app_state_from(auction_hiding_bidders(OffsetDateTime::now_utc() + Duration::days(1))));
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", sample_auction_id())).to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["commission"], Value::Null);
+    assert_eq!(detail["netToSeller"], Value::Null);
+}
+
+#[actix_web::test]
+async fn test_bid_with_expected_version() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["version"], 0);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10, "expectedVersion": 0 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // The second buyer saw the auction before the first bid
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 11, "expectedVersion": 0 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 409);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "version_conflict");
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["version"], 1);
+    assert_eq!(detail["bids"].as_array().unwrap().len(), 1);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 11, "expectedVersion": 1 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_buyer_bids_across_auctions() {
+    let ongoing = OffsetDateTime::now_utc() + Duration::days(1);
+    let in_auction = |auction_id: i64, bid: Bid| Bid { for_auction: auction_id, ..bid };
+    let auctions = [
+        // Buyer 1 leads the first auction and is outbid in the second
+        (Auction { auction_id: 1, expiry: ongoing, ..sample_timed_asc_auction() }, vec![in_auction(1, bid_1())]),
+        (Auction { auction_id: 2, expiry: ongoing, ..sample_timed_asc_auction() }, vec![in_auction(2, bid_1()), in_auction(2, bid_2())]),
+        (Auction { auction_id: 3, expiry: ongoing, ..sample_blind_auction() }, vec![in_auction(3, bid_1()), in_auction(3, bid_2())]),
+        (Auction { auction_id: 4, ..sample_timed_asc_auction() }, vec![in_auction(4, bid_2())]),
+    ];
+    let mut repository = Repository::new();
+    for (auction, bids) in auctions {
+        let (state, results) = empty_state(&auction).apply_bids(bids);
+        assert!(results.iter().all(Result::is_ok), "{:?}", results);
+        repository.insert(auction, state);
+    }
+    let app = init_app!(init_app_state_from(repository));
+
+    let req = test::TestRequest::get().uri("/my/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    let bids: Value = test::call_and_read_body_json(&app, req).await;
+    let statuses: Vec<(i64, &str, &str)> = bids.as_array().unwrap().iter()
+        .map(|bid| (bid["auctionId"].as_i64().unwrap(), bid["amount"].as_str().unwrap(), bid["status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(statuses, vec![(1, "SEK10", "Winning"), (2, "SEK10", "Outbid"), (3, "SEK10", "Sealed")]);
+
+    // Only the own sealed bid is listed, the ended auction was won
+    let req = test::TestRequest::get().uri("/my/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .to_request();
+    let bids: Value = test::call_and_read_body_json(&app, req).await;
+    let statuses: Vec<(i64, &str)> = bids.as_array().unwrap().iter()
+        .map(|bid| (bid["auctionId"].as_i64().unwrap(), bid["status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(statuses, vec![(2, "Winning"), (3, "Sealed"), (4, "Won")]);
+
+    let req = test::TestRequest::get().uri("/my/bids").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_requests_are_served_after_a_panic_holding_the_lock() {
+    let app_state = init_app_state();
+    let app = init_app!(app_state.clone());
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let poisoning = app_state.clone();
+    let panicked = std::thread::spawn(move || {
+        let _guard = poisoning.lock().unwrap();
+        panic!("Panic while holding the lock");
+    }).join();
+    assert!(panicked.is_err());
+    assert!(app_state.is_poisoned());
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert!(!app_state.is_poisoned());
+}
+
+#[actix_web::test]
+async fn test_streamed_listing_matches_buffered_listing() {
+    let repository: Repository = (1..=350)
+        .map(|auction_id| {
+            let auction = Auction { auction_id, ..sample_timed_asc_auction() };
+            let state = empty_state(&auction);
+            (auction, state)
+        })
+        .collect();
+    let app = init_app!(init_app_state_from(repository));
+
+    for query in ["", "?seller=Sample_Seller", "?seller=Nobody"] {
+        let req = test::TestRequest::get().uri(&format!("/auctions{}", query)).to_request();
+        let buffered: Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri(&format!("/auctions/stream{}", query)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/json");
+        let streamed: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+
+        assert_eq!(streamed, buffered);
+    }
+}
+
+#[actix_web::test]
+async fn test_local_times_in_requested_time_zone() {
+    let summer_auction = Auction {
+        starts_at: datetime!(2016-07-01 10:00 UTC),
+        expiry: datetime!(2016-07-02 10:00 UTC),
+        ..sample_timed_asc_auction()
+    };
+    let mut repository = Repository::new();
+    repository.insert(summer_auction.clone(), empty_state(&summer_auction));
+    let app = init_app!(init_app_state_from(repository));
+
+    // Stockholm is two hours ahead of UTC in summer, the canonical fields stay in UTC
+    let req = test::TestRequest::get().uri("/auctions/1?tz=Europe/Stockholm").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["startsAt"], "2016-07-01T10:00:00Z");
+    assert_eq!(detail["localStartsAt"], "2016-07-01T12:00:00+02:00");
+    assert_eq!(detail["localExpiry"], "2016-07-02T12:00:00+02:00");
+
+    let req = test::TestRequest::get().uri("/auctions?tz=Europe/Stockholm").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(auctions[0]["localStartsAt"], "2016-07-01T12:00:00+02:00");
+
+    // Without tz there are no local times
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(detail.get("localStartsAt").is_none());
+
+    let req = test::TestRequest::get().uri("/auctions/1?tz=Europe/Atlantis").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_secret_reserve_is_redacted_in_responses() {
+    let app = init_app!();
+
+    let mut auction_request = ongoing_auction_request(sample_auction_id());
+    auction_request["type"] = json!("English|SEK100|SEK5|0|ReserveSecret");
+    auction_request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+    let event: Value = test::read_body_json(resp).await;
+    assert_eq!(event["auction"]["type"], "English|*|5|0|ReserveSecret");
+}
+
+#[actix_web::test]
+async fn test_seller_auction_limit() {
+    let config = SiteConfig { max_active_auctions_per_seller: Some(1), ..SiteConfig::default() };
+    let app = init_app!(init_app_state(), config);
+
+    let create = |id: i64| test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(id))
+        .to_request();
+    assert_eq!(test::call_service(&app, create(1)).await.status(), 200);
+
+    // At the limit further auctions are rejected, other sellers are not affected
+    let resp = test::call_service(&app, create(2)).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "seller_auction_limit_reached");
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(ongoing_auction_request(3))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Once an auction has ended the seller can create another
+    let req = test::TestRequest::post().uri("/auctions/1/close")
+        .insert_header(jwt_payload(&sample_seller()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert_eq!(test::call_service(&app, create(2)).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_auction_items_show_bid_count_and_current_price() {
+    let ongoing = OffsetDateTime::now_utc() + Duration::days(1);
+    let english = Auction { expiry: ongoing, ..sample_timed_asc_auction() };
+    let (english_state, results) = empty_state(&english).apply_bids([bid_1(), bid_2()]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let blind = Auction { auction_id: 2, expiry: ongoing, ..sample_blind_auction() };
+    let (blind_state, result) = empty_state(&blind).add_bid(Bid { for_auction: 2, ..bid_1() });
+    assert!(result.is_ok(), "{:?}", result);
+
+    // Expired but not swept yet
+    let ended_blind = Auction { auction_id: 3, ..sample_blind_auction() };
+    let (ended_blind_state, result) = empty_state(&ended_blind).add_bid(Bid { for_auction: 3, ..bid_1() });
+    assert!(result.is_ok(), "{:?}", result);
+
+    let repository: Repository = [(english, english_state), (blind, blind_state), (ended_blind, ended_blind_state)].into_iter().collect();
+    let app = init_app!(init_app_state_from(repository));
+
+    let req = test::TestRequest::get().uri("/auctions").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(auctions[0]["bidCount"], 2);
+    assert_eq!(auctions[0]["currentPrice"], "SEK12");
+    // The sealed bid is neither counted nor priced until disclosed
+    assert_eq!(auctions[1]["bidCount"], 0);
+    assert_eq!(auctions[1]["currentPrice"], Value::Null);
+    // Disclosed as of now once the auction has ended
+    assert_eq!(auctions[2]["bidCount"], 1);
+    assert_eq!(auctions[2]["currentPrice"], format!("SEK{}", bid_amount_1()));
+}
+
+#[actix_web::test]
+async fn test_unauthorized_responses_give_the_reason() {
+    let app = init_app!();
+
+    let request = |header: Option<String>| {
+        let mut req = test::TestRequest::post().uri("/auctions")
+            .set_json(ongoing_auction_request(sample_auction_id()));
+        if let Some(value) = header {
+            req = req.insert_header(("x-jwt-payload", value));
+        }
+        req.to_request()
+    };
+
+    let cases = vec![
+        (None, "missing_token"),
+        (Some(String::new()), "missing_token"),
+        (Some("not base64!".to_string()), "bad_base64"),
+        (Some(general_purpose::STANDARD.encode("{not json")), "bad_json"),
+        (Some(general_purpose::STANDARD.encode(json!({ "name": "Seller", "u_typ": "0" }).to_string())), "missing_claim"),
+    ];
+    for (header, reason) in cases {
+        let resp = test::call_service(&app, request(header)).await;
+        assert_eq!(resp.status(), 401);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], reason);
+    }
+
+    // The happy path is unchanged
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_bid_response_includes_position() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result["$type"], "BidAccepted");
+    assert_eq!(result["accepted"], true);
+    assert_eq!(result["newHighBid"], "VAC10");
+    assert!(result["newExpiry"].is_string());
+    assert_eq!(result["yourPosition"], "leading");
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .set_json(json!({ "amount": 20 }))
+        .to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result["newHighBid"], "VAC20");
+    assert_eq!(result["yourPosition"], "leading");
+
+    // A bid too low to be accepted tells the first bidder that they have been outbid
+    let req = test::TestRequest::post().uri("/auctions/1/bids?dryRun=true")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 15 }))
+        .to_request();
+    let preview: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(preview["wouldAccept"], false);
+    assert_eq!(preview["yourPosition"], "outbid");
+}
+
+#[actix_web::test]
+async fn test_misspelled_request_fields_are_rejected() {
+    let app = init_app!();
+
+    let mut auction_request = ongoing_auction_request(sample_auction_id());
+    auction_request["titel"] = json!("auction");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert!(error["message"].as_str().unwrap().contains("unknown field `titel`"), "{}", error);
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "ammount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert!(error["message"].as_str().unwrap().contains("unknown field `ammount`"), "{}", error);
+}
+
+#[actix_web::test]
+async fn test_verify_after_restoring_a_snapshot() {
+    let audit_file = "./test_verify_restored_audit_log.jsonl";
+    let _ = std::fs::remove_file(audit_file);
+    // The auction was added before the audit log was kept, only the snapshot has it
+    let restored = {
+        let mut repository = Repository::new();
+        let auction = Auction {
+            starts_at: OffsetDateTime::now_utc() - Duration::hours(1),
+            expiry: OffsetDateTime::now_utc() + Duration::hours(1),
+            ..sample_timed_asc_auction()
+        };
+        let state = empty_state(&auction);
+        repository.insert(auction, state);
+        repository
+    };
+    let audit_log = AuditLog::new(audit_file).starting_from(restored.clone()).unwrap();
+    let app = init_app!(init_app_state_from(restored), SiteConfig::default(), audit_log);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/admin/verify")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report, json!({ "consistent": true, "discrepancies": [] }));
+
+    std::fs::remove_file(audit_file).unwrap();
+}
+
+#[actix_web::test]
+async fn test_verify_reports_drift_from_the_audit_log() {
+    let audit_file = "./test_verify_audit_log.jsonl";
+    let _ = std::fs::remove_file(audit_file);
+    let app_state = init_app_state();
+    let app = init_app!(app_state.clone(), SiteConfig::default(), AuditLog::new(audit_file));
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/admin/verify")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let verify = || test::TestRequest::get().uri("/admin/verify")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, verify()).await;
+    assert_eq!(report, json!({ "consistent": true, "discrepancies": [] }));
+
+    // Drop the bid from the live state only
+    {
+        let mut repository = app_state.lock().unwrap();
+        let auction = repository.get_auction(sample_auction_id()).unwrap().clone();
+        let state = empty_state(&auction);
+        repository.insert(auction, state);
+    }
+
+    let report: Value = test::call_and_read_body_json(&app, verify()).await;
+    std::fs::remove_file(audit_file).unwrap();
+    assert_eq!(report["consistent"], false);
+    assert_eq!(report["discrepancies"], json!([
+        { "kind": "BidCount", "auctionId": sample_auction_id(), "live": 0, "replayed": 1 }
+    ]));
+}
+
+#[actix_web::test]
+async fn test_replay_to_leaves_out_later_records() {
+    let audit_file = "./test_replay_to_audit_log.jsonl";
+    let _ = std::fs::remove_file(audit_file);
+    let audit_log = AuditLog::new(audit_file);
+    assert_eq!(audit_log.position().unwrap(), 0);
+    let record = |command| AuditRecord {
+        at: sample_starts_at(),
+        actor: sample_seller().user_id().clone(),
+        source_ip: None,
+        command,
+        result: Ok(()),
+    };
+    audit_log.append(&record(Command::AddAuction { timestamp: sample_starts_at(), auction: sample_timed_asc_auction() })).unwrap();
+    let position = audit_log.position().unwrap();
+    audit_log.append(&record(Command::PlaceBid { timestamp: bid_1().at, bid: bid_1() })).unwrap();
+
+    let as_of_position = audit_log.replay_to(position).unwrap();
+    let replayed = audit_log.replay().unwrap();
+    std::fs::remove_file(audit_file).unwrap();
+    assert_eq!(as_of_position.get_state(sample_auction_id()).unwrap().get_bids().len(), 0);
+    assert_eq!(replayed.get_state(sample_auction_id()).unwrap().get_bids().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_watching_auctions() {
+    let app = init_app!(init_app_state(), SiteConfig::default(), Watchlist::default());
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let watch = |user: &User| test::TestRequest::post().uri("/auctions/1/watch")
+        .insert_header(jwt_payload(user))
+        .to_request();
+    let detail_request = || test::TestRequest::get().uri("/auctions/1").to_request();
+
+    let status: Value = test::call_and_read_body_json(&app, watch(&buyer_1())).await;
+    assert_eq!(status, json!({ "watching": true, "watcherCount": 1 }));
+    // Watching again counts the user once
+    let status: Value = test::call_and_read_body_json(&app, watch(&buyer_1())).await;
+    assert_eq!(status["watcherCount"], 1);
+    let status: Value = test::call_and_read_body_json(&app, watch(&buyer_2())).await;
+    assert_eq!(status["watcherCount"], 2);
+
+    let detail: Value = test::call_and_read_body_json(&app, detail_request()).await;
+    assert_eq!(detail["watcherCount"], 2);
+
+    let req = test::TestRequest::delete().uri("/auctions/1/watch")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    let status: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status, json!({ "watching": false, "watcherCount": 1 }));
+
+    let detail: Value = test::call_and_read_body_json(&app, detail_request()).await;
+    assert_eq!(detail["watcherCount"], 1);
+
+    let req = test::TestRequest::post().uri("/auctions/2/watch")
+        .insert_header(jwt_payload(&buyer_1()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    let req = test::TestRequest::post().uri("/auctions/1/watch").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_currency_allowlist() {
+    // Added before the allowlist was configured
+    let virtual_auction = Auction {
+        auction_id: 5,
+        expiry: OffsetDateTime::now_utc() + Duration::days(1),
+        auction_currency: Currency::VAC,
+        typ: AuctionType::TimedAscending(timed_ascending::Options::default_options(Currency::VAC)),
+        ..sample_timed_asc_auction()
+    };
+    let mut repository = Repository::new();
+    repository.insert(virtual_auction.clone(), empty_state(&virtual_auction));
+    let config = SiteConfig { allowed_currencies: Some(vec![Currency::SEK, Currency::DKK]), ..SiteConfig::default() };
+    let app = init_app!(init_app_state_from(repository), config);
+
+    // The default currency VAC is not allowed
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "currency_not_allowed");
+
+    let mut auction_request = ongoing_auction_request(sample_auction_id());
+    auction_request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/5/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "currency_not_allowed");
+
+    let req = test::TestRequest::get().uri("/currencies").to_request();
+    let currencies: Value = test::call_and_read_body_json(&app, req).await;
+    let codes: Vec<&str> = currencies.as_array().unwrap().iter().map(|c| c["code"].as_str().unwrap()).collect();
+    assert_eq!(codes, vec!["SEK", "DKK"]);
+}
+
+#[actix_web::test]
+async fn test_bid_currency_must_match_the_auction() {
+    let app = init_app!();
+
+    let mut auction_request = ongoing_auction_request(sample_auction_id());
+    auction_request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let bid = |user: &User, body: Value| test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(user))
+        .set_json(body)
+        .to_request();
+
+    // Without a currency the amount is in the auction currency
+    let result: Value = test::call_and_read_body_json(&app, bid(&buyer_1(), json!({ "amount": 10 }))).await;
+    assert_eq!(result["newHighBid"], "SEK10");
+    let result: Value = test::call_and_read_body_json(&app, bid(&buyer_2(), json!({ "amount": 20, "currency": "SEK" }))).await;
+    assert_eq!(result["newHighBid"], "SEK20");
+
+    let resp = test::call_service(&app, bid(&buyer_1(), json!({ "amount": 30, "currency": "DKK" }))).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "bid_currency_mismatch");
+}
+
+#[actix_web::test]
+async fn test_auction_request_can_be_validated_without_creating_it() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions/validate")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+    let validation: Value = test::read_body_json(resp).await;
+    assert_eq!(validation, json!({ "valid": true, "errors": [] }));
+
+    // Nothing is stored
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}", sample_auction_id())).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+
+    let req = test::TestRequest::post().uri("/auctions/validate")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({
+            "id": sample_auction_id(),
+            "startsAt": "2016-01-01T08:28:00.000Z",
+            "endsAt": "2015-01-01T08:28:00.000Z",
+            "title": " ",
+            "currency": "SEK",
+            "type": "English|VAC0|VAC0|0"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.status());
+    let validation: Value = test::read_body_json(resp).await;
+    assert_eq!(validation["valid"], false);
+    let codes: Vec<&str> = validation["errors"].as_array().unwrap().iter()
+        .map(|error| error["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, vec!["invalid_auction_title", "invalid_auction_duration", "auction_currency_mismatch"]);
+}
+
+#[actix_web::test]
+async fn test_auction_winner() {
+    // The sample auction has ended with a bid, the second is still open
+    let ended = sample_timed_asc_auction();
+    let (state, _) = empty_state(&ended).add_bid(bid_1());
+    let mut repository = Repository::new();
+    repository.insert(ended.clone(), state);
+    let app = init_app!(init_app_state_from(repository));
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(2))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri(&format!("/auctions/{}/winner", ended.auction_id)).to_request();
+    let winner: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(winner, json!({
+        "ended": true,
+        "winner": buyer_1().user_id(),
+        "price": format!("SEK{}", bid_1().bid_amount),
+        "outcome": "sold"
+    }));
+
+    let req = test::TestRequest::get().uri("/auctions/2/winner").to_request();
+    let winner: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(winner, json!({ "ended": false, "winner": null, "price": null, "outcome": "ongoing" }));
+
+    let req = test::TestRequest::get().uri("/auctions/999/winner").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_min_raise_guard() {
+    let auction_request = |id: i64, typ: &str| {
+        let mut request = ongoing_auction_request(id);
+        request["type"] = json!(typ);
+        request
+    };
+    let config = SiteConfig { max_min_raise_bps: Some(5000), ..SiteConfig::default() };
+    let app = init_app!(init_app_state(), config);
+
+    // Half of the reserve price is the largest min raise
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request(1, "English|VAC1000|VAC500|0"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request(2, "English|VAC1000|VAC100000|0"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "min_raise_too_large");
+
+    // Without the guard any min raise is accepted
+    let app = init_app!();
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(auction_request(2, "English|VAC1000|VAC100000|0"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_events_are_logged() {
+    let event_file = "./test_event_log.jsonl";
+    let _ = std::fs::remove_file(event_file);
+    let app = init_app!(init_app_state(), SiteConfig::default(), EventLog::new(event_file));
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Only the produced events are logged, not the rejected bid
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({ "amount": 12 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let events = EventLog::new(event_file).read_events().unwrap();
+    assert!(matches!(events.as_slice(), [Event::AuctionAdded { .. }, Event::BidAccepted { .. }]), "{:?}", events);
+    std::fs::remove_file(event_file).unwrap();
+}
+
+#[actix_web::test]
+async fn test_error_messages_follow_accept_language() {
+    let app = init_app!();
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&sample_seller()))
+        .insert_header(("Accept-Language", "sv-SE,sv;q=0.9"))
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "seller_cannot_place_bids");
+    assert!(error["message"].as_str().unwrap().starts_with("Säljaren kan inte lägga bud"), "{}", error);
+
+    // Unknown languages get the English message
+    let req = test::TestRequest::get().uri("/auctions/999/winner")
+        .insert_header(("Accept-Language", "fr"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["message"], "Unknown auction: 999");
+}
+
+#[actix_web::test]
+async fn test_bids_from_the_sellers_address_are_rejected() {
+    let config = SiteConfig { block_shill_bids: true, ..SiteConfig::default() };
+    let app = init_app!(init_app_state(), config, ShillGuard::new());
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .peer_addr("10.0.0.7:4711".parse().unwrap())
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .peer_addr("10.0.0.7:5012".parse().unwrap())
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "suspected_shill_bid");
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .peer_addr("10.0.0.8:5012".parse().unwrap())
+        .set_json(json!({ "amount": 10 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_2()))
+        .peer_addr("192.168.1.2:5012".parse().unwrap())
+        .set_json(json!({ "amount": 12 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/admin/near-misses")
+        .insert_header(jwt_payload(&support()))
+        .to_request();
+    let near_misses: Value = test::call_and_read_body_json(&app, req).await;
+    let near_misses = near_misses.as_array().unwrap();
+    assert_eq!(near_misses.len(), 1);
+    assert_eq!(near_misses[0]["bidder"], buyer_1().user_id().as_str());
+    assert_eq!(near_misses[0]["sourceIp"], "10.0.0.8");
+    assert_eq!(near_misses[0]["sellerIp"], "10.0.0.7");
+}
+
+#[actix_web::test]
+async fn test_near_misses_are_capped() {
+    let shill_guard = ShillGuard::new();
+    for i in 0..MAX_NEAR_MISSES + 1 {
+        shill_guard.record_near_miss(NearMiss {
+            auction_id: i as i64,
+            bidder: buyer_1().user_id().clone(),
+            source_ip: "10.0.0.8".to_string(),
+            seller_ip: "10.0.0.7".to_string(),
+            at: sample_bid_time(),
+        });
+    }
+    let near_misses = shill_guard.near_misses();
+    assert_eq!(near_misses.len(), MAX_NEAR_MISSES);
+    // The oldest is dropped
+    assert_eq!(near_misses[0].auction_id, 1);
+    assert_eq!(near_misses[MAX_NEAR_MISSES - 1].auction_id, MAX_NEAR_MISSES as i64);
+}
+
+#[actix_web::test]
+async fn test_rate_limiter_drops_idle_buckets() {
+    let rate_limiter = RateLimiter::new(2, std::time::Duration::from_millis(10));
+    let start = std::time::Instant::now();
+    assert!(rate_limiter.check(buyer_1().user_id(), start).is_ok());
+    assert!(rate_limiter.check(buyer_2().user_id(), start).is_ok());
+    assert_eq!(rate_limiter.tracked_users(), 2);
+
+    // Both buckets have refilled, only the user bidding again is kept
+    let later = start + std::time::Duration::from_millis(30);
+    assert!(rate_limiter.check(buyer_1().user_id(), later).is_ok());
+    assert_eq!(rate_limiter.tracked_users(), 1);
+
+    // A dropped bucket starts full again
+    assert!(rate_limiter.check(buyer_2().user_id(), later).is_ok());
+    assert!(rate_limiter.check(buyer_2().user_id(), later).is_ok());
+    assert!(rate_limiter.check(buyer_2().user_id(), later).is_err());
+}
+
+#[actix_web::test]
+async fn test_auctions_listing_projected_on_fields() {
+    let app = init_app!();
+
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(ongoing_auction_request(sample_auction_id()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions?fields=id,currentPrice").to_request();
+    let auctions: Value = test::call_and_read_body_json(&app, req).await;
+    let item = auctions[0].as_object().unwrap();
+    let mut keys: Vec<&str> = item.keys().map(String::as_str).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["currentPrice", "id"]);
+    assert_eq!(item["id"], sample_auction_id());
+
+    let req = test::TestRequest::get().uri("/auctions?fields=id,price").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    let message = error["message"].as_str().unwrap();
+    assert!(message.contains("price"), "{}", message);
+    assert!(message.contains("currentPrice"), "{}", message);
+}
+
+#[actix_web::test]
+async fn test_amounts_above_the_currency_ceiling_are_rejected() {
+    let config = SiteConfig { max_amounts: vec![Amount::new(Currency::SEK, 1_000_000)], ..SiteConfig::default() };
+    let app = init_app!(init_app_state(), config);
+
+    let mut request = ongoing_auction_request(2);
+    request["currency"] = json!("SEK");
+    request["type"] = json!("English|SEK2000000|SEK0|0");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "amount_above_ceiling");
+
+    let mut request = ongoing_auction_request(sample_auction_id());
+    request["currency"] = json!("SEK");
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": "99999999999999" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], "amount_above_ceiling");
+    assert!(error["message"].as_str().unwrap().contains("SEK1000000"), "{}", error["message"]);
+
+    let req = test::TestRequest::post().uri("/auctions/1/bids")
+        .insert_header(jwt_payload(&buyer_1()))
+        .set_json(json!({ "amount": 1_000_000 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_auction_detail_of_expired_auction_without_later_commands() {
+    // The stored state is as of the last bid, the auction has expired since
+    let auction = sample_timed_asc_auction();
+    let (state, results) = empty_state(&auction).apply_bids([bid_1(), bid_2()]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let mut repository = Repository::new();
+    repository.insert(auction, state);
+    let app = init_app!(init_app_state_from(repository));
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let detail: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(detail["outcome"]["$type"], "Sold");
+    assert_eq!(detail["winner"], buyer_2().user_id().as_str());
+    assert_eq!(detail["winnerPrice"], format!("SEK{}", bid_amount_2()));
+    assert_eq!(detail["timeLeftSeconds"], Value::Null);
+    assert_eq!(detail["lastBidTime"], bid_2().at.format(&time::format_description::well_known::Rfc3339).unwrap());
+}
+
+#[actix_web::test]
+async fn test_auction_detail_etag_changes_when_the_auction_ends() {
+    let app = init_app!();
+
+    let ends_at = (OffsetDateTime::now_utc() + Duration::milliseconds(500))
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let req = test::TestRequest::post().uri("/auctions")
+        .insert_header(jwt_payload(&sample_seller()))
+        .set_json(json!({ "id": 1, "startsAt": "2016-01-01T08:28:00.000Z", "endsAt": ends_at, "title": "auction" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get().uri("/auctions/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+
+    // No command reaches the auction as it ends
+    std::thread::sleep(std::time::Duration::from_millis(600));
+
+    let req = test::TestRequest::get().uri("/auctions/1")
+        .insert_header(("if-none-match", etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_ne!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+}
+
+#[actix_web::test]
+async fn test_sweeps_publish_started_auctions() {
+    let auction = sample_timed_asc_auction();
+    let mut repository = Repository::new();
+    repository.insert(auction.clone(), empty_state(&auction));
+    let app_state = init_app_state_from(repository);
+    let event_bus = EventBus::default();
+    let mut events = event_bus.subscribe();
+
+    let sweeps = actix_web::rt::spawn(run_sweeps(
+        app_state.clone(),
+        std::time::Duration::from_millis(10),
+        Some(actix_web::web::Data::new(event_bus.clone())),
+        None,
+        None,
+    ));
+    let event = actix_web::rt::time::timeout(std::time::Duration::from_secs(5), events.recv()).await;
+    sweeps.abort();
+
+    assert!(matches!(event, Ok(Ok(Event::AuctionStarted { auction_id, .. })) if auction_id == auction.auction_id), "{:?}", event);
+    assert!(app_state.lock().unwrap().is_start_reported(auction.auction_id));
+}
+
+#[actix_web::test]
+async fn test_sweeps_publish_ended_auctions() {
+    // The auction has expired with a winning bid and no command since
+    let auction = sample_timed_asc_auction();
+    let (state, results) = empty_state(&auction).apply_bids([bid_1(), bid_2()]);
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let mut repository = Repository::new();
+    repository.insert(auction.clone(), state);
+    repository.mark_start_reported(auction.auction_id);
+    let app_state = init_app_state_from(repository);
+    let event_bus = EventBus::default();
+    let mut events = event_bus.subscribe();
+    let shill_guard = actix_web::web::Data::new(ShillGuard::new());
+    shill_guard.record_seller_ip(auction.auction_id, "10.0.0.7".to_string());
+
+    let sweeps = actix_web::rt::spawn(run_sweeps(
+        app_state.clone(),
+        std::time::Duration::from_millis(10),
+        Some(actix_web::web::Data::new(event_bus.clone())),
+        None,
+        Some(shill_guard.clone()),
+    ));
+    let event = actix_web::rt::time::timeout(std::time::Duration::from_secs(5), events.recv()).await;
+    sweeps.abort();
+
+    match event {
+        Ok(Ok(Event::AuctionEnded { auction_id, winner, .. })) => {
+            assert_eq!(auction_id, auction.auction_id);
+            assert_eq!(winner.as_ref(), Some(buyer_2().user_id()));
+        },
+        other => panic!("Expected AuctionEnded, got {:?}", other),
+    }
+    assert!(app_state.lock().unwrap().get_state(auction.auction_id).unwrap().has_ended());
+    // The address of the ended auction is forgotten
+    assert_eq!(shill_guard.check(&bid_1(), Some("10.0.0.7")), Ok(None));
+}